@@ -0,0 +1,468 @@
+#![allow(unused_qualifications)]
+
+use async_compression::futures::bufread::GzipDecoder;
+use futures::{
+	future::Either, io::BufReader, AsyncBufReadExt, FutureExt, Stream, StreamExt, TryStreamExt
+};
+use http::{Method, StatusCode};
+use recycle::VecExt;
+use rusoto_s3::{GetObjectRequest, Object, S3Client};
+use serde::{Deserialize, Serialize};
+use serde_closure::FnMutNamed;
+use std::{convert::identity, slice, time::Duration};
+
+use amadeus_core::{
+	into_par_stream::IteratorExt, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
+};
+use amadeus_types::{Data, DateTime, IpAddr, Url};
+
+use super::{
+	http_serde, list, parse_lines, get_object, AwsCredentials, AwsError, AwsRegion, AwsRetry, Ref, RUSOTO_DISPATCHER
+};
+
+/// The access logs of Application Load Balancers or Classic Load Balancers, as delivered to an S3
+/// bucket.
+///
+/// Gzipped objects, as Application Load Balancers write, are decompressed. Classic Load
+/// Balancer logs are parsed into the same [`AlbRow`], with the fields they lack set to `None`.
+/// Lines that can't be parsed are returned as [`AwsError::MalformedLine`], unless
+/// [`skip_malformed`](Alb::skip_malformed) is set.
+#[derive(Clone, Debug)]
+pub struct Alb {
+	region: AwsRegion,
+	bucket: String,
+	objects: Vec<String>,
+	credentials: AwsCredentials,
+	skip_malformed: bool,
+	retry: AwsRetry,
+}
+impl Alb {
+	pub async fn new(region: AwsRegion, bucket: &str, prefix: &str) -> Result<Self, AwsError> {
+		Self::new_with(region, bucket, prefix, AwsCredentials::Environment).await
+	}
+	pub async fn new_with(
+		region: AwsRegion, bucket: &str, prefix: &str, credentials: AwsCredentials,
+	) -> Result<Self, AwsError> {
+		let retry = AwsRetry::default();
+		let (bucket, prefix) = (bucket.to_owned(), prefix.to_owned());
+		let client = S3Client::new_with(
+			Ref(&*RUSOTO_DISPATCHER),
+			credentials.clone(),
+			region.clone(),
+		);
+
+		let objects = list(&client, &retry, &bucket, &prefix)
+			.await?
+			.map(|object: Object| object.key.unwrap());
+
+		Ok(Self {
+			region,
+			bucket,
+			objects,
+			credentials,
+			skip_malformed: false,
+			retry,
+		})
+	}
+	/// How failed requests to get the objects are retried. Listing them when constructed is
+	/// retried per [`AwsRetry::default()`].
+	pub fn retry(mut self, retry: AwsRetry) -> Self {
+		self.retry = retry;
+		self
+	}
+	/// Skip lines that can't be parsed, logging a warning for each, rather than returning an
+	/// error.
+	pub fn skip_malformed(mut self, skip_malformed: bool) -> Self {
+		self.skip_malformed = skip_malformed;
+		self
+	}
+}
+
+#[cfg(not(nightly))]
+type Output = std::pin::Pin<Box<dyn Stream<Item = Result<AlbRow, AwsError>> + Send>>;
+#[cfg(nightly)]
+type Output = impl Stream<Item = Result<AlbRow, AwsError>> + Send;
+
+FnMutNamed! {
+	pub type Closure<> = |self, credentials: AwsCredentials, region: AwsRegion, bucket: String, skip_malformed: bool, retry: AwsRetry|key=> String| -> Output where {
+		let (credentials, region, bucket, skip_malformed, retry) = (
+			self.credentials.clone(),
+			self.region.clone(),
+			self.bucket.clone(),
+			self.skip_malformed,
+			self.retry,
+		);
+		#[allow(clippy::let_and_return)]
+		let ret = async move {
+			let client = S3Client::new_with(
+				Ref(once_cell::sync::Lazy::force(&RUSOTO_DISPATCHER)),
+				credentials,
+				region,
+			);
+			let request = GetObjectRequest {
+				bucket,
+				key: key.clone(),
+				..GetObjectRequest::default()
+			};
+			let rows = get_object(&client, &retry, request)
+				.await
+				.map_err(AwsError::from)
+				.map(|body| {
+					let body = BufReader::new(TryStreamExt::into_async_read(body));
+					// Content-Encoding isn't set, so decode by extension
+					let body = if key.ends_with(".gz") {
+						let mut body = GzipDecoder::new(body);
+						body.multiple_members(true);
+						Either::Left(BufReader::new(body))
+					} else {
+						Either::Right(body)
+					};
+					parse_lines(body.lines(), key, skip_malformed, |line| {
+						if !line.trim().is_empty() {
+							Some(AlbRow::from_line(line))
+						} else {
+							None
+						}
+					})
+				});
+			ResultExpandIter::new(rows)
+		}
+		.flatten_stream()
+		.map(|x: Result<Result<AlbRow, _>, _>| x.and_then(identity));
+		#[cfg(not(nightly))]
+		let ret = ret.boxed();
+		ret
+	}
+}
+
+impl Source for Alb {
+	type Item = AlbRow;
+	type Error = AwsError;
+
+	type ParStream = DistParStream<Self::DistStream>;
+	#[cfg(not(nightly))]
+	#[allow(clippy::type_complexity)]
+	type DistStream = amadeus_core::par_stream::FlatMap<
+		amadeus_core::into_par_stream::PartitionsDistStream<std::vec::IntoIter<String>>,
+		Closure,
+	>;
+	#[cfg(nightly)]
+	type DistStream = impl DistributedStream<Item = Result<Self::Item, Self::Error>>;
+
+	fn par_stream(self) -> Self::ParStream {
+		DistParStream::new(self.dist_stream())
+	}
+	#[allow(clippy::let_and_return)]
+	fn dist_stream(self) -> Self::DistStream {
+		let Self {
+			bucket,
+			region,
+			objects,
+			credentials,
+			skip_malformed,
+			retry,
+		} = self;
+		objects.into_iter().dist_partitions().flat_map(Closure::new(
+			credentials,
+			region,
+			bucket,
+			skip_malformed,
+			retry,
+		))
+	}
+}
+
+/// A request to a load balancer, as logged in its access logs.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+pub struct AlbRow {
+	/// The type of request or connection, such as `http`, `https`, `h2` or `ws`. `None` for
+	/// Classic Load Balancers.
+	pub kind: Option<String>,
+	pub time: DateTime,
+	pub load_balancer: String,
+	pub client_ip: IpAddr,
+	pub client_port: u16,
+	/// The target the request was routed to, `None` if it couldn't be routed.
+	pub target_ip: Option<IpAddr>,
+	pub target_port: Option<u16>,
+	pub request_processing_time: Option<Duration>,
+	pub target_processing_time: Option<Duration>,
+	pub response_processing_time: Option<Duration>,
+	#[serde(with = "http_serde")]
+	pub elb_status: Option<StatusCode>,
+	#[serde(with = "http_serde")]
+	pub target_status: Option<StatusCode>,
+	pub received_bytes: u64,
+	pub sent_bytes: u64,
+	/// The method, URL and HTTP version of the request. `None` for TCP and TLS listeners.
+	#[serde(with = "http_serde")]
+	pub method: Option<Method>,
+	pub url: Option<Url>,
+	pub http_version: Option<String>,
+	pub user_agent: Option<String>,
+	pub ssl_protocol_cipher: Option<(String, String)>,
+	pub target_group_arn: Option<String>,
+	pub trace_id: Option<String>,
+	/// The SNI domain provided by the client in the TLS handshake.
+	pub domain_name: Option<String>,
+	pub chosen_cert_arn: Option<String>,
+	pub matched_rule_priority: Option<u32>,
+	pub request_creation_time: Option<DateTime>,
+	pub actions_executed: Vec<String>,
+	pub redirect_url: Option<String>,
+	pub error_reason: Option<String>,
+}
+impl Data for AlbRow {
+	type Vec = Vec<Self>;
+	type DynamicType = ();
+
+	fn new_vec(_type: Self::DynamicType) -> Self::Vec {
+		Vec::new()
+	}
+}
+impl AlbRow {
+	fn from_line(line: &str) -> Result<Self, String> {
+		let values = split(line)?;
+		// Application Load Balancer entries start with the type, Classic ones with the time
+		let (kind, values) = match values.split_first() {
+			Some((kind, values)) if !kind.starts_with(|c: char| c.is_ascii_digit()) => {
+				(Some(kind.clone()), values)
+			}
+			_ => (None, &*values),
+		};
+		let mut values = Values(values.iter());
+		let time = parse_time(values.next("time")?)?;
+		let load_balancer = values.next("elb")?.to_owned();
+		let (client_ip, client_port) =
+			address(values.next("client:port")?)?.ok_or("missing client:port")?;
+		let target = address(values.next("target:port")?)?;
+		let request_processing_time = duration(values.next("request_processing_time")?)?;
+		let target_processing_time = duration(values.next("target_processing_time")?)?;
+		let response_processing_time = duration(values.next("response_processing_time")?)?;
+		let elb_status = status(values.next("elb_status_code")?)?;
+		let target_status = status(values.next("target_status_code")?)?;
+		let received_bytes = parse(values.next("received_bytes")?)?;
+		let sent_bytes = parse(values.next("sent_bytes")?)?;
+		let mut request = values.next("request")?.splitn(3, ' ').map(optional);
+		let method = request
+			.next()
+			.flatten()
+			.map(|method| {
+				method
+					.parse::<Method>()
+					.map_err(|_| format!("invalid method {:?}", method))
+			})
+			.transpose()?;
+		let url = request
+			.next()
+			.flatten()
+			.map(|url| Url::parse(url).map_err(|err| format!("invalid url {:?}: {}", url, err)))
+			.transpose()?;
+		let http_version = request.next().flatten().map(str::to_owned);
+		let user_agent = optional(values.next("user_agent")?).map(str::to_owned);
+		let ssl_cipher = values.next("ssl_cipher")?;
+		let ssl_protocol = values.next("ssl_protocol")?;
+		let ssl_protocol_cipher = if let ("-", "-") = (ssl_protocol, ssl_cipher) {
+			None
+		} else {
+			Some((ssl_protocol.to_owned(), ssl_cipher.to_owned()))
+		};
+		// Only written by Application Load Balancers, which have added fields over time and may
+		// add more, so missing fields are allowed and unknown ones ignored.
+		let target_group_arn = values.optional().map(str::to_owned);
+		let trace_id = values.optional().map(str::to_owned);
+		let domain_name = values.optional().map(str::to_owned);
+		let chosen_cert_arn = values.optional().map(str::to_owned);
+		let matched_rule_priority = values.optional().map(parse).transpose()?;
+		let request_creation_time = values.optional().map(parse_time).transpose()?;
+		let actions_executed = values
+			.optional()
+			.map(|actions| actions.split(',').map(str::to_owned).collect())
+			.unwrap_or_default();
+		let redirect_url = values.optional().map(str::to_owned);
+		let error_reason = values.optional().map(str::to_owned);
+		Ok(AlbRow {
+			kind,
+			time,
+			load_balancer,
+			client_ip,
+			client_port,
+			target_ip: target.map(|(ip, _)| ip),
+			target_port: target.map(|(_, port)| port),
+			request_processing_time,
+			target_processing_time,
+			response_processing_time,
+			elb_status,
+			target_status,
+			received_bytes,
+			sent_bytes,
+			method,
+			url,
+			http_version,
+			user_agent,
+			ssl_protocol_cipher,
+			target_group_arn,
+			trace_id,
+			domain_name,
+			chosen_cert_arn,
+			matched_rule_priority,
+			request_creation_time,
+			actions_executed,
+			redirect_url,
+			error_reason,
+		})
+	}
+}
+
+struct Values<'a>(slice::Iter<'a, String>);
+impl<'a> Values<'a> {
+	fn next(&mut self, name: &str) -> Result<&'a str, String> {
+		self.0
+			.next()
+			.map(String::as_str)
+			.ok_or_else(|| format!("missing {}", name))
+	}
+	fn optional(&mut self) -> Option<&'a str> {
+		self.0.next().map(String::as_str).and_then(optional)
+	}
+}
+
+/// Split a line on spaces, other than within double quotes, which are removed.
+fn split(line: &str) -> Result<Vec<String>, String> {
+	let mut values = Vec::new();
+	let mut chars = line.trim().chars();
+	while let Some(c) = chars.next() {
+		let mut value = String::new();
+		if c == '"' {
+			loop {
+				match chars.next() {
+					Some('"') => break,
+					Some('\\') => value.push(chars.next().ok_or("unterminated escape")?),
+					Some(c) => value.push(c),
+					None => return Err("unterminated quote".to_owned()),
+				}
+			}
+			match chars.next() {
+				Some(' ') | None => (),
+				Some(c) => return Err(format!("unexpected {:?} after closing quote", c)),
+			}
+		} else {
+			value.push(c);
+			value.extend(chars.by_ref().take_while(|&c| c != ' '));
+		}
+		values.push(value);
+	}
+	Ok(values)
+}
+
+fn optional(value: &str) -> Option<&str> {
+	let value = value.trim();
+	if value != "-" && !value.is_empty() {
+		Some(value)
+	} else {
+		None
+	}
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+	value
+		.parse()
+		.map_err(|_| format!("couldn't parse {:?}", value))
+}
+
+fn parse_time(value: &str) -> Result<DateTime, String> {
+	chrono::DateTime::parse_from_rfc3339(value)
+		.map(|time| DateTime::from_chrono(&time))
+		.map_err(|err| format!("invalid time {:?}: {}", value, err))
+}
+
+/// An `ip:port` pair, with IPv6 addresses optionally in brackets.
+fn address(value: &str) -> Result<Option<(IpAddr, u16)>, String> {
+	optional(value)
+		.map(|value| {
+			let i = value
+				.rfind(':')
+				.ok_or_else(|| format!("invalid address {:?}", value))?;
+			let ip = value[..i].trim_start_matches('[').trim_end_matches(']');
+			Ok((parse(ip)?, parse(&value[i + 1..])?))
+		})
+		.transpose()
+}
+
+/// A duration in seconds, which is -1 if the request didn't get that far.
+fn duration(value: &str) -> Result<Option<Duration>, String> {
+	let seconds = parse::<f64>(value)?;
+	Ok(if seconds >= 0.0 {
+		Some(Duration::from_secs_f64(seconds))
+	} else {
+		None
+	})
+}
+
+/// A status code, which Classic Load Balancers log as 0 if there was no response.
+fn status(value: &str) -> Result<Option<StatusCode>, String> {
+	optional(value)
+		.filter(|&status| status != "0")
+		.map(|status| {
+			StatusCode::from_bytes(status.as_bytes())
+				.map_err(|_| format!("invalid status code {:?}", status))
+		})
+		.transpose()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn application() {
+		let row = AlbRow::from_line(r#"https 2018-07-02T22:23:00.186641Z app/my-loadbalancer/50dc6c495c0c9188 192.168.131.39:2817 10.0.0.1:80 0.086 0.048 0.037 200 200 0 57 "GET https://www.example.com:443/ HTTP/1.1" "curl/7.46.0" ECDHE-RSA-AES128-GCM-SHA256 TLSv1.2 arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337281-1d84f3d73c47ec4e58577259" "www.example.com" "arn:aws:acm:us-east-2:123456789012:certificate/12345678-1234-1234-1234-123456789012" 1 2018-07-02T22:22:48.364000Z "authenticate,forward" "-" "-" "10.0.0.1:80" "200" "-" "-""#).unwrap();
+		assert_eq!(row.kind.as_deref(), Some("https"));
+		assert_eq!(row.client_port, 2817);
+		assert_eq!(row.target_port, Some(80));
+		assert_eq!(row.target_processing_time, Some(Duration::from_millis(48)));
+		assert_eq!(row.elb_status, Some(StatusCode::OK));
+		assert_eq!(row.method, Some(Method::GET));
+		assert_eq!(row.url.unwrap().as_str(), "https://www.example.com/");
+		assert_eq!(row.http_version.as_deref(), Some("HTTP/1.1"));
+		assert_eq!(
+			row.ssl_protocol_cipher,
+			Some((
+				"TLSv1.2".to_owned(),
+				"ECDHE-RSA-AES128-GCM-SHA256".to_owned()
+			))
+		);
+		assert_eq!(row.domain_name.as_deref(), Some("www.example.com"));
+		assert_eq!(row.matched_rule_priority, Some(1));
+		assert_eq!(row.actions_executed, ["authenticate", "forward"]);
+		assert_eq!(row.redirect_url, None);
+	}
+
+	#[test]
+	fn classic() {
+		let row = AlbRow::from_line(r#"2015-05-13T23:39:43.945958Z my-loadbalancer 192.168.131.39:2817 - -1 -1 -1 503 0 0 0 "GET http://www.example.com:80/ HTTP/1.1" "curl/7.38.0" - -"#).unwrap();
+		assert_eq!(row.kind, None);
+		assert_eq!(row.load_balancer, "my-loadbalancer");
+		assert_eq!(row.target_ip, None);
+		assert_eq!(row.request_processing_time, None);
+		assert_eq!(row.elb_status, Some(StatusCode::SERVICE_UNAVAILABLE));
+		assert_eq!(row.target_status, None);
+		assert_eq!(row.ssl_protocol_cipher, None);
+		assert_eq!(row.target_group_arn, None);
+
+		let row = AlbRow::from_line(r#"2015-05-13T23:39:43.945958Z my-loadbalancer 192.168.131.39:2817 10.0.0.1:80 0.001069 0.000028 0.000041 - - 82 305 "- - - " "-" - -"#).unwrap();
+		assert_eq!(row.method, None);
+		assert_eq!(row.url, None);
+		assert_eq!(row.elb_status, None);
+		assert_eq!(row.received_bytes, 82);
+	}
+
+	#[test]
+	fn malformed() {
+		assert!(AlbRow::from_line("https 2018-07-02T22:23:00.186641Z").is_err());
+		assert!(AlbRow::from_line(
+			r#"2015-05-13T23:39:43.945958Z my-loadbalancer 192.168.131.39:2817 - -1 -1 -1 503 0 0 0 "GET"#
+		)
+		.is_err());
+	}
+}
@@ -2,29 +2,36 @@
 
 use async_compression::futures::bufread::GzipDecoder;
 use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
-use futures::{future, io::BufReader, AsyncBufReadExt, FutureExt, Stream, StreamExt, TryStreamExt};
+use futures::{io::BufReader, AsyncBufReadExt, FutureExt, Stream, StreamExt, TryStreamExt};
 use http::{Method, StatusCode};
 use recycle::VecExt;
-use rusoto_s3::{GetObjectRequest, Object, S3Client, S3};
+use rusoto_s3::{GetObjectRequest, Object, S3Client};
 use serde::{Deserialize, Serialize};
 use serde_closure::FnMutNamed;
-use std::{
-	convert::identity, io::{self}, time::Duration
-};
+use std::{convert::identity, time::Duration};
 
 use amadeus_core::{
-	into_par_stream::IntoDistributedStream, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
+	into_par_stream::IteratorExt, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
 };
 use amadeus_types::{Data, DateTime, IpAddr, Url};
 
-use super::{list, retry, AwsCredentials, AwsError, AwsRegion, Ref, RUSOTO_DISPATCHER};
+use super::{
+	columns::Columns, http_serde, list, parse_lines, get_object, AwsCredentials, AwsError, AwsRegion, AwsRetry, Ref, RUSOTO_DISPATCHER
+};
 
+/// The standard access logs of a Cloudfront distribution, as delivered to an S3 bucket.
+///
+/// Columns are mapped using each file's `#Fields:` header, so files written before or after
+/// Cloudfront added fields can be read. Lines that can't be parsed are returned as
+/// [`AwsError::MalformedLine`], unless [`skip_malformed`](Cloudfront::skip_malformed) is set.
 #[derive(Clone, Debug)]
 pub struct Cloudfront {
 	region: AwsRegion,
 	bucket: String,
 	objects: Vec<String>,
 	credentials: AwsCredentials,
+	skip_malformed: bool,
+	retry: AwsRetry,
 }
 impl Cloudfront {
 	pub async fn new(region: AwsRegion, bucket: &str, prefix: &str) -> Result<Self, AwsError> {
@@ -33,6 +40,7 @@ impl Cloudfront {
 	pub async fn new_with(
 		region: AwsRegion, bucket: &str, prefix: &str, credentials: AwsCredentials,
 	) -> Result<Self, AwsError> {
+		let retry = AwsRetry::default();
 		let (bucket, prefix) = (bucket.to_owned(), prefix.to_owned());
 		let client = S3Client::new_with(
 			Ref(&*RUSOTO_DISPATCHER),
@@ -40,7 +48,7 @@ impl Cloudfront {
 			region.clone(),
 		);
 
-		let objects = list(&client, &bucket, &prefix)
+		let objects = list(&client, &retry, &bucket, &prefix)
 			.await?
 			.map(|object: Object| object.key.unwrap());
 
@@ -49,8 +57,22 @@ impl Cloudfront {
 			bucket,
 			objects,
 			credentials,
+			skip_malformed: false,
+			retry,
 		})
 	}
+	/// How failed requests to get the objects are retried. Listing them when constructed is
+	/// retried per [`AwsRetry::default()`].
+	pub fn retry(mut self, retry: AwsRetry) -> Self {
+		self.retry = retry;
+		self
+	}
+	/// Skip lines that can't be parsed, logging a warning for each, rather than returning an
+	/// error.
+	pub fn skip_malformed(mut self, skip_malformed: bool) -> Self {
+		self.skip_malformed = skip_malformed;
+		self
+	}
 }
 
 #[cfg(not(nightly))]
@@ -59,9 +81,14 @@ type Output = std::pin::Pin<Box<dyn Stream<Item = Result<CloudfrontRow, AwsError
 type Output = impl Stream<Item = Result<CloudfrontRow, AwsError>> + Send;
 
 FnMutNamed! {
-	pub type Closure<> = |self, credentials: AwsCredentials, region: AwsRegion, bucket: String|key=> String| -> Output where {
-		let (credentials, region, bucket) =
-			(self.credentials.clone(), self.region.clone(), self.bucket.clone());
+	pub type Closure<> = |self, credentials: AwsCredentials, region: AwsRegion, bucket: String, skip_malformed: bool, retry: AwsRetry|key=> String| -> Output where {
+		let (credentials, region, bucket, skip_malformed, retry) = (
+			self.credentials.clone(),
+			self.region.clone(),
+			self.bucket.clone(),
+			self.skip_malformed,
+			self.retry,
+		);
 		#[allow(clippy::let_and_return)]
 		let ret = async move {
 			let client = S3Client::new_with(
@@ -69,36 +96,31 @@ FnMutNamed! {
 				credentials,
 				region,
 			);
-			let rows = retry(|| {
-				client.get_object(GetObjectRequest {
-					bucket: bucket.clone(),
-					key: key.clone(),
-					..GetObjectRequest::default()
-				})
-			})
-			.await
-			.map_err(AwsError::from)
-			.map(|res| {
-				let body = BufReader::new(TryStreamExt::into_async_read(res.body.unwrap()));
-				let mut body = GzipDecoder::new(body); // Content-Encoding isn't set, so decode manually
-				body.multiple_members(true);
-				BufReader::new(body)
-					.lines()
-					.filter(|x: &Result<String, io::Error>| {
-						future::ready(if let Ok(x) = x {
-							x.chars().find(|x| !x.is_whitespace()) != Some('#')
-						} else {
-							true
-						})
-					})
-					.then(|x: Result<String, io::Error>| async {
-						if let Ok(x) = x {
-							Ok(CloudfrontRow::from_line(&x))
+			let request = GetObjectRequest {
+				bucket,
+				key: key.clone(),
+				..GetObjectRequest::default()
+			};
+			let rows = get_object(&client, &retry, request)
+				.await
+				.map_err(AwsError::from)
+				.map(|body| {
+					let body = BufReader::new(TryStreamExt::into_async_read(body));
+					let mut body = GzipDecoder::new(body); // Content-Encoding isn't set, so decode manually
+					body.multiple_members(true);
+					let mut columns = Columns::new(FIELDS.iter().copied());
+					parse_lines(BufReader::new(body).lines(), key, skip_malformed, move |line| {
+						let line = line.trim_start();
+						if line.starts_with("#Fields:") {
+							columns = header(line);
+							None
+						} else if line.starts_with('#') || line.trim().is_empty() {
+							None
 						} else {
-							Err(AwsError::from(x.err().unwrap()))
+							Some(CloudfrontRow::from_line(&columns, line))
 						}
 					})
-			});
+				});
 			ResultExpandIter::new(rows)
 		}
 		.flatten_stream()
@@ -117,7 +139,7 @@ impl Source for Cloudfront {
 	#[cfg(not(nightly))]
 	#[allow(clippy::type_complexity)]
 	type DistStream = amadeus_core::par_stream::FlatMap<
-		amadeus_core::into_par_stream::IterDistStream<std::vec::IntoIter<String>>,
+		amadeus_core::into_par_stream::PartitionsDistStream<std::vec::IntoIter<String>>,
 		Closure,
 	>;
 	#[cfg(nightly)]
@@ -133,10 +155,16 @@ impl Source for Cloudfront {
 			region,
 			objects,
 			credentials,
+			skip_malformed,
+			retry,
 		} = self;
-		objects
-			.into_dist_stream()
-			.flat_map(Closure::new(credentials, region, bucket))
+		objects.into_iter().dist_partitions().flat_map(Closure::new(
+			credentials,
+			region,
+			bucket,
+			skip_malformed,
+			retry,
+		))
 	}
 }
 
@@ -165,6 +193,14 @@ pub struct CloudfrontRow {
 	pub http_version: String,
 	pub fle_status: Option<String>,
 	pub fle_encrypted_fields: Option<String>,
+	// The fields below were added in December 2019, and are `None` in older logs.
+	pub remote_port: Option<u16>,
+	pub time_to_first_byte: Option<Duration>,
+	pub detailed_result_type: Option<String>,
+	pub content_type: Option<String>,
+	pub content_length: Option<u64>,
+	pub range_start: Option<u64>,
+	pub range_end: Option<u64>,
 }
 impl Data for CloudfrontRow {
 	type Vec = Vec<Self>;
@@ -175,177 +211,179 @@ impl Data for CloudfrontRow {
 	}
 }
 impl CloudfrontRow {
-	fn from_line(line: &str) -> Self {
-		let mut values = line.split('\t');
-		let date = values.next().unwrap();
-		let time = values.next().unwrap();
-		let x_edge_location = values.next().unwrap();
-		let sc_bytes = values.next().unwrap();
-		let c_ip = values.next().unwrap();
-		let cs_method = values.next().unwrap();
-		let cs_host = values.next().unwrap();
-		let cs_uri_stem = values.next().unwrap();
-		let sc_status = values.next().unwrap();
-		let cs_referer = values.next().unwrap();
-		let cs_user_agent = values.next().unwrap();
-		let cs_uri_query = values.next().unwrap();
-		let cs_cookie = values.next().unwrap();
-		let x_edge_result_type = values.next().unwrap();
-		let x_edge_request_id = values.next().unwrap();
-		let x_host_header = values.next().unwrap();
-		let cs_protocol = values.next().unwrap();
-		let cs_bytes = values.next().unwrap();
-		let time_taken = values.next().unwrap();
-		let x_forwarded_for = values.next().unwrap();
-		let ssl_protocol = values.next().unwrap();
-		let ssl_cipher = values.next().unwrap();
-		let x_edge_response_result_type = values.next().unwrap();
-		let cs_protocol_version = values.next().unwrap();
-		let fle_status = values.next().unwrap();
-		let fle_encrypted_fields = values.next().unwrap();
-		assert_eq!(values.next(), None);
-		let time = DateTime::from_chrono(&Utc.from_utc_datetime(&NaiveDateTime::new(
-			NaiveDate::parse_from_str(&date, "%Y-%m-%d").unwrap(),
-			NaiveTime::parse_from_str(&time, "%H:%M:%S").unwrap(),
-		)));
+	fn from_line(columns: &Columns, line: &str) -> Result<Self, String> {
+		let values = columns.values(line.split('\t'));
+		let date = values.required("date")?;
+		let time = values.required("time")?;
+		let time = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+			.and_then(|date| {
+				NaiveTime::parse_from_str(time, "%H:%M:%S")
+					.map(|time| NaiveDateTime::new(date, time))
+			})
+			.map_err(|err| format!("invalid date and time {:?} {:?}: {}", date, time, err))?;
+		let time = DateTime::from_chrono(&Utc.from_utc_datetime(&time));
+		let sc_status = values.required("sc-status")?;
 		let status = if sc_status != "000" {
-			Some(StatusCode::from_bytes(sc_status.as_bytes()).unwrap())
+			Some(
+				StatusCode::from_bytes(sc_status.as_bytes())
+					.map_err(|_| format!("invalid sc-status {:?}", sc_status))?,
+			)
 		} else {
 			None
 		};
-		#[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
-		let time_taken =
-			Duration::from_millis((time_taken.parse::<f64>().unwrap() * 1000.0).round() as u64);
-		CloudfrontRow {
+		let cs_uri_query = values.optional("cs-uri-query");
+		let url = format!(
+			"{}://{}{}{}{}",
+			values.required("cs-protocol")?,
+			values.required("x-host-header")?,
+			values.required("cs-uri-stem")?,
+			if cs_uri_query.is_some() { "?" } else { "" },
+			cs_uri_query.unwrap_or("")
+		);
+		let url = Url::parse(&url).map_err(|err| format!("invalid url {:?}: {}", url, err))?;
+		let ssl_protocol_cipher = values
+			.optional("ssl-protocol")
+			.zip(values.optional("ssl-cipher"))
+			.map(|(protocol, cipher)| (protocol.to_owned(), cipher.to_owned()));
+		Ok(CloudfrontRow {
 			time,
-			edge_location: x_edge_location.to_owned(),
-			response_bytes: sc_bytes.parse().unwrap(),
-			remote_ip: c_ip.parse().unwrap(),
-			method: cs_method.parse().unwrap(),
-			host: cs_host.to_owned(),
-			url: Url::parse(&format!(
-				"{}://{}{}{}{}",
-				cs_protocol,
-				x_host_header,
-				cs_uri_stem,
-				if cs_uri_query == "-" { "" } else { "?" },
-				if cs_uri_query == "-" {
-					""
-				} else {
-					&cs_uri_query
-				}
-			))
-			.unwrap(),
+			edge_location: values.required("x-edge-location")?.to_owned(),
+			response_bytes: values.parse("sc-bytes")?,
+			remote_ip: values.parse("c-ip")?,
+			method: values.parse("cs-method")?,
+			host: values.required("cs(Host)")?.to_owned(),
+			url,
 			status,
-			user_agent: if cs_user_agent != "-" {
-				Some(cs_user_agent.to_owned())
-			} else {
-				None
-			},
-			referer: if cs_referer != "-" {
-				Some(cs_referer.to_owned())
-			} else {
-				None
-			},
-			cookie: if cs_cookie != "-" {
-				Some(cs_cookie.to_owned())
-			} else {
-				None
-			},
-			result_type: x_edge_result_type.to_owned(),
-			request_id: x_edge_request_id.to_owned(),
-			request_bytes: cs_bytes.parse().unwrap(),
-			time_taken,
-			forwarded_for: if x_forwarded_for != "-" {
-				Some(x_forwarded_for.to_owned())
-			} else {
-				None
-			},
-			ssl_protocol_cipher: if let ("-", "-") = (&*ssl_protocol, &*ssl_cipher) {
-				None
-			} else {
-				Some((ssl_protocol.to_owned(), ssl_cipher.to_owned()))
-			},
-			response_result_type: x_edge_response_result_type.to_owned(),
-			http_version: cs_protocol_version.to_owned(),
-			fle_status: if fle_status != "-" {
-				Some(fle_status.to_owned())
-			} else {
-				None
-			},
-			fle_encrypted_fields: if fle_encrypted_fields != "-" {
-				Some(fle_encrypted_fields.to_owned())
-			} else {
-				None
-			},
-		}
+			user_agent: values.optional("cs(User-Agent)").map(str::to_owned),
+			referer: values.optional("cs(Referer)").map(str::to_owned),
+			cookie: values.optional("cs(Cookie)").map(str::to_owned),
+			result_type: values.required("x-edge-result-type")?.to_owned(),
+			request_id: values.required("x-edge-request-id")?.to_owned(),
+			request_bytes: values.parse("cs-bytes")?,
+			time_taken: seconds(values.required("time-taken")?)?,
+			forwarded_for: values.optional("x-forwarded-for").map(str::to_owned),
+			ssl_protocol_cipher,
+			response_result_type: values.required("x-edge-response-result-type")?.to_owned(),
+			http_version: values.required("cs-protocol-version")?.to_owned(),
+			fle_status: values.optional("fle-status").map(str::to_owned),
+			fle_encrypted_fields: values.optional("fle-encrypted-fields").map(str::to_owned),
+			remote_port: values.parse_optional("c-port")?,
+			time_to_first_byte: values
+				.optional("time-to-first-byte")
+				.map(seconds)
+				.transpose()?,
+			detailed_result_type: values
+				.optional("x-edge-detailed-result-type")
+				.map(str::to_owned),
+			content_type: values.optional("sc-content-type").map(str::to_owned),
+			content_length: values.parse_optional("sc-content-len")?,
+			range_start: values.parse_optional("sc-range-start")?,
+			range_end: values.parse_optional("sc-range-end")?,
+		})
 	}
 }
 
-mod http_serde {
-	use http::{Method, StatusCode};
-	use serde::{Deserialize, Deserializer, Serialize, Serializer};
+/// The fields of standard logs, in the order used if a file has no `#Fields:` header.
+const FIELDS: [&str; 33] = [
+	"date",
+	"time",
+	"x-edge-location",
+	"sc-bytes",
+	"c-ip",
+	"cs-method",
+	"cs(Host)",
+	"cs-uri-stem",
+	"sc-status",
+	"cs(Referer)",
+	"cs(User-Agent)",
+	"cs-uri-query",
+	"cs(Cookie)",
+	"x-edge-result-type",
+	"x-edge-request-id",
+	"x-host-header",
+	"cs-protocol",
+	"cs-bytes",
+	"time-taken",
+	"x-forwarded-for",
+	"ssl-protocol",
+	"ssl-cipher",
+	"x-edge-response-result-type",
+	"cs-protocol-version",
+	"fle-status",
+	"fle-encrypted-fields",
+	"c-port",
+	"time-to-first-byte",
+	"x-edge-detailed-result-type",
+	"sc-content-type",
+	"sc-content-len",
+	"sc-range-start",
+	"sc-range-end",
+];
 
-	pub(crate) struct Serde<T>(T);
+fn header(line: &str) -> Columns {
+	let fields = line.trim().trim_start_matches("#Fields:");
+	Columns::new(fields.split_whitespace())
+}
 
-	impl Serialize for Serde<&Method> {
-		fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-		where
-			S: Serializer,
-		{
-			self.0.as_str().serialize(serializer)
-		}
-	}
-	impl Serialize for Serde<&Option<StatusCode>> {
-		fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-		where
-			S: Serializer,
-		{
-			self.0.map(|x| x.as_u16()).serialize(serializer)
-		}
-	}
-	impl<'de> Deserialize<'de> for Serde<Method> {
-		fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-		where
-			D: Deserializer<'de>,
-		{
-			String::deserialize(deserializer)
-				.and_then(|x| {
-					x.parse::<Method>()
-						.map_err(|err| serde::de::Error::custom(err.to_string()))
-				})
-				.map(Self)
-		}
-	}
-	impl<'de> Deserialize<'de> for Serde<Option<StatusCode>> {
-		fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-		where
-			D: Deserializer<'de>,
-		{
-			Option::<u16>::deserialize(deserializer)
-				.and_then(|x| {
-					x.map(|x| {
-						StatusCode::from_u16(x)
-							.map_err(|err| serde::de::Error::custom(err.to_string()))
-					})
-					.transpose()
-				})
-				.map(Self)
-		}
-	}
+/// A duration in seconds, to millisecond precision.
+fn seconds(value: &str) -> Result<Duration, String> {
+	let seconds = value
+		.parse::<f64>()
+		.ok()
+		.filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+		.ok_or_else(|| format!("invalid duration {:?}", value))?;
+	#[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+	Ok(Duration::from_millis((seconds * 1000.0).round() as u64))
+}
 
-	pub(crate) fn serialize<T, S>(t: &T, serializer: S) -> Result<S::Ok, S::Error>
-	where
-		for<'a> Serde<&'a T>: Serialize,
-		S: Serializer,
-	{
-		Serde(t).serialize(serializer)
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const HEADER: &str = "#Fields: date time x-edge-location sc-bytes c-ip cs-method cs(Host) cs-uri-stem sc-status cs(Referer) cs(User-Agent) cs-uri-query cs(Cookie) x-edge-result-type x-edge-request-id x-host-header cs-protocol cs-bytes time-taken x-forwarded-for ssl-protocol ssl-cipher x-edge-response-result-type cs-protocol-version fle-status fle-encrypted-fields c-port time-to-first-byte x-edge-detailed-result-type sc-content-type sc-content-len sc-range-start sc-range-end";
+	const LINE: &str = "2019-12-04\t21:02:31\tLAX1\t392\t192.0.2.100\tGET\td111111abcdef8.cloudfront.net\t/index.html\t200\t-\tMozilla/5.0\ta=b\t-\tHit\tSOX4xwn4XV6Q4rgb7XiVGOHms_BGlTAC4KyHmureZmBNrjGdRLiNIQ==\td111111abcdef8.cloudfront.net\thttps\t23\t0.001\t-\tTLSv1.2\tECDHE-RSA-AES128-GCM-SHA256\tHit\tHTTP/2.0\t-\t-\t11040\t0.001\tHit\ttext/html\t78\t-\t-";
+
+	#[test]
+	fn fields_header() {
+		let row = CloudfrontRow::from_line(&header(HEADER), LINE).unwrap();
+		assert_eq!(
+			row,
+			CloudfrontRow::from_line(&Columns::new(FIELDS.iter().copied()), LINE).unwrap()
+		);
+		assert_eq!(row.method, Method::GET);
+		assert_eq!(
+			row.url.as_str(),
+			"https://d111111abcdef8.cloudfront.net/index.html?a=b"
+		);
+		assert_eq!(row.time_taken, Duration::from_millis(1));
+		assert_eq!(row.remote_port, Some(11040));
+		assert_eq!(row.content_type.as_deref(), Some("text/html"));
+		assert_eq!(row.range_start, None);
+
+		// a file from before c-port etc. were added
+		let old = LINE
+			.splitn(27, '\t')
+			.take(26)
+			.collect::<Vec<_>>()
+			.join("\t");
+		let columns = header(&HEADER.split(' ').take(27).collect::<Vec<_>>().join(" "));
+		let row = CloudfrontRow::from_line(&columns, &old).unwrap();
+		assert_eq!(row.remote_port, None);
+		assert_eq!(row.content_length, None);
 	}
-	pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
-	where
-		Serde<T>: Deserialize<'de>,
-		D: Deserializer<'de>,
-	{
-		Serde::<T>::deserialize(deserializer).map(|x| x.0)
+
+	#[test]
+	fn malformed() {
+		let columns = Columns::new(FIELDS.iter().copied());
+		let truncated = LINE.splitn(6, '\t').take(5).collect::<Vec<_>>().join("\t");
+		assert_eq!(
+			CloudfrontRow::from_line(&columns, &truncated),
+			Err("missing sc-status".to_owned())
+		);
+		let invalid = LINE.replace("\t392\t", "\tabc\t");
+		assert_eq!(
+			CloudfrontRow::from_line(&columns, &invalid),
+			Err("invalid sc-bytes \"abc\"".to_owned())
+		);
 	}
 }
@@ -0,0 +1,337 @@
+#![allow(unused_qualifications)]
+
+use async_compression::futures::bufread::GzipDecoder;
+use futures::{
+	future::Either, io::BufReader, stream, AsyncReadExt, FutureExt, Stream, StreamExt, TryStreamExt
+};
+use recycle::VecExt;
+use rusoto_s3::{GetObjectRequest, S3Client};
+use serde::{Deserialize, Serialize};
+use serde_closure::FnMutNamed;
+use std::convert::identity;
+
+use amadeus_core::{
+	file::Directory, into_par_stream::IteratorExt, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
+};
+use amadeus_types::{Data, DateTime, Json};
+
+use super::{
+	get_object, AwsCredentials, AwsError, AwsRegion, AwsRetry, Ref, S3Directory, RUSOTO_DISPATCHER
+};
+
+/// The events logged by a Cloudtrail trail, as delivered to an S3 bucket.
+///
+/// Each object holds a gzipped JSON document with a `Records` array, each element of which is
+/// parsed into a [`CloudtrailRow`]. Digest files are skipped.
+#[derive(Clone, Debug)]
+pub struct Cloudtrail {
+	region: AwsRegion,
+	bucket: String,
+	objects: Vec<String>,
+	credentials: AwsCredentials,
+	retry: AwsRetry,
+}
+impl Cloudtrail {
+	pub async fn new(region: AwsRegion, bucket: &str, prefix: &str) -> Result<Self, AwsError> {
+		Self::new_with(region, bucket, prefix, AwsCredentials::Environment).await
+	}
+	pub async fn new_with(
+		region: AwsRegion, bucket: &str, prefix: &str, credentials: AwsCredentials,
+	) -> Result<Self, AwsError> {
+		let objects = S3Directory::new_with(region.clone(), bucket, prefix, credentials.clone())
+			.partitions_filter(|path| {
+				path.iter().all(|dir| dir != "CloudTrail-Digest")
+					&& path.file_name().map_or(true, |file_name| {
+						let file_name = file_name.to_string_lossy();
+						file_name.ends_with(".json.gz") || file_name.ends_with(".json")
+					})
+			})
+			.await?
+			.map(|partition| partition.key);
+
+		Ok(Self {
+			region,
+			bucket: bucket.to_owned(),
+			objects,
+			credentials,
+			retry: AwsRetry::default(),
+		})
+	}
+	/// How failed requests to get the objects are retried. Listing them when constructed is
+	/// retried per [`AwsRetry::default()`].
+	pub fn retry(mut self, retry: AwsRetry) -> Self {
+		self.retry = retry;
+		self
+	}
+}
+
+#[cfg(not(nightly))]
+type Output = std::pin::Pin<Box<dyn Stream<Item = Result<CloudtrailRow, AwsError>> + Send>>;
+#[cfg(nightly)]
+type Output = impl Stream<Item = Result<CloudtrailRow, AwsError>> + Send;
+
+FnMutNamed! {
+	pub type Closure<> = |self, credentials: AwsCredentials, region: AwsRegion, bucket: String, retry: AwsRetry|key=> String| -> Output where {
+		let (credentials, region, bucket, retry) = (
+			self.credentials.clone(),
+			self.region.clone(),
+			self.bucket.clone(),
+			self.retry,
+		);
+		#[allow(clippy::let_and_return)]
+		let ret = async move {
+			let client = S3Client::new_with(
+				Ref(once_cell::sync::Lazy::force(&RUSOTO_DISPATCHER)),
+				credentials,
+				region,
+			);
+			let rows = async {
+				let request = GetObjectRequest {
+					bucket,
+					key: key.clone(),
+					..GetObjectRequest::default()
+				};
+				let body = get_object(&client, &retry, request).await?;
+				let body = BufReader::new(TryStreamExt::into_async_read(body));
+				// Content-Encoding isn't set, so decode by extension
+				let mut body = if key.ends_with(".gz") {
+					let mut body = GzipDecoder::new(body);
+					body.multiple_members(true);
+					Either::Left(body)
+				} else {
+					Either::Right(body)
+				};
+				let mut buf = Vec::new();
+				let _ = body.read_to_end(&mut buf).await?;
+				let Records { records } = serde_json::from_slice(&buf)
+					.map_err(|err| AwsError::ParseError(format!("{}: {}", key, err)))?;
+				Ok::<_, AwsError>(stream::iter(records.into_iter().map(move |record| {
+					CloudtrailRow::from_record(record)
+						.map_err(|err| AwsError::ParseError(format!("{}: {}", key, err)))
+				})))
+			}
+			.await;
+			ResultExpandIter::new(rows)
+		}
+		.flatten_stream()
+		.map(|x: Result<Result<CloudtrailRow, _>, _>| x.and_then(identity));
+		#[cfg(not(nightly))]
+		let ret = ret.boxed();
+		ret
+	}
+}
+
+impl Source for Cloudtrail {
+	type Item = CloudtrailRow;
+	type Error = AwsError;
+
+	type ParStream = DistParStream<Self::DistStream>;
+	#[cfg(not(nightly))]
+	#[allow(clippy::type_complexity)]
+	type DistStream = amadeus_core::par_stream::FlatMap<
+		amadeus_core::into_par_stream::PartitionsDistStream<std::vec::IntoIter<String>>,
+		Closure,
+	>;
+	#[cfg(nightly)]
+	type DistStream = impl DistributedStream<Item = Result<Self::Item, Self::Error>>;
+
+	fn par_stream(self) -> Self::ParStream {
+		DistParStream::new(self.dist_stream())
+	}
+	#[allow(clippy::let_and_return)]
+	fn dist_stream(self) -> Self::DistStream {
+		let Self {
+			bucket,
+			region,
+			objects,
+			credentials,
+			retry,
+		} = self;
+		objects.into_iter().dist_partitions().flat_map(Closure::new(
+			credentials,
+			region,
+			bucket,
+			retry,
+		))
+	}
+}
+
+/// An API call or other event recorded by Cloudtrail.
+///
+/// The fields of `userIdentity` are flattened into the row. Fields whose contents vary by
+/// service, such as `requestParameters`, are kept as JSON.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+pub struct CloudtrailRow {
+	pub event_time: DateTime,
+	pub event_version: String,
+	pub event_source: String,
+	pub event_name: String,
+	pub event_type: Option<String>,
+	pub event_category: Option<String>,
+	pub event_id: String,
+	pub aws_region: String,
+	/// The IP address the request came from, or for requests made by an AWS service on the
+	/// user's behalf, the name of the service.
+	pub source_ip_address: Option<String>,
+	pub user_agent: Option<String>,
+	pub user_type: Option<String>,
+	pub principal_id: Option<String>,
+	pub user_arn: Option<String>,
+	pub account_id: Option<String>,
+	pub access_key_id: Option<String>,
+	pub user_name: Option<String>,
+	pub invoked_by: Option<String>,
+	pub error_code: Option<String>,
+	pub error_message: Option<String>,
+	pub request_id: Option<String>,
+	pub request_parameters: Option<Json>,
+	pub response_elements: Option<Json>,
+	pub additional_event_data: Option<Json>,
+	pub resources: Option<Json>,
+	pub read_only: Option<bool>,
+	pub management_event: Option<bool>,
+	pub recipient_account_id: Option<String>,
+}
+impl Data for CloudtrailRow {
+	type Vec = Vec<Self>;
+	type DynamicType = ();
+
+	fn new_vec(_type: Self::DynamicType) -> Self::Vec {
+		Vec::new()
+	}
+}
+impl CloudtrailRow {
+	fn from_record(record: Record) -> Result<Self, String> {
+		let event_time = chrono::DateTime::parse_from_rfc3339(&record.event_time)
+			.map(|time| DateTime::from_chrono(&time))
+			.map_err(|err| format!("invalid eventTime {:?}: {}", record.event_time, err))?;
+		let user_identity = record.user_identity.unwrap_or_default();
+		Ok(Self {
+			event_time,
+			event_version: record.event_version,
+			event_source: record.event_source,
+			event_name: record.event_name,
+			event_type: record.event_type,
+			event_category: record.event_category,
+			event_id: record.event_id,
+			aws_region: record.aws_region,
+			source_ip_address: record.source_ip_address,
+			user_agent: record.user_agent,
+			user_type: user_identity.kind,
+			principal_id: user_identity.principal_id,
+			user_arn: user_identity.arn,
+			account_id: user_identity.account_id,
+			access_key_id: user_identity.access_key_id,
+			user_name: user_identity.user_name,
+			invoked_by: user_identity.invoked_by,
+			error_code: record.error_code,
+			error_message: record.error_message,
+			request_id: record.request_id,
+			request_parameters: json(record.request_parameters),
+			response_elements: json(record.response_elements),
+			additional_event_data: json(record.additional_event_data),
+			resources: json(record.resources),
+			read_only: record.read_only.map(ReadOnly::into_bool),
+			management_event: record.management_event,
+			recipient_account_id: record.recipient_account_id,
+		})
+	}
+}
+
+#[derive(Deserialize)]
+struct Records {
+	#[serde(rename = "Records")]
+	records: Vec<Record>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Record {
+	event_version: String,
+	event_time: String,
+	event_source: String,
+	event_name: String,
+	event_type: Option<String>,
+	event_category: Option<String>,
+	#[serde(rename = "eventID")]
+	event_id: String,
+	aws_region: String,
+	#[serde(rename = "sourceIPAddress")]
+	source_ip_address: Option<String>,
+	user_agent: Option<String>,
+	user_identity: Option<UserIdentity>,
+	error_code: Option<String>,
+	error_message: Option<String>,
+	#[serde(rename = "requestID")]
+	request_id: Option<String>,
+	request_parameters: Option<serde_json::Value>,
+	response_elements: Option<serde_json::Value>,
+	additional_event_data: Option<serde_json::Value>,
+	resources: Option<serde_json::Value>,
+	read_only: Option<ReadOnly>,
+	management_event: Option<bool>,
+	recipient_account_id: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct UserIdentity {
+	#[serde(rename = "type")]
+	kind: Option<String>,
+	principal_id: Option<String>,
+	arn: Option<String>,
+	account_id: Option<String>,
+	access_key_id: Option<String>,
+	user_name: Option<String>,
+	invoked_by: Option<String>,
+}
+
+/// Some services log `readOnly` as a string rather than a boolean.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ReadOnly {
+	Bool(bool),
+	String(String),
+}
+impl ReadOnly {
+	fn into_bool(self) -> bool {
+		match self {
+			Self::Bool(read_only) => read_only,
+			Self::String(read_only) => read_only == "true",
+		}
+	}
+}
+
+/// JSON values as text, with `null` treated as missing.
+fn json(value: Option<serde_json::Value>) -> Option<Json> {
+	value
+		.filter(|value| !value.is_null())
+		.map(|value| Json::from(value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn records() {
+		let Records { records } = serde_json::from_str(r#"{"Records":[{"eventVersion":"1.08","userIdentity":{"type":"IAMUser","principalId":"AIDAEXAMPLE","arn":"arn:aws:iam::123456789012:user/Alice","accountId":"123456789012","accessKeyId":"AKIAEXAMPLE","userName":"Alice"},"eventTime":"2020-07-21T13:44:53Z","eventSource":"s3.amazonaws.com","eventName":"GetObject","awsRegion":"us-east-1","sourceIPAddress":"192.0.2.1","userAgent":"aws-cli/2.0","requestParameters":{"bucketName":"my-bucket","key":"a.txt"},"responseElements":null,"requestID":"ABC123","eventID":"6ba6ee45-bd49-4c79-a98a-1ba8ac7bd2e6","readOnly":true,"resources":[{"type":"AWS::S3::Object","ARN":"arn:aws:s3:::my-bucket/a.txt"}],"eventType":"AwsApiCall","managementEvent":false,"recipientAccountId":"123456789012"},{"eventVersion":"1.05","userIdentity":{"type":"AWSService","invokedBy":"ec2.amazonaws.com"},"eventTime":"2020-07-21T13:45:00Z","eventSource":"kms.amazonaws.com","eventName":"Decrypt","awsRegion":"us-east-1","sourceIPAddress":"ec2.amazonaws.com","eventID":"9c2b1e2a-0d5f-4e1d-8d5c-2c1a7f3e4b5a","readOnly":"true","errorCode":"AccessDenied"}]}"#).unwrap();
+		let rows = records
+			.into_iter()
+			.map(CloudtrailRow::from_record)
+			.collect::<Result<Vec<_>, _>>()
+			.unwrap();
+		assert_eq!(rows[0].user_name.as_deref(), Some("Alice"));
+		assert_eq!(rows[0].event_name, "GetObject");
+		assert_eq!(
+			rows[0].request_parameters.as_ref().map(ToString::to_string),
+			Some(r#"{"bucketName":"my-bucket","key":"a.txt"}"#.to_owned())
+		);
+		assert_eq!(rows[0].response_elements, None);
+		assert_eq!(rows[0].read_only, Some(true));
+		assert_eq!(rows[1].invoked_by.as_deref(), Some("ec2.amazonaws.com"));
+		assert_eq!(rows[1].read_only, Some(true));
+		assert_eq!(rows[1].error_code.as_deref(), Some("AccessDenied"));
+		assert_eq!(rows[1].user_arn, None);
+	}
+}
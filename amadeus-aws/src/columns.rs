@@ -0,0 +1,70 @@
+//! Parsing of log lines whose fields are named by a header, as written by Cloudfront and VPC
+//! Flow Logs.
+
+use std::{collections::HashMap, str::FromStr};
+
+/// The index of each field within the lines of a log file.
+pub(crate) struct Columns(HashMap<String, usize>);
+impl Columns {
+	pub(crate) fn new<'a>(fields: impl IntoIterator<Item = &'a str>) -> Self {
+		Self(
+			fields
+				.into_iter()
+				.enumerate()
+				.map(|(i, field)| (field.to_owned(), i))
+				.collect(),
+		)
+	}
+	pub(crate) fn values<'a>(&self, values: impl Iterator<Item = &'a str>) -> Values<'a, '_> {
+		Values {
+			columns: self,
+			values: values.collect(),
+		}
+	}
+}
+
+/// The values of a line, looked up by field name.
+pub(crate) struct Values<'a, 'b> {
+	columns: &'b Columns,
+	values: Vec<&'a str>,
+}
+impl<'a> Values<'a, '_> {
+	/// Errors if the line doesn't have exactly one value per field, as when it's truncated.
+	pub(crate) fn exact(self) -> Result<Self, String> {
+		if self.values.len() != self.columns.0.len() {
+			return Err(format!(
+				"expected {} values, found {}",
+				self.columns.0.len(),
+				self.values.len()
+			));
+		}
+		Ok(self)
+	}
+	fn get(&self, field: &str) -> Option<&'a str> {
+		let i = *self.columns.0.get(field)?;
+		self.values.get(i).copied()
+	}
+	pub(crate) fn required(&self, field: &str) -> Result<&'a str, String> {
+		self.get(field).ok_or_else(|| format!("missing {}", field))
+	}
+	/// The value of `field`, or `None` if it's missing or `-`.
+	pub(crate) fn optional(&self, field: &str) -> Option<&'a str> {
+		self.get(field)
+			.filter(|&value| value != "-" && !value.is_empty())
+	}
+	pub(crate) fn parse<T: FromStr>(&self, field: &str) -> Result<T, String> {
+		let value = self.required(field)?;
+		value
+			.parse()
+			.map_err(|_| format!("invalid {} {:?}", field, value))
+	}
+	pub(crate) fn parse_optional<T: FromStr>(&self, field: &str) -> Result<Option<T>, String> {
+		self.optional(field)
+			.map(|value| {
+				value
+					.parse()
+					.map_err(|_| format!("invalid {} {:?}", field, value))
+			})
+			.transpose()
+	}
+}
@@ -1,18 +1,24 @@
 use async_trait::async_trait;
-use futures::{future, future::LocalBoxFuture, FutureExt};
-use rusoto_core::RusotoError;
+use futures::{
+	future, future::LocalBoxFuture, stream, AsyncReadExt, FutureExt, StreamExt, TryStreamExt
+};
 use rusoto_s3::{GetObjectRequest, HeadObjectRequest, S3Client, S3};
 use serde::{Deserialize, Serialize};
 use std::{
-	convert::{TryFrom, TryInto}, sync::Arc
+	convert::{TryFrom, TryInto}, io, sync::Arc
 };
-use tokio::io::AsyncReadExt;
+use tracing::{debug_span, field, Instrument};
 
 use amadeus_core::{
 	file::{Directory, File, Page, Partition, PathBuf}, util::IoError
 };
 
-use super::{retry, AwsCredentials, AwsError, AwsRegion, Ref, RUSOTO_DISPATCHER};
+use super::{
+	get_object, retry, AwsCredentials, AwsError, AwsRegion, AwsRetry, Ref, RUSOTO_DISPATCHER
+};
+
+/// The most prefixes listed at once while walking an [`S3Directory`].
+const MAX_LISTS: usize = 16;
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct S3Directory {
@@ -20,6 +26,7 @@ pub struct S3Directory {
 	bucket: String,
 	prefix: String,
 	credentials: AwsCredentials,
+	retry: AwsRetry,
 }
 impl S3Directory {
 	pub fn new(region: AwsRegion, bucket: &str, prefix: &str) -> Self {
@@ -34,8 +41,14 @@ impl S3Directory {
 			bucket,
 			prefix,
 			credentials,
+			retry: AwsRetry::default(),
 		}
 	}
+	/// How failed requests are retried.
+	pub fn retry(mut self, retry: AwsRetry) -> Self {
+		self.retry = retry;
+		self
+	}
 }
 #[async_trait(?Send)]
 impl Directory for S3Directory {
@@ -50,61 +63,61 @@ impl Directory for S3Directory {
 			bucket,
 			prefix,
 			credentials,
+			retry,
 		} = self;
 		let client = S3Client::new_with(
 			Ref(&*RUSOTO_DISPATCHER),
 			credentials.clone(),
 			region.clone(),
 		);
-		let objects = super::list(&client, &bucket, &prefix).await?;
-
-		let mut current_path = PathBuf::new();
-		let mut skip = false;
-		let mut last_key: Option<String> = None;
-		objects
-			.into_iter()
-			.filter(|object| {
-				let key = object.key.as_ref().unwrap();
-				assert!(key.starts_with(&prefix));
-				let key = &key[prefix.len()..];
-				assert!(last_key.is_none() || **last_key.as_ref().unwrap() < *key, "S3 API not returning objects in \"UTF-8 character encoding in lexicographical order\" as their docs specify");
-				last_key = Some(key.to_owned());
-				let mut path = key.split('/').collect::<Vec<&str>>();
-				let file_name = path.pop().unwrap();
-				skip = skip
-					&& path.len() >= current_path.depth()
-					&& current_path.iter().eq(path
-						.iter()
-						.take(current_path.depth())
-						.copied());
-				if skip {
-					return false;
-				}
-				while current_path.depth() > path.len()
-					|| (current_path.depth() > 0
-						&& current_path.last().unwrap() != path[current_path.depth() - 1])
-				{
-					let _ = current_path.pop().unwrap();
+		// Walk the tree a level at a time, listing the directories of each level concurrently
+		// and only descending into those that `f` accepts.
+		let mut objects = Vec::new();
+		let mut level = vec![(PathBuf::new(), prefix)];
+		while !level.is_empty() {
+			let (client, retry, bucket) = (&client, &retry, &bucket);
+			let listings = stream::iter(level)
+				.map(|(path, prefix)| async move {
+					let listing = super::list_delimited(client, retry, bucket, &prefix).await?;
+					Ok::<_, AwsError>((path, prefix, listing))
+				})
+				.buffered(MAX_LISTS)
+				.try_collect::<Vec<_>>()
+				.await?;
+			level = Vec::new();
+			for (mut path, prefix, (dir_objects, dir_prefixes)) in listings {
+				for object in dir_objects {
+					let key = object.key.as_ref().unwrap();
+					assert!(key.starts_with(&prefix));
+					path.set_file_name(Some(&key[prefix.len()..]));
+					let ret = f(&path);
+					path.set_file_name::<Vec<u8>>(None);
+					if ret {
+						objects.push(object);
+					}
 				}
-				while path.len() > current_path.depth() {
-					current_path.push(path[current_path.depth()]);
-					if !f(&current_path) {
-						skip = true;
-						return false;
+				for dir_prefix in dir_prefixes {
+					assert!(dir_prefix.starts_with(&prefix) && dir_prefix.ends_with('/'));
+					let mut path = path.clone();
+					path.push(&dir_prefix[prefix.len()..dir_prefix.len() - 1]);
+					if f(&path) {
+						level.push((path, dir_prefix));
 					}
 				}
-				current_path.set_file_name(Some(file_name));
-				let ret = f(&current_path);
-				current_path.set_file_name::<Vec<u8>>(None);
-				ret
-			})
+			}
+		}
+		objects.sort_by(|a, b| a.key.cmp(&b.key));
+
+		objects
+			.into_iter()
 			.map(|object| {
 				Ok(S3Partition {
 					region: region.clone(),
 					bucket: bucket.clone(),
 					key: object.key.unwrap(),
 					len: object.size.unwrap().try_into().unwrap(),
-					credentials: credentials.clone()
+					credentials: credentials.clone(),
+					retry,
 				})
 			})
 			.collect()
@@ -127,6 +140,7 @@ pub struct S3File {
 	bucket: String,
 	key: String,
 	credentials: AwsCredentials,
+	retry: AwsRetry,
 }
 impl S3File {
 	pub fn new(region: AwsRegion, bucket: &str, key: &str) -> Self {
@@ -141,8 +155,14 @@ impl S3File {
 			bucket,
 			key,
 			credentials,
+			retry: AwsRetry::default(),
 		}
 	}
+	/// How failed requests are retried.
+	pub fn retry(mut self, retry: AwsRetry) -> Self {
+		self.retry = retry;
+		self
+	}
 }
 #[async_trait(?Send)]
 impl File for S3File {
@@ -160,7 +180,14 @@ impl Partition for S3File {
 
 	async fn pages(self) -> Result<Vec<Self::Page>, Self::Error> {
 		Ok(vec![
-			S3Page::new(self.region, self.bucket, self.key, self.credentials).await,
+			S3Page::new(
+				self.region,
+				self.bucket,
+				self.key,
+				self.credentials,
+				self.retry,
+			)
+			.await?,
 		])
 	}
 }
@@ -169,9 +196,10 @@ impl Partition for S3File {
 pub struct S3Partition {
 	region: AwsRegion,
 	bucket: String,
-	key: String,
+	pub(crate) key: String,
 	len: u64,
 	credentials: AwsCredentials,
+	retry: AwsRetry,
 }
 #[async_trait(?Send)]
 impl Partition for S3Partition {
@@ -180,12 +208,13 @@ impl Partition for S3Partition {
 
 	async fn pages(self) -> Result<Vec<Self::Page>, Self::Error> {
 		let client = S3Client::new_with(Ref(&*RUSOTO_DISPATCHER), self.credentials, self.region);
-		let (bucket, key, len) = (self.bucket, self.key, self.len);
+		let (bucket, key, len, retry) = (self.bucket, self.key, self.len, self.retry);
 		let inner = Arc::new(S3PageInner {
 			client,
 			bucket,
 			key,
 			len,
+			retry,
 		});
 		Ok(vec![S3Page { inner }])
 	}
@@ -196,6 +225,7 @@ struct S3PageInner {
 	bucket: String,
 	key: String,
 	len: u64,
+	retry: AwsRetry,
 }
 pub struct S3Page {
 	inner: Arc<S3PageInner>,
@@ -203,9 +233,10 @@ pub struct S3Page {
 impl S3Page {
 	async fn new(
 		region: AwsRegion, bucket: String, key: String, credentials: AwsCredentials,
-	) -> Self {
+		retry_: AwsRetry,
+	) -> Result<Self, IoError> {
 		let client = S3Client::new_with(Ref(&*RUSOTO_DISPATCHER), credentials, region);
-		let object = retry(|| {
+		let object = retry(&retry_, || {
 			client.head_object(HeadObjectRequest {
 				bucket: bucket.clone(),
 				key: key.clone(),
@@ -213,15 +244,16 @@ impl S3Page {
 			})
 		})
 		.await
-		.unwrap();
+		.map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
 		let len = object.content_length.unwrap().try_into().unwrap();
 		let inner = Arc::new(S3PageInner {
 			client,
 			bucket,
 			key,
 			len,
+			retry: retry_,
 		});
-		Self { inner }
+		Ok(Self { inner })
 	}
 }
 impl Page for S3Page {
@@ -236,48 +268,65 @@ impl Page for S3Page {
 		let self_ = S3Page {
 			inner: self.inner.clone(),
 		};
-		Box::pin(async move {
+		let span = debug_span!(
+			"page_read",
+			bucket = %self.inner.bucket,
+			key = %self.inner.key,
+			offset,
+			len,
+			bytes = field::Empty,
+			retries = field::Empty,
+		);
+		let span_ = span.clone();
+		async move {
 			let len = len.min(usize::try_from(self_.inner.len.saturating_sub(offset)).unwrap());
 			let mut buf_ = vec![0; len].into_boxed_slice();
 			let mut buf = &mut *buf_;
 			let len: u64 = len.try_into().unwrap();
 			let mut pos = 0_u64;
-			let mut errors: usize = 0;
+			// Times the body was cut off and the remainder requested again
+			let mut errors: u32 = 0;
 			let end = offset + len - 1;
 			while !buf.is_empty() {
 				let start = offset + pos;
 				assert_eq!(start, end + 1 - u64::try_from(buf.len()).unwrap()); // TODO
-				let res = self_.inner.client.get_object(GetObjectRequest {
+				let request = GetObjectRequest {
 					bucket: self_.inner.bucket.clone(),
 					key: self_.inner.key.clone(),
 					range: Some(format!("bytes={}-{}", start, end)),
 					..GetObjectRequest::default()
-				});
-				let res = res.await;
-				match res {
-					Err(RusotoError::HttpDispatch(_)) if errors < 10 => {
-						errors += 1;
-						continue;
-					}
-					Err(RusotoError::Unknown(response))
-						if response.status.is_server_error() && errors < 10 =>
-					{
-						errors += 1;
-						continue;
-					}
-					_ => (),
-				}
-				let mut read = res.unwrap().body.unwrap().into_async_read();
+				};
+				let body = get_object(&self_.inner.client, &self_.inner.retry, request)
+					.await
+					.map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+				let mut read = TryStreamExt::into_async_read(body);
 				while !buf.is_empty() {
-					let res = read.read_buf(&mut buf).await;
-					match res {
+					match read.read(buf).await {
 						Ok(0) | Err(_) => break,
-						Ok(n) => pos += u64::try_from(n).unwrap(),
+						Ok(n) => {
+							pos += u64::try_from(n).unwrap();
+							let tmp = buf;
+							buf = &mut tmp[n..];
+						}
+					}
+				}
+				if !buf.is_empty() {
+					errors += 1;
+					if errors >= self_.inner.retry.max_attempts {
+						return Err(io::Error::new(
+							io::ErrorKind::UnexpectedEof,
+							"S3 response body cut off",
+						)
+						.into());
 					}
 				}
 			}
+			let _ = span_.record("bytes", pos);
+			let _ = span_.record("retries", errors);
 			Ok(buf_)
-		})
+		}
+		.instrument(span)
+		.boxed_local()
 	}
 	fn write(
 		&self, _offset: u64, _buf: Box<[u8]>,
@@ -0,0 +1,88 @@
+use http::{Method, StatusCode};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub(crate) struct Serde<T>(T);
+
+impl Serialize for Serde<&Method> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		self.0.as_str().serialize(serializer)
+	}
+}
+impl Serialize for Serde<&Option<Method>> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		self.0.as_ref().map(Method::as_str).serialize(serializer)
+	}
+}
+impl Serialize for Serde<&Option<StatusCode>> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		self.0.map(|x| x.as_u16()).serialize(serializer)
+	}
+}
+impl<'de> Deserialize<'de> for Serde<Method> {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		String::deserialize(deserializer)
+			.and_then(|x| {
+				x.parse::<Method>()
+					.map_err(|err| serde::de::Error::custom(err.to_string()))
+			})
+			.map(Self)
+	}
+}
+impl<'de> Deserialize<'de> for Serde<Option<Method>> {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		Option::<String>::deserialize(deserializer)
+			.and_then(|x| {
+				x.map(|x| {
+					x.parse::<Method>()
+						.map_err(|err| serde::de::Error::custom(err.to_string()))
+				})
+				.transpose()
+			})
+			.map(Self)
+	}
+}
+impl<'de> Deserialize<'de> for Serde<Option<StatusCode>> {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		Option::<u16>::deserialize(deserializer)
+			.and_then(|x| {
+				x.map(|x| {
+					StatusCode::from_u16(x).map_err(|err| serde::de::Error::custom(err.to_string()))
+				})
+				.transpose()
+			})
+			.map(Self)
+	}
+}
+
+pub(crate) fn serialize<T, S>(t: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+	for<'a> Serde<&'a T>: Serialize,
+	S: Serializer,
+{
+	Serde(t).serialize(serializer)
+}
+pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+	Serde<T>: Deserialize<'de>,
+	D: Deserializer<'de>,
+{
+	Serde::<T>::deserialize(deserializer).map(|x| x.0)
+}
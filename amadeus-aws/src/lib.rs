@@ -30,30 +30,49 @@
 )]
 #![deny(unsafe_code)]
 
+mod alb;
 mod cloudfront;
+mod cloudtrail;
+mod columns;
 mod file;
+mod http_serde;
+mod vpc_flow;
 
 use async_trait::async_trait;
-use futures::{stream, StreamExt, TryFutureExt, TryStreamExt};
+use futures::{future, Stream, StreamExt};
+use http::StatusCode;
 use once_cell::sync::Lazy;
 use rusoto_core::{
-	credential::StaticProvider, request::{DispatchSignedRequest, DispatchSignedRequestFuture, HttpClient}, signature::SignedRequest, RusotoError
+	credential::StaticProvider, request::{DispatchSignedRequest, DispatchSignedRequestFuture, HttpClient}, signature::SignedRequest, ByteStream, RusotoError
 };
-use rusoto_credential::{CredentialsError, DefaultCredentialsProvider, ProvideAwsCredentials};
-use rusoto_s3::{GetObjectError, ListObjectsV2Error, ListObjectsV2Request, Object, S3Client, S3};
+use rusoto_credential::{
+	AutoRefreshingProvider, CredentialsError, DefaultCredentialsProvider, ProfileProvider, ProvideAwsCredentials, Variable
+};
+use rusoto_s3::{
+	GetObjectError, GetObjectRequest, ListObjectsV2Error, ListObjectsV2Request, Object, S3Client, S3
+};
+use rusoto_sts::{StsAssumeRoleSessionCredentialsProvider, StsClient, WebIdentityProvider};
 use serde::{Deserialize, Serialize};
 use std::{
-	error, fmt::{self, Display}, future::Future, io, ops::FnMut, time::Duration
+	collections::HashMap, env, error, fmt::{self, Display}, future::Future, io, ops::FnMut, sync::{Arc, Mutex}, time::Duration
 };
 
-use amadeus_core::util::{IoError, ResultExpand};
+use tokio::sync::Semaphore;
+
+use amadeus_core::{par_stream::record_bytes_read, util::IoError};
 
+#[doc(inline)]
+pub use alb::{Alb, AlbRow};
 #[doc(inline)]
 pub use cloudfront::{Cloudfront, CloudfrontRow};
 #[doc(inline)]
+pub use cloudtrail::{Cloudtrail, CloudtrailRow};
+#[doc(inline)]
 pub use file::{S3Directory, S3File};
 #[doc(inline)]
 pub use rusoto_core::Region as AwsRegion;
+#[doc(inline)]
+pub use vpc_flow::{VpcFlow, VpcFlowRow};
 
 // https://docs.datadoghq.com/integrations/amazon_web_services/?tab=allpermissions#enable-logging-for-your-aws-service
 
@@ -61,60 +80,200 @@ static RUSOTO_DISPATCHER: Lazy<HttpClient> =
 	Lazy::new(|| HttpClient::new().expect("failed to create request dispatcher"));
 static RUSOTO_CREDENTIALS_PROVIDER: Lazy<DefaultCredentialsProvider> =
 	Lazy::new(|| DefaultCredentialsProvider::new().expect("failed to create credentials provider"));
+#[allow(clippy::type_complexity)]
+static CREDENTIALS_PROVIDERS: Lazy<
+	Mutex<HashMap<AwsCredentials, Arc<dyn ProvideAwsCredentials + Send + Sync>>>,
+> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// The most GET requests to S3 in flight at once in this process, across all sources.
+static GETS: Lazy<Semaphore> = Lazy::new(|| {
+	let max = env::var("AMADEUS_AWS_MAX_GETS")
+		.ok()
+		.and_then(|max| max.parse().ok())
+		.unwrap_or(DEFAULT_MAX_GETS);
+	Semaphore::new(max)
+});
+const DEFAULT_MAX_GETS: usize = 64;
+
+/// How failed requests to S3 are retried.
+///
+/// The `n`th retry waits for a random duration between half and all of
+/// `min(base_delay * 2^n, max_delay)`, so that processes retrying at once spread out.
+///
+/// Separately, each process makes at most 64 GET requests at once, or as many as the environment
+/// variable `AMADEUS_AWS_MAX_GETS` says.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct AwsRetry {
+	/// The most times a request is made, including the first.
+	pub max_attempts: u32,
+	pub base_delay: Duration,
+	pub max_delay: Duration,
+	/// Retry requests that couldn't be sent or got no response, such as on a connection reset.
+	pub dispatch_errors: bool,
+	/// Retry 5xx responses, other than throttling.
+	pub server_errors: bool,
+	/// Retry throttling responses: 503 `SlowDown` and 429.
+	pub throttling: bool,
+}
+impl Default for AwsRetry {
+	fn default() -> Self {
+		Self {
+			max_attempts: 10,
+			base_delay: Duration::from_millis(50),
+			max_delay: Duration::from_secs(20),
+			dispatch_errors: true,
+			server_errors: true,
+			throttling: true,
+		}
+	}
+}
+impl AwsRetry {
+	/// Never retry.
+	pub fn none() -> Self {
+		Self {
+			max_attempts: 1,
+			..Self::default()
+		}
+	}
+	fn is_retryable<E>(&self, err: &RusotoError<E>) -> bool {
+		match err {
+			RusotoError::HttpDispatch(_) => self.dispatch_errors,
+			RusotoError::Unknown(response)
+				if response.status == StatusCode::SERVICE_UNAVAILABLE
+					|| response.status == StatusCode::TOO_MANY_REQUESTS =>
+			{
+				self.throttling
+			}
+			RusotoError::Unknown(response) => {
+				self.server_errors && response.status.is_server_error()
+			}
+			_ => false,
+		}
+	}
+	fn delay(&self, retries: u32) -> Duration {
+		let delay = self
+			.base_delay
+			.checked_mul(1 << retries.min(31))
+			.map_or(self.max_delay, |delay| delay.min(self.max_delay));
+		delay / 2 + delay.mul_f64(rand::random()) / 2
+	}
+}
 
-fn retry<F, FU, T, S>(f: F) -> impl Future<Output = Result<T, RusotoError<S>>>
+async fn retry<F, FU, T, S>(policy: &AwsRetry, mut f: F) -> Result<T, RusotoError<S>>
 where
-	F: FnMut() -> FU + Unpin,
+	F: FnMut() -> FU,
 	FU: Future<Output = Result<T, RusotoError<S>>>,
 {
-	futures_retry::FutureRetry::new(f, |err| match err {
-		RusotoError::HttpDispatch(_) => {
-			futures_retry::RetryPolicy::WaitRetry(std::time::Duration::from_millis(10))
+	let mut retries = 0;
+	loop {
+		match f().await {
+			Err(err) if retries + 1 < policy.max_attempts && policy.is_retryable(&err) => {
+				tracing::debug!(retries, "retrying request");
+				tokio::time::delay_for(policy.delay(retries)).await;
+				retries += 1;
+			}
+			res => return res,
 		}
-		RusotoError::Unknown(response) if response.status.is_server_error() => {
-			futures_retry::RetryPolicy::WaitRetry(std::time::Duration::from_millis(10))
+	}
+}
+
+/// Get an object, or part of one, retrying per `policy`. One of this process's GET permits is
+/// held until the returned body is dropped.
+async fn get_object(
+	client: &S3Client, policy: &AwsRetry, request: GetObjectRequest,
+) -> Result<
+	impl Stream<Item = <ByteStream as Stream>::Item> + Send + Unpin,
+	RusotoError<GetObjectError>,
+> {
+	let permit = GETS.acquire().await;
+	let res = retry(policy, || client.get_object(request.clone())).await?;
+	Ok(res.body.unwrap().map(move |chunk| {
+		let _ = &permit;
+		if let Ok(chunk) = &chunk {
+			record_bytes_read(chunk.len());
 		}
-		e => futures_retry::RetryPolicy::ForwardError(e),
+		chunk
+	}))
+}
+
+/// Parse each line of object `key` with `parse`, which returns `None` for lines without a row
+/// such as comments. Lines that fail to parse are returned as [`AwsError::MalformedLine`], or if
+/// `skip_malformed` is set, logged and skipped.
+fn parse_lines<S, F, T>(
+	lines: S, key: String, skip_malformed: bool, mut parse: F,
+) -> impl Stream<Item = Result<T, AwsError>>
+where
+	S: Stream<Item = io::Result<String>>,
+	F: FnMut(&str) -> Option<Result<T, String>>,
+{
+	let mut line = 0;
+	lines.filter_map(move |x| {
+		line += 1;
+		let row = match x {
+			Ok(x) => match parse(&x) {
+				Some(Err(message)) if skip_malformed => {
+					tracing::warn!(%key, line, %message, "skipping malformed line");
+					None
+				}
+				Some(row) => Some(row.map_err(|message| {
+					AwsError::MalformedLine(MalformedLine {
+						key: key.clone(),
+						line,
+						message,
+					})
+				})),
+				None => None,
+			},
+			Err(err) => Some(Err(AwsError::from(err))),
+		};
+		future::ready(row)
 	})
-	.map_ok(|(x, _)| x)
-	.map_err(|(x, _)| x)
 }
 
 async fn list(
-	client: &S3Client, bucket: &str, prefix: &str,
+	client: &S3Client, policy: &AwsRetry, bucket: &str, prefix: &str,
 ) -> Result<Vec<Object>, RusotoError<ListObjectsV2Error>> {
-	let (first, continuation_token) = (true, None);
-	let objects: Result<Vec<Object>, _> = stream::unfold(
-		(first, continuation_token),
-		|(mut first, mut continuation_token)| async move {
-			if !first && continuation_token.is_none() {
-				return None;
-			}
-			first = false;
-			Some((
-				stream::iter(ResultExpand(
-					retry(|| {
-						client.list_objects_v2(ListObjectsV2Request {
-							bucket: bucket.to_owned(),
-							prefix: Some(prefix.to_owned()),
-							continuation_token: continuation_token.take(),
-							..ListObjectsV2Request::default()
-						})
-					})
-					.await
-					.map(|res| {
-						continuation_token = res.next_continuation_token;
-						res.contents.unwrap_or_default().into_iter()
-					}),
-				)),
-				(first, continuation_token),
-			))
-		},
-	)
-	.flatten()
-	.try_collect()
-	.await;
-	objects
+	list_objects(client, policy, bucket, prefix, None)
+		.await
+		.map(|(objects, _)| objects)
+}
+
+/// List the objects directly under `prefix`, along with the common prefixes (each ending in `/`)
+/// of those further down, without listing the objects beneath them.
+async fn list_delimited(
+	client: &S3Client, policy: &AwsRetry, bucket: &str, prefix: &str,
+) -> Result<(Vec<Object>, Vec<String>), RusotoError<ListObjectsV2Error>> {
+	list_objects(client, policy, bucket, prefix, Some("/")).await
+}
+
+async fn list_objects(
+	client: &S3Client, policy: &AwsRetry, bucket: &str, prefix: &str, delimiter: Option<&str>,
+) -> Result<(Vec<Object>, Vec<String>), RusotoError<ListObjectsV2Error>> {
+	let (mut objects, mut prefixes) = (Vec::new(), Vec::new());
+	let mut continuation_token = None;
+	loop {
+		let res = retry(policy, || {
+			client.list_objects_v2(ListObjectsV2Request {
+				bucket: bucket.to_owned(),
+				prefix: Some(prefix.to_owned()),
+				delimiter: delimiter.map(ToOwned::to_owned),
+				continuation_token: continuation_token.clone(),
+				..ListObjectsV2Request::default()
+			})
+		})
+		.await?;
+		objects.extend(res.contents.unwrap_or_default());
+		prefixes.extend(
+			res.common_prefixes
+				.unwrap_or_default()
+				.into_iter()
+				.map(|prefix| prefix.prefix.unwrap()),
+		);
+		continuation_token = res.next_continuation_token;
+		if continuation_token.is_none() {
+			break Ok((objects, prefixes));
+		}
+	}
 }
 
 struct Ref<T: 'static>(&'static T);
@@ -135,17 +294,104 @@ where
 	}
 }
 
-#[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[derive(Clone, Eq, PartialEq, Hash, Serialize, Deserialize, Debug)]
 pub enum AwsCredentials {
 	Anonymous,
-	AccessKey { id: String, secret: String },
+	AccessKey {
+		id: String,
+		secret: String,
+		/// The token of temporary credentials, as issued by STS.
+		#[serde(default)]
+		session_token: Option<String>,
+	},
+	/// The default chain: environment variables, then `~/.aws/credentials`, then the container
+	/// or instance role.
 	Environment,
+	/// A named profile from a shared credentials file, `~/.aws/credentials` by default.
+	Profile {
+		name: String,
+		file: Option<String>,
+	},
+	/// Temporary credentials for `role_arn`, got from STS using the `source` credentials.
+	AssumeRole {
+		role_arn: String,
+		session_name: String,
+		external_id: Option<String>,
+		source: Box<AwsCredentials>,
+	},
+	/// Temporary credentials for `role_arn`, got from STS in exchange for the OIDC token in
+	/// `token_file`, as provided to Kubernetes service accounts. The file is read again each
+	/// time the credentials are refreshed.
+	WebIdentity {
+		role_arn: String,
+		token_file: String,
+		session_name: Option<String>,
+	},
 }
 impl Default for AwsCredentials {
 	fn default() -> Self {
 		AwsCredentials::Environment
 	}
 }
+impl AwsCredentials {
+	/// The provider for `Profile`, `AssumeRole` and `WebIdentity`. These are cached per process,
+	/// so that the credentials they get are reused by every request until they near expiry.
+	fn provider(&self) -> Result<Arc<dyn ProvideAwsCredentials + Send + Sync>, CredentialsError> {
+		let mut providers = CREDENTIALS_PROVIDERS.lock().unwrap();
+		if let Some(provider) = providers.get(self) {
+			return Ok(provider.clone());
+		}
+		let provider: Arc<dyn ProvideAwsCredentials + Send + Sync> = match self {
+			AwsCredentials::Profile { name, file } => {
+				let provider = if let Some(file) = file {
+					ProfileProvider::with_configuration(file, name)
+				} else {
+					let mut provider = ProfileProvider::new()?;
+					provider.set_profile(name.clone());
+					provider
+				};
+				Arc::new(AutoRefreshingProvider::new(provider)?)
+			}
+			AwsCredentials::AssumeRole {
+				role_arn,
+				session_name,
+				external_id,
+				source,
+			} => {
+				let client = StsClient::new_with(
+					Ref(&*RUSOTO_DISPATCHER),
+					(**source).clone(),
+					AwsRegion::default(),
+				);
+				Arc::new(AutoRefreshingProvider::new(
+					StsAssumeRoleSessionCredentialsProvider::new(
+						client,
+						role_arn.clone(),
+						session_name.clone(),
+						external_id.clone(),
+						None,
+						None,
+						None,
+					),
+				)?)
+			}
+			AwsCredentials::WebIdentity {
+				role_arn,
+				token_file,
+				session_name,
+			} => Arc::new(AutoRefreshingProvider::new(WebIdentityProvider::new(
+				Variable::from_text_file(token_file.clone()),
+				role_arn.clone(),
+				Some(session_name.clone()),
+			))?),
+			AwsCredentials::Anonymous
+			| AwsCredentials::AccessKey { .. }
+			| AwsCredentials::Environment => unreachable!(),
+		};
+		let _ = providers.insert(self.clone(), provider.clone());
+		Ok(provider)
+	}
+}
 #[async_trait]
 impl ProvideAwsCredentials for AwsCredentials {
 	async fn credentials(&self) -> Result<rusoto_credential::AwsCredentials, CredentialsError> {
@@ -156,17 +402,64 @@ impl ProvideAwsCredentials for AwsCredentials {
 					.await
 			}
 
-			AwsCredentials::AccessKey { id, secret } => {
-				StaticProvider::new(id.clone(), secret.clone(), None, None)
+			AwsCredentials::AccessKey {
+				id,
+				secret,
+				session_token,
+			} => {
+				StaticProvider::new(id.clone(), secret.clone(), session_token.clone(), None)
 					.credentials()
 					.await
 			}
 
 			AwsCredentials::Environment => RUSOTO_CREDENTIALS_PROVIDER.credentials().await,
+
+			AwsCredentials::Profile { .. }
+			| AwsCredentials::AssumeRole { .. }
+			| AwsCredentials::WebIdentity { .. } => self.provider()?.credentials().await,
 		}
 	}
 }
 
+/// The region for an S3-compatible service, such as MinIO or Ceph, listening at `endpoint` – for
+/// example `http://localhost:9000`. It can be passed to [`S3File`], [`S3Directory`] and
+/// [`Cloudfront`] in place of an AWS region. Requests are signed for `region`, which for most
+/// such services should be [`AwsRegion::UsEast1`].
+///
+/// Requests are always path-style, i.e. sent to `{endpoint}/{bucket}/{key}`, so the service
+/// doesn't need a DNS entry per bucket.
+pub fn custom_endpoint(region: &AwsRegion, endpoint: &str) -> AwsRegion {
+	AwsRegion::Custom {
+		name: region.name().to_owned(),
+		endpoint: endpoint.trim_end_matches('/').to_owned(),
+	}
+}
+
+/// `region`, or if the environment variable `AWS_ENDPOINT_URL_S3` or `AWS_ENDPOINT_URL` is set,
+/// a [custom endpoint](custom_endpoint) at its value. This makes it possible to run the same
+/// binary against a local S3 emulator.
+pub fn endpoint_from_env(region: AwsRegion) -> AwsRegion {
+	match env::var("AWS_ENDPOINT_URL_S3").or_else(|_| env::var("AWS_ENDPOINT_URL")) {
+		Ok(endpoint) if !endpoint.is_empty() => custom_endpoint(&region, &endpoint),
+		_ => region,
+	}
+}
+
+/// A line of a log file that couldn't be parsed.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+pub struct MalformedLine {
+	/// The key of the object the line is in.
+	pub key: String,
+	/// The number of the line, counting from 1.
+	pub line: u64,
+	pub message: String,
+}
+impl Display for MalformedLine {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}:{}: {}", self.key, self.line, self.message)
+	}
+}
+
 #[derive(Debug)]
 #[allow(clippy::pub_enum_variant_names)]
 pub enum AwsError {
@@ -176,6 +469,7 @@ pub enum AwsError {
 	Credentials(CredentialsError),
 	Validation(String),
 	ParseError(String),
+	MalformedLine(MalformedLine),
 	Unknown(rusoto_core::request::BufferedHttpResponse),
 	Io(IoError),
 }
@@ -192,6 +486,7 @@ impl Clone for AwsError {
 			}
 			Self::Validation(err) => Self::Validation(err.clone()),
 			Self::ParseError(err) => Self::ParseError(err.clone()),
+			Self::MalformedLine(err) => Self::MalformedLine(err.clone()),
 			Self::Unknown(rusoto_core::request::BufferedHttpResponse {
 				status,
 				body,
@@ -212,6 +507,7 @@ impl PartialEq for AwsError {
 			| (Self::NoSuchKey(a), Self::NoSuchKey(b))
 			| (Self::Validation(a), Self::Validation(b))
 			| (Self::ParseError(a), Self::ParseError(b)) => a == b,
+			(Self::MalformedLine(a), Self::MalformedLine(b)) => a == b,
 			(Self::HttpDispatch(a), Self::HttpDispatch(b)) => a == b,
 			(Self::Credentials(a), Self::Credentials(b)) => a == b,
 			(Self::Unknown(a), Self::Unknown(b)) => a == b,
@@ -228,6 +524,7 @@ impl Display for AwsError {
 			| Self::NoSuchKey(err)
 			| Self::Validation(err)
 			| Self::ParseError(err) => err.fmt(f),
+			Self::MalformedLine(err) => err.fmt(f),
 			Self::HttpDispatch(err) => err.fmt(f),
 			Self::Credentials(err) => err.fmt(f),
 			Self::Unknown(err) => fmt::Debug::fmt(err, f),
@@ -270,3 +567,69 @@ impl From<GetObjectError> for AwsError {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use rusoto_core::request::{BufferedHttpResponse, HttpDispatchError};
+
+	use super::*;
+
+	fn response(status: StatusCode) -> RusotoError<GetObjectError> {
+		RusotoError::Unknown(BufferedHttpResponse {
+			status,
+			body: Default::default(),
+			headers: Default::default(),
+		})
+	}
+
+	#[test]
+	fn is_retryable() {
+		let retry = AwsRetry::default();
+		let dispatch = HttpDispatchError::new(String::from("connection reset"));
+		let dispatch = RusotoError::<GetObjectError>::HttpDispatch(dispatch);
+		assert!(retry.is_retryable(&dispatch));
+		assert!(retry.is_retryable(&response(StatusCode::SERVICE_UNAVAILABLE)));
+		assert!(retry.is_retryable(&response(StatusCode::TOO_MANY_REQUESTS)));
+		assert!(retry.is_retryable(&response(StatusCode::INTERNAL_SERVER_ERROR)));
+		assert!(!retry.is_retryable(&response(StatusCode::FORBIDDEN)));
+		let service = RusotoError::Service(GetObjectError::NoSuchKey(String::new()));
+		assert!(!retry.is_retryable(&service));
+
+		let retry = AwsRetry {
+			dispatch_errors: false,
+			server_errors: false,
+			..AwsRetry::default()
+		};
+		assert!(!retry.is_retryable(&dispatch));
+		assert!(retry.is_retryable(&response(StatusCode::SERVICE_UNAVAILABLE)));
+		assert!(!retry.is_retryable(&response(StatusCode::BAD_GATEWAY)));
+		let retry = AwsRetry {
+			throttling: false,
+			..AwsRetry::default()
+		};
+		assert!(!retry.is_retryable(&response(StatusCode::SERVICE_UNAVAILABLE)));
+		assert!(!retry.is_retryable(&response(StatusCode::TOO_MANY_REQUESTS)));
+		assert!(retry.is_retryable(&response(StatusCode::BAD_GATEWAY)));
+	}
+
+	#[test]
+	fn delay() {
+		let retry = AwsRetry {
+			base_delay: Duration::from_millis(100),
+			max_delay: Duration::from_secs(1),
+			..AwsRetry::default()
+		};
+		for _ in 0..100 {
+			for (retries, full) in &[(0, 100), (1, 200), (3, 800), (4, 1000), (40, 1000)] {
+				let full = Duration::from_millis(*full);
+				let delay = retry.delay(*retries);
+				assert!(full / 2 <= delay && delay <= full, "{:?} {:?}", delay, full);
+			}
+		}
+		let retry = AwsRetry {
+			base_delay: Duration::from_secs(u64::max_value()),
+			..AwsRetry::default()
+		};
+		assert!(retry.delay(31) <= retry.max_delay);
+	}
+}
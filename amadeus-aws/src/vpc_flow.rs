@@ -0,0 +1,308 @@
+#![allow(unused_qualifications)]
+
+use async_compression::futures::bufread::GzipDecoder;
+use chrono::{TimeZone, Utc};
+use futures::{
+	future::Either, io::BufReader, AsyncBufReadExt, FutureExt, Stream, StreamExt, TryStreamExt
+};
+use recycle::VecExt;
+use rusoto_s3::{GetObjectRequest, S3Client};
+use serde::{Deserialize, Serialize};
+use serde_closure::FnMutNamed;
+use std::convert::identity;
+
+use amadeus_core::{
+	file::File, into_par_stream::IteratorExt, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
+};
+use amadeus_types::{Data, DateTime, IpAddr};
+
+use super::{
+	columns::Columns, parse_lines, get_object, AwsCredentials, AwsError, AwsRegion, AwsRetry, Ref, S3Directory, RUSOTO_DISPATCHER
+};
+
+/// VPC Flow Logs, as delivered to an S3 bucket in the text format.
+///
+/// Fields are mapped using the header line of each file, so both the default format and custom
+/// formats including fields from later versions can be read; fields not in the format are `None`.
+/// Lines that can't be parsed are returned as [`AwsError::MalformedLine`], unless
+/// [`skip_malformed`](VpcFlow::skip_malformed) is set.
+#[derive(Clone, Debug)]
+pub struct VpcFlow {
+	region: AwsRegion,
+	bucket: String,
+	objects: Vec<String>,
+	credentials: AwsCredentials,
+	skip_malformed: bool,
+	retry: AwsRetry,
+}
+impl VpcFlow {
+	pub async fn new(region: AwsRegion, bucket: &str, prefix: &str) -> Result<Self, AwsError> {
+		Self::new_with(region, bucket, prefix, AwsCredentials::Environment).await
+	}
+	pub async fn new_with(
+		region: AwsRegion, bucket: &str, prefix: &str, credentials: AwsCredentials,
+	) -> Result<Self, AwsError> {
+		let objects = S3Directory::new_with(region.clone(), bucket, prefix, credentials.clone())
+			.partitions()
+			.await?
+			.map(|partition| partition.key);
+
+		Ok(Self {
+			region,
+			bucket: bucket.to_owned(),
+			objects,
+			credentials,
+			skip_malformed: false,
+			retry: AwsRetry::default(),
+		})
+	}
+	/// How failed requests to get the objects are retried. Listing them when constructed is
+	/// retried per [`AwsRetry::default()`].
+	pub fn retry(mut self, retry: AwsRetry) -> Self {
+		self.retry = retry;
+		self
+	}
+	/// Skip lines that can't be parsed, logging a warning for each, rather than returning an
+	/// error.
+	pub fn skip_malformed(mut self, skip_malformed: bool) -> Self {
+		self.skip_malformed = skip_malformed;
+		self
+	}
+}
+
+#[cfg(not(nightly))]
+type Output = std::pin::Pin<Box<dyn Stream<Item = Result<VpcFlowRow, AwsError>> + Send>>;
+#[cfg(nightly)]
+type Output = impl Stream<Item = Result<VpcFlowRow, AwsError>> + Send;
+
+FnMutNamed! {
+	pub type Closure<> = |self, credentials: AwsCredentials, region: AwsRegion, bucket: String, skip_malformed: bool, retry: AwsRetry|key=> String| -> Output where {
+		let (credentials, region, bucket, skip_malformed, retry) = (
+			self.credentials.clone(),
+			self.region.clone(),
+			self.bucket.clone(),
+			self.skip_malformed,
+			self.retry,
+		);
+		#[allow(clippy::let_and_return)]
+		let ret = async move {
+			let client = S3Client::new_with(
+				Ref(once_cell::sync::Lazy::force(&RUSOTO_DISPATCHER)),
+				credentials,
+				region,
+			);
+			let request = GetObjectRequest {
+				bucket,
+				key: key.clone(),
+				..GetObjectRequest::default()
+			};
+			let rows = get_object(&client, &retry, request)
+				.await
+				.map_err(AwsError::from)
+				.map(|body| {
+					let body = BufReader::new(TryStreamExt::into_async_read(body));
+					// Content-Encoding isn't set, so decode by extension
+					let body = if key.ends_with(".gz") {
+						let mut body = GzipDecoder::new(body);
+						body.multiple_members(true);
+						Either::Left(BufReader::new(body))
+					} else {
+						Either::Right(body)
+					};
+					let mut columns = None;
+					parse_lines(body.lines(), key, skip_malformed, move |line| {
+						if line.trim().is_empty() {
+							None
+						} else if let Some(columns) = &columns {
+							Some(VpcFlowRow::from_line(columns, line))
+						} else {
+							columns = Some(Columns::new(line.split_whitespace()));
+							None
+						}
+					})
+				});
+			ResultExpandIter::new(rows)
+		}
+		.flatten_stream()
+		.map(|x: Result<Result<VpcFlowRow, _>, _>| x.and_then(identity));
+		#[cfg(not(nightly))]
+		let ret = ret.boxed();
+		ret
+	}
+}
+
+impl Source for VpcFlow {
+	type Item = VpcFlowRow;
+	type Error = AwsError;
+
+	type ParStream = DistParStream<Self::DistStream>;
+	#[cfg(not(nightly))]
+	#[allow(clippy::type_complexity)]
+	type DistStream = amadeus_core::par_stream::FlatMap<
+		amadeus_core::into_par_stream::PartitionsDistStream<std::vec::IntoIter<String>>,
+		Closure,
+	>;
+	#[cfg(nightly)]
+	type DistStream = impl DistributedStream<Item = Result<Self::Item, Self::Error>>;
+
+	fn par_stream(self) -> Self::ParStream {
+		DistParStream::new(self.dist_stream())
+	}
+	#[allow(clippy::let_and_return)]
+	fn dist_stream(self) -> Self::DistStream {
+		let Self {
+			bucket,
+			region,
+			objects,
+			credentials,
+			skip_malformed,
+			retry,
+		} = self;
+		objects.into_iter().dist_partitions().flat_map(Closure::new(
+			credentials,
+			region,
+			bucket,
+			skip_malformed,
+			retry,
+		))
+	}
+}
+
+/// A flow, or the absence of one, in a capture window of a network interface.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+pub struct VpcFlowRow {
+	pub version: Option<u8>,
+	pub account_id: Option<String>,
+	pub interface_id: Option<String>,
+	pub src_addr: Option<IpAddr>,
+	pub dst_addr: Option<IpAddr>,
+	pub src_port: Option<u16>,
+	pub dst_port: Option<u16>,
+	/// The IANA protocol number, such as 6 for TCP.
+	pub protocol: Option<u8>,
+	pub packets: Option<u64>,
+	pub bytes: Option<u64>,
+	pub start: Option<DateTime>,
+	pub end: Option<DateTime>,
+	/// `ACCEPT` or `REJECT`.
+	pub action: Option<String>,
+	/// `OK`, `NODATA` or `SKIPDATA`.
+	pub log_status: Option<String>,
+	pub vpc_id: Option<String>,
+	pub subnet_id: Option<String>,
+	pub instance_id: Option<String>,
+	pub tcp_flags: Option<u16>,
+	/// `IPv4`, `IPv6` or `EFA`.
+	pub kind: Option<String>,
+	pub pkt_src_addr: Option<IpAddr>,
+	pub pkt_dst_addr: Option<IpAddr>,
+	pub region: Option<String>,
+	pub az_id: Option<String>,
+	pub sublocation_type: Option<String>,
+	pub sublocation_id: Option<String>,
+	pub pkt_src_aws_service: Option<String>,
+	pub pkt_dst_aws_service: Option<String>,
+	/// `ingress` or `egress`.
+	pub flow_direction: Option<String>,
+	pub traffic_path: Option<u8>,
+}
+impl Data for VpcFlowRow {
+	type Vec = Vec<Self>;
+	type DynamicType = ();
+
+	fn new_vec(_type: Self::DynamicType) -> Self::Vec {
+		Vec::new()
+	}
+}
+impl VpcFlowRow {
+	fn from_line(columns: &Columns, line: &str) -> Result<Self, String> {
+		let values = columns.values(line.split_whitespace()).exact()?;
+		let string = |field: &str| values.optional(field).map(str::to_owned);
+		let time = |field: &str| -> Result<Option<DateTime>, String> {
+			values
+				.parse_optional(field)?
+				.map(|seconds| {
+					Utc.timestamp_opt(seconds, 0)
+						.single()
+						.map(|time| DateTime::from_chrono(&time))
+						.ok_or_else(|| format!("invalid {} {}", field, seconds))
+				})
+				.transpose()
+		};
+		Ok(Self {
+			version: values.parse_optional("version")?,
+			account_id: string("account-id"),
+			interface_id: string("interface-id"),
+			src_addr: values.parse_optional("srcaddr")?,
+			dst_addr: values.parse_optional("dstaddr")?,
+			src_port: values.parse_optional("srcport")?,
+			dst_port: values.parse_optional("dstport")?,
+			protocol: values.parse_optional("protocol")?,
+			packets: values.parse_optional("packets")?,
+			bytes: values.parse_optional("bytes")?,
+			start: time("start")?,
+			end: time("end")?,
+			action: string("action"),
+			log_status: string("log-status"),
+			vpc_id: string("vpc-id"),
+			subnet_id: string("subnet-id"),
+			instance_id: string("instance-id"),
+			tcp_flags: values.parse_optional("tcp-flags")?,
+			kind: string("type"),
+			pkt_src_addr: values.parse_optional("pkt-srcaddr")?,
+			pkt_dst_addr: values.parse_optional("pkt-dstaddr")?,
+			region: string("region"),
+			az_id: string("az-id"),
+			sublocation_type: string("sublocation-type"),
+			sublocation_id: string("sublocation-id"),
+			pkt_src_aws_service: string("pkt-src-aws-service"),
+			pkt_dst_aws_service: string("pkt-dst-aws-service"),
+			flow_direction: string("flow-direction"),
+			traffic_path: values.parse_optional("traffic-path")?,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn default_format() {
+		let columns = Columns::new("version account-id interface-id srcaddr dstaddr srcport dstport protocol packets bytes start end action log-status".split(' '));
+		let row = VpcFlowRow::from_line(&columns, "2 123456789010 eni-1235b8ca123456789 172.31.16.139 172.31.16.21 20641 22 6 20 4249 1418530010 1418530070 ACCEPT OK").unwrap();
+		assert_eq!(row.version, Some(2));
+		assert_eq!(row.src_addr, Some("172.31.16.139".parse().unwrap()));
+		assert_eq!(row.dst_port, Some(22));
+		assert_eq!(row.bytes, Some(4249));
+		assert_eq!(row.action.as_deref(), Some("ACCEPT"));
+		assert_eq!(row.vpc_id, None);
+
+		let row = VpcFlowRow::from_line(
+			&columns,
+			"2 123456789010 eni-11111111aaaaaaaaa - - - - - - - 1431280876 1431280934 - NODATA",
+		)
+		.unwrap();
+		assert_eq!(row.src_addr, None);
+		assert_eq!(row.log_status.as_deref(), Some("NODATA"));
+	}
+
+	#[test]
+	fn custom_format() {
+		let columns = Columns::new("version vpc-id subnet-id instance-id interface-id account-id type srcaddr dstaddr srcport dstport pkt-srcaddr pkt-dstaddr protocol bytes packets start end action tcp-flags log-status flow-direction traffic-path".split(' '));
+		let row = VpcFlowRow::from_line(&columns, "5 vpc-abcdefab012345678 subnet-aaaaaaaa012345678 i-01234567890123456 eni-1235b8ca123456789 123456789012 IPv4 52.213.180.42 10.0.0.62 43416 5001 52.213.180.42 10.0.0.62 6 568 8 1566848875 1566848933 ACCEPT 2 OK ingress 1").unwrap();
+		assert_eq!(row.tcp_flags, Some(2));
+		assert_eq!(row.kind.as_deref(), Some("IPv4"));
+		assert_eq!(row.flow_direction.as_deref(), Some("ingress"));
+		assert_eq!(row.traffic_path, Some(1));
+
+		assert_eq!(
+			VpcFlowRow::from_line(&columns, "5 vpc-abcdefab012345678 subnet-aaaaaaaa012345678 i-01234567890123456 eni-1235b8ca123456789 123456789012 IPv4 52.213.180.42 10.0.0.62 http 5001 52.213.180.42 10.0.0.62 6 568 8 1566848875 1566848933 ACCEPT 2 OK ingress 1"),
+			Err("invalid srcport \"http\"".to_owned())
+		);
+		assert_eq!(
+			VpcFlowRow::from_line(&columns, "5 vpc-abcdefab012345678 subnet-aaaaaaaa012345678 i-01234567890123456 eni-1235b8ca123456789 123456789012 IPv4 52.213.180.42 10.0.0.62 43416 5001"),
+			Err("expected 23 values, found 11".to_owned())
+		);
+	}
+}
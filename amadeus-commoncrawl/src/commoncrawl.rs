@@ -1,25 +1,112 @@
 use futures::{pin_mut, ready, AsyncRead, AsyncReadExt, Stream};
 use pin_project::pin_project;
 use std::{
-	borrow::Cow, future::Future, io::{self, Read}, iter, pin::Pin, task::{Context, Poll}
+	borrow::Cow, future::Future, io::{self, Read}, iter, ops::Range, pin::Pin, task::{Context, Poll}
 };
 use url::Url;
 
-use amadeus_types::Webpage;
+use amadeus_types::{IpAddr, Webpage};
 
-use super::parser;
+use super::{
+	parser::{self, RecordType}, WarcRecord
+};
 
 const BUF: usize = 1 << 22; // 4 MiB
 const CHOMP: usize = 1 << 13; // 8 KiB
 
+/// The input of a parser, buffered so that each record can be parsed once it's all been read.
+#[derive(Clone, Debug)]
+struct Buffer {
+	res: Vec<u8>,
+	offset: usize,
+	done: bool,
+}
+impl Buffer {
+	fn new() -> Self {
+		Self {
+			res: Vec::with_capacity(BUF),
+			offset: 0,
+			done: false,
+		}
+	}
+
+	/// Parse the next record, calling `read` to read up to the given number of bytes more into
+	/// the buffer whenever it doesn't hold a whole record. `f` is called with each record and the
+	/// range of the buffer holding its content, and records it returns `None` for are skipped.
+	fn poll_next<R>(
+		&mut self, mut read: impl FnMut(&mut Vec<u8>, u64) -> Poll<io::Result<u64>>,
+		mut f: impl FnMut(&parser::Record<'_>, Range<usize>) -> Option<R>,
+	) -> Poll<Result<Option<R>, io::Error>> {
+		let mut eof = false;
+		while !self.done {
+			let _ = self.res.splice(..self.offset, iter::empty());
+			self.offset = 0;
+			match parser::record(&self.res) {
+				Ok((rem, record)) if rem.len() >= 4 => {
+					self.offset = self.res.len() - rem.len() + 4; // 4 is \r\n\r\n
+					let start = (record.content.as_ptr() as usize) - (self.res.as_ptr() as usize);
+					let content = start..start + record.content.len();
+					match f(&record, content) {
+						Some(record) => return Poll::Ready(Ok(Some(record))),
+						None => continue,
+					}
+				}
+				Ok(_) | Err(nom::Err::Incomplete(_)) => (),
+				Err(nom::Err::Error(nom::Context::Code(value, kind)))
+				| Err(nom::Err::Failure(nom::Context::Code(value, kind))) => {
+					self.done = true;
+					let value = String::from_utf8_lossy(value);
+					let message = match kind {
+						nom::ErrorKind::Custom(parser::INVALID_TYPE) => {
+							format!("unknown WARC-Type {:?}", value)
+						}
+						nom::ErrorKind::Custom(parser::INVALID_LENGTH) => {
+							format!("invalid Content-Length {:?}", value)
+						}
+						_ => String::from("invalid WARC record header"),
+					};
+					return Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, message)));
+				}
+			}
+			if eof {
+				self.done = true;
+				if !self.res.is_empty() {
+					return Poll::Ready(Err(io::Error::new(
+						io::ErrorKind::UnexpectedEof,
+						"WARC file ends mid-record",
+					)));
+				}
+				break;
+			}
+			if self.res.len() == BUF {
+				self.done = true;
+				return Poll::Ready(Err(io::Error::new(
+					io::ErrorKind::InvalidData,
+					format!("WARC record larger than the {} byte buffer", BUF),
+				)));
+			}
+			let max = CHOMP.min(BUF - self.res.len()) as u64;
+			let n = match ready!(read(&mut self.res, max)) {
+				Ok(n) => n,
+				Err(err) => {
+					self.done = true;
+					return Poll::Ready(Err(err));
+				}
+			};
+			assert_eq!(self.res.capacity(), BUF);
+			eof = n == 0;
+		}
+		Poll::Ready(Ok(None))
+	}
+}
+
 #[pin_project]
 #[derive(Clone, Debug)]
 pub(crate) struct WarcParser<I> {
 	#[pin]
 	input: I,
 	state: WarcParserState,
-	res: Vec<u8>,
-	offset: usize,
+	buffer: Buffer,
 }
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 enum WarcParserState {
@@ -27,97 +114,68 @@ enum WarcParserState {
 	Request,
 	Response,
 	Metadata,
-	Done,
 }
 impl<I> WarcParser<I> {
 	pub(crate) fn new(input: I) -> WarcParser<I> {
 		WarcParser {
 			input,
 			state: WarcParserState::Info,
-			res: Vec::with_capacity(BUF),
-			offset: 0,
+			buffer: Buffer::new(),
 		}
 	}
+
+	/// Step through the request, response and metadata records of a Common Crawl file, returning
+	/// the IP, URL and content of the response records.
+	fn record(
+		state: &mut WarcParserState, record: &parser::Record<'_>, content: Range<usize>,
+	) -> Option<(IpAddr, Url, Range<usize>)> {
+		*state = match *state {
+			WarcParserState::Info => {
+				assert!(record.type_ == RecordType::WARCInfo);
+				WarcParserState::Request
+			}
+			WarcParserState::Request => {
+				assert!(record.type_ == RecordType::Request);
+				WarcParserState::Response
+			}
+			WarcParserState::Response => {
+				assert!(record.type_ == RecordType::Response);
+				*state = WarcParserState::Metadata;
+				return Some((
+					record.ip_address.unwrap().parse().unwrap(),
+					Url::parse(record.target_uri.unwrap()).unwrap(),
+					content,
+				));
+			}
+			WarcParserState::Metadata => {
+				assert!(record.type_ == RecordType::Metadata);
+				WarcParserState::Request
+			}
+		};
+		None
+	}
 }
 impl<I> WarcParser<I>
 where
 	I: Read,
 {
 	pub(crate) fn next_borrowed(&mut self) -> Result<Option<Webpage<'_>>, io::Error> {
-		if let WarcParserState::Done = self.state {
-			return Ok(None);
-		}
-		'chomp: loop {
-			assert!(
-				self.res.len() < BUF,
-				"Individual record > configured BUF {:?}",
-				BUF
-			);
-			let n = io::copy(
-				&mut self
-					.input
-					.by_ref()
-					.take(CHOMP.min(BUF - self.res.len()) as u64),
-				&mut self.res,
-			)?;
-			assert_eq!(self.res.capacity(), BUF);
-			if n == 0 && self.offset == self.res.len() {
-				assert_eq!(self.state, WarcParserState::Request);
-				self.state = WarcParserState::Done;
-				return Ok(None);
-			}
-
-			loop {
-				let _ = self.res.splice(..self.offset, iter::empty());
-				self.offset = 0;
-				if self.offset == self.res.len() {
-					continue 'chomp;
-				}
-				let record = match parser::record(&self.res[self.offset..]) {
-					Ok((rem, record)) => {
-						let record_len = self.res.len() - self.offset - rem.len() + 4; // 4 is \r\n\r\n
-						if self.offset + record_len > self.res.len() {
-							continue 'chomp;
-						}
-						self.offset += record_len;
-						record
-					}
-					Err(nom::Err::Incomplete(_)) => {
-						continue 'chomp;
-					}
-					_ => panic!(),
-				};
-				self.state = match self.state {
-					WarcParserState::Info => {
-						assert!(record.type_ == parser::RecordType::WARCInfo);
-						WarcParserState::Request
-					}
-					WarcParserState::Request => {
-						assert!(record.type_ == parser::RecordType::Request);
-						WarcParserState::Response
-					}
-					WarcParserState::Response => {
-						assert!(record.type_ == parser::RecordType::Response);
-						self.state = WarcParserState::Metadata;
-
-						let content: *const u8 = record.content.as_ptr();
-						let buffer: *const u8 = self.res.as_slice().as_ptr();
-						let start = (content as usize) - (buffer as usize);
-						let end = start + record.content.len();
-						return Ok(Some(Webpage {
-							ip: record.ip_address.unwrap().parse().unwrap(),
-							url: Url::parse(record.target_uri.unwrap()).unwrap(),
-							contents: Cow::Borrowed(&self.res[start..end]),
-						}));
-					}
-					WarcParserState::Metadata => {
-						assert!(record.type_ == parser::RecordType::Metadata);
-						WarcParserState::Request
-					}
-					WarcParserState::Done => unreachable!(),
-				}
+		let (input, state) = (&mut self.input, &mut self.state);
+		let next = self.buffer.poll_next(
+			|res, max| Poll::Ready(io::copy(&mut input.by_ref().take(max), res)),
+			|record, content| Self::record(state, record, content),
+		);
+		Ok(match next {
+			Poll::Ready(next) => {
+				let res = &self.buffer.res;
+				next?.map(move |(ip, url, content)| Webpage {
+					ip,
+					url,
+					contents: Cow::Borrowed(&res[content]),
+				})
 			}
-		}
+			Poll::Pending => unreachable!(),
+		})
 	}
 }
 impl<I> WarcParser<I>
@@ -128,77 +186,21 @@ where
 		self: Pin<&mut Self>, cx: &mut Context,
 	) -> Poll<Result<Option<Webpage<'_>>, io::Error>> {
 		let mut self_ = self.project();
-		if let WarcParserState::Done = self_.state {
-			return Poll::Ready(Ok(None));
-		}
-		'chomp: loop {
-			assert!(
-				self_.res.len() < BUF,
-				"Individual record > configured BUF {:?}",
-				BUF
-			);
-			let from = (&mut self_.input).take(CHOMP.min(BUF - self_.res.len()) as u64);
-			let copy = futures::io::copy(from, self_.res);
-			pin_mut!(copy);
-			let n = ready!(copy.poll(cx))?;
-			assert_eq!(self_.res.capacity(), BUF);
-			if n == 0 && *self_.offset == self_.res.len() {
-				assert_eq!(*self_.state, WarcParserState::Request);
-				*self_.state = WarcParserState::Done;
-				return Poll::Ready(Ok(None));
-			}
-
-			loop {
-				let _ = self_.res.splice(..*self_.offset, iter::empty());
-				*self_.offset = 0;
-				if *self_.offset == self_.res.len() {
-					continue 'chomp;
-				}
-				let record = match parser::record(&self_.res[*self_.offset..]) {
-					Ok((rem, record)) => {
-						let record_len = self_.res.len() - *self_.offset - rem.len() + 4; // 4 is \r\n\r\n
-						if *self_.offset + record_len > self_.res.len() {
-							continue 'chomp;
-						}
-						*self_.offset += record_len;
-						record
-					}
-					Err(nom::Err::Incomplete(_)) => {
-						continue 'chomp;
-					}
-					_ => panic!(),
-				};
-				*self_.state = match *self_.state {
-					WarcParserState::Info => {
-						assert!(record.type_ == parser::RecordType::WARCInfo);
-						WarcParserState::Request
-					}
-					WarcParserState::Request => {
-						assert!(record.type_ == parser::RecordType::Request);
-						WarcParserState::Response
-					}
-					WarcParserState::Response => {
-						assert!(record.type_ == parser::RecordType::Response);
-						*self_.state = WarcParserState::Metadata;
-
-						let content: *const u8 = record.content.as_ptr();
-						let buffer: *const u8 = self_.res.as_slice().as_ptr();
-						let start = (content as usize) - (buffer as usize);
-						let end = start + record.content.len();
-						return Poll::Ready(Ok(Some(Webpage {
-							ip: record.ip_address.unwrap().parse().unwrap(),
-							url: Url::parse(record.target_uri.unwrap()).unwrap(),
-							contents: Cow::Borrowed(&self_.res[start..end]),
-						})));
-					}
-					WarcParserState::Metadata => {
-						assert!(record.type_ == parser::RecordType::Metadata);
-						WarcParserState::Request
-					}
-					WarcParserState::Done => unreachable!(),
-				}
-			}
-		}
+		let (input, state) = (&mut self_.input, &mut self_.state);
+		let next = ready!(self_.buffer.poll_next(
+			|res, max| {
+				let copy = futures::io::copy(input.as_mut().take(max), res);
+				pin_mut!(copy);
+				copy.poll(cx)
+			},
+			|record, content| Self::record(state, record, content),
+		))?;
+		let res = &self_.buffer.res;
+		Poll::Ready(Ok(next.map(move |(ip, url, content)| Webpage {
+			ip,
+			url,
+			contents: Cow::Borrowed(&res[content]),
+		})))
 	}
 }
 impl<I> Iterator for WarcParser<I>
@@ -225,3 +227,52 @@ where
 		)
 	}
 }
+
+/// Parses every record of a WARC file, or only those of `record_types` if given, rather than
+/// expecting the request, response and metadata records of a Common Crawl file.
+#[pin_project]
+#[derive(Clone, Debug)]
+pub(crate) struct RecordParser<I> {
+	#[pin]
+	input: I,
+	record_types: Option<Vec<RecordType>>,
+	buffer: Buffer,
+}
+impl<I> RecordParser<I> {
+	pub(crate) fn new(input: I, record_types: Option<Vec<RecordType>>) -> RecordParser<I> {
+		RecordParser {
+			input,
+			record_types,
+			buffer: Buffer::new(),
+		}
+	}
+}
+impl<I> Stream for RecordParser<I>
+where
+	I: AsyncRead,
+{
+	type Item = Result<WarcRecord, io::Error>;
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let mut self_ = self.project();
+		let (input, record_types) = (&mut self_.input, &*self_.record_types);
+		let next = ready!(self_.buffer.poll_next(
+			|res, max| {
+				let copy = futures::io::copy(input.as_mut().take(max), res);
+				pin_mut!(copy);
+				copy.poll(cx)
+			},
+			|record, _content| {
+				if let Some(record_types) = record_types {
+					if !record_types.contains(&record.type_) {
+						return None;
+					}
+				}
+				Some(
+					WarcRecord::from_record(record)
+						.map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message)),
+				)
+			},
+		));
+		Poll::Ready(next.and_then(Option::transpose).transpose())
+	}
+}
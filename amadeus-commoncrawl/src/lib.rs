@@ -28,20 +28,30 @@
 
 mod commoncrawl;
 mod parser;
+mod warc;
+mod wat;
+mod wet;
 
 use async_compression::futures::bufread::GzipDecoder; // TODO: use stream or https://github.com/alexcrichton/flate2-rs/pull/214
-use futures::{io::BufReader, AsyncBufReadExt, FutureExt, Stream, StreamExt, TryStreamExt};
+use futures::{
+	io::BufReader, AsyncBufReadExt, AsyncRead, FutureExt, Stream, StreamExt, TryStreamExt
+};
 use reqwest_resume::ClientExt;
 use serde_closure::FnMutNamed;
 use std::{io, time};
 
 use amadeus_core::{
-	into_par_stream::IntoDistributedStream, par_stream::DistributedStream, util::DistParStream, Source
+	into_par_stream::IteratorExt, par_stream::{record_bytes_read, DistributedStream}, util::{DistParStream, ResultExpandIter}, Source
 };
 use amadeus_types::Webpage;
 
 use commoncrawl::WarcParser;
 
+pub use parser::RecordType as WarcRecordType;
+pub use warc::{Warc, WarcDirectory, WarcError, WarcRecord};
+pub use wat::{CommonCrawlWat, WatRecord};
+pub use wet::{CommonCrawlWet, WetRecord};
+
 /// See https://commoncrawl.s3.amazonaws.com/crawl-data/index.html
 #[derive(Clone, Debug)]
 pub struct CommonCrawl {
@@ -50,36 +60,58 @@ pub struct CommonCrawl {
 impl CommonCrawl {
 	/// CC-MAIN-2020-24
 	pub async fn new(id: &str) -> Result<Self, reqwest::Error> {
-		let url = format!(
-			"https://commoncrawl.s3.amazonaws.com/crawl-data/{}/warc.paths.gz",
-			id
-		);
-		let body = reqwest::ClientBuilder::new()
-			.timeout(time::Duration::new(120, 0))
-			.build()
-			.unwrap()
-			.resumable()
-			.get(url.parse().unwrap())
-			.send();
-		let body = body
-			.await?
-			.bytes_stream()
-			.map_err(|e| io::Error::new(io::ErrorKind::Other, e));
-		let body = BufReader::new(body.into_async_read());
-		let mut body = GzipDecoder::new(body); // Content-Encoding isn't set, so decode manually
-		body.multiple_members(true);
-
-		let urls = BufReader::new(body)
-			.lines()
-			.map(|url: Result<String, io::Error>| -> String {
-				format!("http://commoncrawl.s3.amazonaws.com/{}", url.unwrap())
-			})
-			.collect()
-			.await;
+		let urls = paths(id, "warc").await?;
 		Ok(Self { urls })
 	}
 }
 
+/// The URLs of the files of crawl `id` listed in its `{kind}.paths.gz`, where `kind` is `warc`,
+/// `wet` or `wat`.
+async fn paths(id: &str, kind: &str) -> Result<Vec<String>, reqwest::Error> {
+	let url = format!(
+		"https://commoncrawl.s3.amazonaws.com/crawl-data/{}/{}.paths.gz",
+		id, kind
+	);
+	let body = reqwest::ClientBuilder::new()
+		.timeout(time::Duration::new(120, 0))
+		.build()
+		.unwrap()
+		.resumable()
+		.get(url.parse().unwrap())
+		.send();
+	let body = body
+		.await?
+		.bytes_stream()
+		.map_err(|e| io::Error::new(io::ErrorKind::Other, e));
+	let body = BufReader::new(body.into_async_read());
+	let mut body = GzipDecoder::new(body); // Content-Encoding isn't set, so decode manually
+	body.multiple_members(true);
+
+	let urls = BufReader::new(body)
+		.lines()
+		.map(|url: Result<String, io::Error>| -> String {
+			format!("http://commoncrawl.s3.amazonaws.com/{}", url.unwrap())
+		})
+		.collect()
+		.await;
+	Ok(urls)
+}
+
+/// Get the gzipped file at `url`, decompressing it as it streams in.
+async fn get(url: &str) -> Result<impl AsyncRead + Send, io::Error> {
+	let body = reqwest_resume::get(url.parse().unwrap())
+		.await
+		.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+	let body = body
+		.bytes_stream()
+		.inspect_ok(|chunk| record_bytes_read(chunk.len()))
+		.map_err(|e| io::Error::new(io::ErrorKind::Other, e));
+	let body = BufReader::new(body.into_async_read());
+	let mut body = GzipDecoder::new(body); // Content-Encoding isn't set, so decode manually
+	body.multiple_members(true);
+	Ok(body)
+}
+
 #[cfg(not(nightly))]
 type Output = std::pin::Pin<Box<dyn Stream<Item = Result<Webpage<'static>, io::Error>> + Send>>;
 #[cfg(nightly)]
@@ -88,17 +120,12 @@ type Output = impl Stream<Item = Result<Webpage<'static>, io::Error>> + Send;
 FnMutNamed! {
 	pub type Closure<> = |self|url=> String| -> Output where {
 		#[allow(clippy::let_and_return)]
-		let ret = async move {
-				let body = reqwest_resume::get(url.parse().unwrap()).await.unwrap();
-				let body = body
-					.bytes_stream()
-					.map_err(|e| io::Error::new(io::ErrorKind::Other, e));
-				let body = BufReader::new(body.into_async_read());
-				let mut body = GzipDecoder::new(body); // Content-Encoding isn't set, so decode manually
-				body.multiple_members(true);
-				WarcParser::new(body)
-			}
-			.flatten_stream();
+		let ret = async move { Ok(WarcParser::new(get(&url).await?)) }
+			.map(ResultExpandIter::new)
+			.flatten_stream()
+			.map(|webpage: Result<Result<Webpage<'static>, io::Error>, io::Error>| {
+				webpage.and_then(|webpage| webpage)
+			});
 		#[cfg(not(nightly))]
 		let ret = ret.boxed();
 		ret
@@ -113,7 +140,7 @@ impl Source for CommonCrawl {
 	#[cfg(not(nightly))]
 	#[allow(clippy::type_complexity)]
 	type DistStream = amadeus_core::par_stream::FlatMap<
-		amadeus_core::into_par_stream::IterDistStream<std::vec::IntoIter<String>>,
+		amadeus_core::into_par_stream::PartitionsDistStream<std::vec::IntoIter<String>>,
 		Closure,
 	>;
 	#[cfg(nightly)]
@@ -124,6 +151,9 @@ impl Source for CommonCrawl {
 	}
 	#[allow(clippy::let_and_return)]
 	fn dist_stream(self) -> Self::DistStream {
-		self.urls.into_dist_stream().flat_map(Closure::new())
+		self.urls
+			.into_iter()
+			.dist_partitions()
+			.flat_map(Closure::new())
 	}
 }
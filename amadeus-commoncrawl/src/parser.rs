@@ -1,11 +1,15 @@
 //! Web ARChive format parser
 //!
 //! Takes data and separates records in headers and content.
-use nom::{complete, do_parse, many1, map_res, named, opt, space, tag, Err, IResult, Needed};
+use nom::{
+	complete, do_parse, many1, map_res, named, opt, space, tag, Context, Err, ErrorKind, IResult, Needed
+};
+use serde::{Deserialize, Serialize};
 use std::{fmt, str};
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub(crate) enum RecordType {
+/// The `WARC-Type` of a record.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub enum RecordType {
 	WARCInfo,
 	Response,
 	Resource,
@@ -16,8 +20,8 @@ pub(crate) enum RecordType {
 	Continuation,
 }
 impl RecordType {
-	pub(crate) fn parse(x: &str) -> RecordType {
-		match x {
+	pub(crate) fn parse(x: &str) -> Option<RecordType> {
+		Some(match x {
 			"warcinfo" => RecordType::WARCInfo,
 			"response" => RecordType::Response,
 			"resource" => RecordType::Resource,
@@ -26,7 +30,19 @@ impl RecordType {
 			"revisit" => RecordType::Revisit,
 			"conversion" => RecordType::Conversion,
 			"continuation" => RecordType::Continuation,
-			_ => panic!("bad RecordType"),
+			_ => return None,
+		})
+	}
+	pub fn as_str(self) -> &'static str {
+		match self {
+			RecordType::WARCInfo => "warcinfo",
+			RecordType::Response => "response",
+			RecordType::Resource => "resource",
+			RecordType::Request => "request",
+			RecordType::Metadata => "metadata",
+			RecordType::Revisit => "revisit",
+			RecordType::Conversion => "conversion",
+			RecordType::Continuation => "continuation",
 		}
 	}
 }
@@ -36,6 +52,7 @@ impl RecordType {
 pub(crate) struct Record<'a> {
 	// lazy design should not use pub(crate)
 	/// WArc headers
+	pub(crate) headers: Vec<(&'a str, &'a str)>,
 	pub(crate) type_: RecordType,
 	pub(crate) target_uri: Option<&'a str>,
 	pub(crate) ip_address: Option<&'a str>,
@@ -131,6 +148,13 @@ named!(warc_header<&[u8], ((&str, &str), Vec<(&str,&str)>) >,
 	)
 );
 
+/// The [`ErrorKind::Custom`] of a record with a missing or unknown `WARC-Type`, whose input is the
+/// type.
+pub(crate) const INVALID_TYPE: u32 = 1;
+/// The [`ErrorKind::Custom`] of a record with an invalid `Content-Length`, whose input is the
+/// length.
+pub(crate) const INVALID_LENGTH: u32 = 2;
+
 /// Parses one record and returns an IResult from nom
 ///
 /// IResult<&[u8], Record>
@@ -168,7 +192,12 @@ pub(crate) fn record(input: &[u8]) -> IResult<&[u8], Record> {
 		for &(k, v) in &headers {
 			match k {
 				"Content-Length" => {
-					let length_number = v.parse::<usize>().unwrap();
+					let length_number = v.parse::<usize>().map_err(|_| {
+						Err::Failure(Context::Code(
+							v.as_bytes(),
+							ErrorKind::Custom(INVALID_LENGTH),
+						))
+					})?;
 					if length_number <= i.len() {
 						content = Some(&i[0..length_number]);
 						i = &i[length_number..];
@@ -187,12 +216,17 @@ pub(crate) fn record(input: &[u8]) -> IResult<&[u8], Record> {
 		}
 		match content {
 			Some(content) => {
+				let type_ = type_.unwrap_or("");
+				let type_ = RecordType::parse(type_).ok_or(Err::Failure(Context::Code(
+					type_.as_bytes(),
+					ErrorKind::Custom(INVALID_TYPE),
+				)))?;
 				let entry = Record {
-					// headers: h,
-					type_: RecordType::parse(type_.unwrap()),
+					type_,
 					target_uri,
 					ip_address,
 					content,
+					headers,
 				};
 				Ok((i, entry))
 			}
@@ -0,0 +1,319 @@
+use async_compression::futures::bufread::GzipDecoder;
+use async_trait::async_trait;
+use educe::Educe;
+use futures::{
+	future::Either, io::BufReader, stream, AsyncBufReadExt, FutureExt, Stream, StreamExt
+};
+use serde::{Deserialize, Serialize};
+use serde_closure::FnMutNamed;
+use std::{
+	error, fmt::{self, Debug, Display}, io
+};
+
+use amadeus_core::{
+	file::{Directory, File, Page, Partition, PathBuf}, into_par_stream::IteratorExt, par_stream::DistributedStream, util::{DistParStream, IoError, ResultExpandIter}, Source
+};
+use amadeus_types::{Data, DateTime, IpAddr};
+
+use super::{
+	commoncrawl::RecordParser, parser::{self, RecordType}
+};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The records of WARC files, such as those written by a crawler. Gzipped files are detected by
+/// their contents rather than name, and may be compressed either per record as `.warc.gz` files
+/// are or as a whole.
+#[derive(Educe)]
+#[educe(Clone, Debug)]
+pub struct Warc<File>
+where
+	File: amadeus_core::file::File,
+{
+	partitions: Vec<File::Partition>,
+	record_types: Option<Vec<RecordType>>,
+}
+impl<F> Warc<F>
+where
+	F: File,
+{
+	pub async fn new(file: F) -> Result<Self, <Self as Source>::Error> {
+		Ok(Self {
+			partitions: file.partitions().await.map_err(WarcError::File)?,
+			record_types: None,
+		})
+	}
+	/// Only yield records of these types, such as just `response`s. Other records are skipped
+	/// without being copied.
+	#[must_use]
+	pub fn record_types(mut self, record_types: &[RecordType]) -> Self {
+		self.record_types = Some(record_types.to_owned());
+		self
+	}
+}
+
+/// A record of a WARC file.
+///
+/// The headers most often needed are parsed into fields, and all of them are in `headers`. Blocks
+/// of HTTP requests and responses are split into the status code, headers and payload.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct WarcRecord {
+	pub record_type: RecordType,
+	pub record_id: String,
+	pub date: DateTime,
+	pub target_uri: Option<String>,
+	pub ip_address: Option<IpAddr>,
+	pub content_type: Option<String>,
+	pub block_digest: Option<String>,
+	pub payload_digest: Option<String>,
+	pub concurrent_to: Option<String>,
+	pub headers: Vec<(String, String)>,
+	/// For HTTP responses.
+	pub http_status: Option<u16>,
+	/// For HTTP requests and responses.
+	pub http_headers: Option<Vec<(String, String)>>,
+	/// The block, less the HTTP status line and headers if any.
+	pub payload: Vec<u8>,
+}
+impl Data for WarcRecord {
+	type Vec = Vec<Self>;
+	type DynamicType = ();
+
+	fn new_vec(_type: Self::DynamicType) -> Self::Vec {
+		Vec::new()
+	}
+}
+impl WarcRecord {
+	pub(crate) fn from_record(record: &parser::Record<'_>) -> Result<Self, String> {
+		let header = |name: &str| {
+			record
+				.headers
+				.iter()
+				.find(|(key, _)| key.eq_ignore_ascii_case(name))
+				.map(|&(_, value)| value)
+		};
+		let record_id = header("WARC-Record-ID").ok_or("missing WARC-Record-ID")?;
+		let date = header("WARC-Date").ok_or("missing WARC-Date")?;
+		let date = chrono::DateTime::parse_from_rfc3339(date)
+			.map(|date| DateTime::from_chrono(&date))
+			.map_err(|err| format!("invalid WARC-Date {:?}: {}", date, err))?;
+		let ip_address = header("WARC-IP-Address")
+			.map(|ip| {
+				ip.parse()
+					.map_err(|_| format!("invalid WARC-IP-Address {:?}", ip))
+			})
+			.transpose()?;
+		let content_type = header("Content-Type");
+		let message = content_type
+			.filter(|content_type| content_type.starts_with("application/http"))
+			.and_then(|_| http(record.content));
+		let (http_status, http_headers, payload) = match message {
+			Some((status, headers, payload)) => (status, Some(headers), payload),
+			None => (None, None, record.content),
+		};
+		Ok(Self {
+			record_type: record.type_,
+			record_id: record_id.to_owned(),
+			date,
+			target_uri: header("WARC-Target-URI").map(ToOwned::to_owned),
+			ip_address,
+			content_type: content_type.map(ToOwned::to_owned),
+			block_digest: header("WARC-Block-Digest").map(ToOwned::to_owned),
+			payload_digest: header("WARC-Payload-Digest").map(ToOwned::to_owned),
+			concurrent_to: header("WARC-Concurrent-To").map(ToOwned::to_owned),
+			headers: record
+				.headers
+				.iter()
+				.map(|&(key, value)| (key.to_owned(), value.to_owned()))
+				.collect(),
+			http_status,
+			http_headers,
+			payload: payload.to_owned(),
+		})
+	}
+}
+
+/// Split an HTTP message into the status code if it's a response, the headers, and the payload.
+/// Returns `None` if it isn't HTTP.
+#[allow(clippy::type_complexity)]
+fn http(block: &[u8]) -> Option<(Option<u16>, Vec<(String, String)>, &[u8])> {
+	let end = block.windows(4).position(|x| x == b"\r\n\r\n")?;
+	let head = String::from_utf8_lossy(&block[..end]);
+	let mut lines = head.split("\r\n");
+	let start_line = lines.next()?;
+	let status = if start_line.starts_with("HTTP/") {
+		Some(start_line.split(' ').nth(1)?.parse().ok()?)
+	} else {
+		None
+	};
+	let headers = lines
+		.map(|line| {
+			let colon = line.find(':')?;
+			let (key, value) = (&line[..colon], &line[colon + 1..]);
+			Some((key.trim().to_owned(), value.trim().to_owned()))
+		})
+		.collect::<Option<_>>()?;
+	Some((status, headers, &block[end + 4..]))
+}
+
+type Error<P, E> = WarcError<E, <P as Partition>::Error>;
+#[cfg(not(nightly))]
+type Output<P, E> = std::pin::Pin<Box<dyn Stream<Item = Result<WarcRecord, Error<P, E>>>>>;
+#[cfg(nightly)]
+type Output<P: Partition, E> = impl Stream<Item = Result<WarcRecord, Error<P, E>>>;
+
+FnMutNamed! {
+	pub type Closure<P, E> = |self, record_types: Option<Vec<RecordType>>|partition=> P| -> Output<P, E>
+	where
+		P: Partition,
+		E: 'static
+	{
+		let record_types = self.record_types.clone();
+		#[allow(clippy::let_and_return)]
+		let ret = async move {
+				Ok(stream::iter(
+					partition
+						.pages()
+						.await
+						.map_err(WarcError::Partition)?
+						.into_iter(),
+				)
+				.flat_map(move |page| {
+					let record_types = record_types.clone();
+					async move {
+						let mut body = BufReader::new(Box::pin(page.reader()));
+						let body = if body.fill_buf().await?.starts_with(&GZIP_MAGIC) {
+							let mut body = GzipDecoder::new(body);
+							body.multiple_members(true);
+							Either::Left(body)
+						} else {
+							Either::Right(body)
+						};
+						Ok(RecordParser::new(body, record_types))
+					}
+					.map(ResultExpandIter::new)
+					.flatten_stream()
+					.map(|record: Result<Result<WarcRecord, io::Error>, io::Error>| Ok(record??))
+				}))
+			}
+			.map(ResultExpandIter::new)
+			.flatten_stream()
+			.map(|record: Result<Result<WarcRecord, Error<P, E>>, Error<P, E>>| {
+				record.and_then(|record| record)
+			});
+		#[cfg(not(nightly))]
+		let ret = ret.boxed_local();
+		ret
+	}
+}
+
+impl<F> Source for Warc<F>
+where
+	F: File,
+{
+	type Item = WarcRecord;
+	type Error = WarcError<F::Error, <F::Partition as Partition>::Error>;
+
+	type ParStream = DistParStream<Self::DistStream>;
+	#[cfg(not(nightly))]
+	#[allow(clippy::type_complexity)]
+	type DistStream = amadeus_core::par_stream::FlatMap<
+		amadeus_core::into_par_stream::PartitionsDistStream<std::vec::IntoIter<F::Partition>>,
+		Closure<F::Partition, F::Error>,
+	>;
+	#[cfg(nightly)]
+	type DistStream = impl DistributedStream<Item = Result<Self::Item, Self::Error>>;
+
+	fn par_stream(self) -> Self::ParStream {
+		DistParStream::new(self.dist_stream())
+	}
+	#[allow(clippy::let_and_return)]
+	fn dist_stream(self) -> Self::DistStream {
+		self.partitions
+			.into_iter()
+			.dist_partitions()
+			.flat_map(Closure::new(self.record_types))
+	}
+}
+
+/// The `.warc` and `.warc.gz` files in a directory and its subdirectories, skipping hidden files.
+#[derive(Serialize, Deserialize)]
+pub struct WarcDirectory<D> {
+	directory: D,
+}
+impl<D> WarcDirectory<D> {
+	pub fn new(directory: D) -> Self {
+		Self { directory }
+	}
+}
+#[async_trait(?Send)]
+impl<D> File for WarcDirectory<D>
+where
+	D: Directory,
+	D::Partition: Debug,
+{
+	type Partition = D::Partition;
+	type Error = D::Error;
+
+	async fn partitions(self) -> Result<Vec<Self::Partition>, Self::Error> {
+		self.partitions_filter(|_| true).await
+	}
+}
+#[async_trait(?Send)]
+impl<D> Directory for WarcDirectory<D>
+where
+	D: Directory,
+	D::Partition: Debug,
+{
+	async fn partitions_filter<F>(
+		self, mut f: F,
+	) -> Result<Vec<<Self as File>::Partition>, <Self as File>::Error>
+	where
+		F: FnMut(&PathBuf) -> bool,
+	{
+		self.directory
+			.partitions_filter(|path| {
+				let skip = if path.is_file() {
+					let file_name = path.file_name().unwrap().to_string_lossy();
+					let file_name = file_name.trim_end_matches(".gz");
+					let extension = file_name.rfind('.').map(|offset| &file_name[offset + 1..]);
+					file_name.starts_with('.') || extension != Some("warc")
+				} else {
+					path.last().unwrap().to_string_lossy().starts_with('.')
+				};
+				!skip && f(path)
+			})
+			.await
+	}
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub enum WarcError<A, B> {
+	File(A),
+	Partition(B),
+	Io(IoError),
+}
+impl<A, B> error::Error for WarcError<A, B>
+where
+	A: error::Error,
+	B: error::Error,
+{
+}
+impl<A, B> Display for WarcError<A, B>
+where
+	A: Display,
+	B: Display,
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::File(err) => Display::fmt(err, f),
+			Self::Partition(err) => Display::fmt(err, f),
+			Self::Io(err) => Display::fmt(err, f),
+		}
+	}
+}
+impl<A, B> From<io::Error> for WarcError<A, B> {
+	fn from(err: io::Error) -> Self {
+		Self::Io(err.into())
+	}
+}
@@ -0,0 +1,283 @@
+use futures::{future, FutureExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_closure::FnMutNamed;
+use serde_json::{Map, Value};
+use std::io;
+
+use amadeus_core::{
+	into_par_stream::IteratorExt, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
+};
+use amadeus_types::{Data, DateTime, Json};
+
+use super::{commoncrawl::RecordParser, get, parser::RecordType, paths, WarcRecord};
+
+/// The metadata of the webpages of a crawl, such as their HTTP headers and links, from its WAT
+/// files. These are much smaller than the WARC files that [`CommonCrawl`](super::CommonCrawl)
+/// reads.
+#[derive(Clone, Debug)]
+pub struct CommonCrawlWat {
+	urls: Vec<String>,
+}
+impl CommonCrawlWat {
+	/// CC-MAIN-2020-24
+	pub async fn new(id: &str) -> Result<Self, reqwest::Error> {
+		let urls = paths(id, "wat").await?;
+		Ok(Self { urls })
+	}
+}
+
+/// The metadata Common Crawl extracted from an HTTP response.
+///
+/// WAT files also hold metadata for the requests and other records of the WARC files; these are
+/// skipped.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct WatRecord {
+	pub url: String,
+	pub date: DateTime,
+	pub http_status: Option<u16>,
+	/// In name order. Repeated headers are joined by Common Crawl.
+	pub http_headers: Vec<(String, String)>,
+	/// The URLs of the links, images, scripts and so on of the page, as written in it, so often
+	/// relative.
+	pub outlinks: Vec<String>,
+	/// All the metadata, as JSON.
+	pub metadata: Json,
+}
+impl Data for WatRecord {
+	type Vec = Vec<Self>;
+	type DynamicType = ();
+
+	fn new_vec(_type: Self::DynamicType) -> Self::Vec {
+		Vec::new()
+	}
+}
+impl WatRecord {
+	fn from_record(record: WarcRecord) -> Result<Option<Self>, String> {
+		let metadata: Metadata = serde_json::from_slice(&record.payload)
+			.map_err(|err| format!("invalid WAT metadata: {}", err))?;
+		let envelope = metadata.envelope;
+		if envelope.warc_header_metadata.warc_type.as_deref() != Some("response") {
+			return Ok(None);
+		}
+		let response = envelope
+			.payload_metadata
+			.http_response_metadata
+			.unwrap_or_default();
+		let http_status = response
+			.response_message
+			.and_then(|message| message.status)
+			.map(|status| {
+				status
+					.parse()
+					.map_err(|_| format!("invalid response status {:?}", status))
+			})
+			.transpose()?;
+		let http_headers = response
+			.headers
+			.into_iter()
+			.map(|(key, value)| match value {
+				Value::String(value) => (key, value),
+				value => (key, value.to_string()),
+			})
+			.collect();
+		let outlinks = response
+			.html_metadata
+			.map(|html| html.links.into_iter().filter_map(|link| link.url).collect())
+			.unwrap_or_default();
+		Ok(Some(Self {
+			url: record.target_uri.ok_or("missing WARC-Target-URI")?,
+			date: record.date,
+			http_status,
+			http_headers,
+			outlinks,
+			metadata: Json::from(String::from_utf8_lossy(&record.payload).into_owned()),
+		}))
+	}
+}
+
+// The parts of the metadata that are parsed into fields. See
+// https://webarchive.jira.com/wiki/spaces/Iresearch/pages/13467719/JSON+Metadata+Format
+
+#[derive(Deserialize)]
+struct Metadata {
+	#[serde(rename = "Envelope")]
+	envelope: Envelope,
+}
+#[derive(Deserialize)]
+struct Envelope {
+	#[serde(rename = "WARC-Header-Metadata", default)]
+	warc_header_metadata: WarcHeaderMetadata,
+	#[serde(rename = "Payload-Metadata", default)]
+	payload_metadata: PayloadMetadata,
+}
+#[derive(Deserialize, Default)]
+struct WarcHeaderMetadata {
+	#[serde(rename = "WARC-Type")]
+	warc_type: Option<String>,
+}
+#[derive(Deserialize, Default)]
+struct PayloadMetadata {
+	#[serde(rename = "HTTP-Response-Metadata")]
+	http_response_metadata: Option<HttpResponseMetadata>,
+}
+#[derive(Deserialize, Default)]
+struct HttpResponseMetadata {
+	#[serde(rename = "Response-Message")]
+	response_message: Option<ResponseMessage>,
+	#[serde(rename = "Headers", default)]
+	headers: Map<String, Value>,
+	#[serde(rename = "HTML-Metadata")]
+	html_metadata: Option<HtmlMetadata>,
+}
+#[derive(Deserialize)]
+struct ResponseMessage {
+	#[serde(rename = "Status")]
+	status: Option<String>,
+}
+#[derive(Deserialize)]
+struct HtmlMetadata {
+	#[serde(rename = "Links", default)]
+	links: Vec<Link>,
+}
+#[derive(Deserialize)]
+struct Link {
+	url: Option<String>,
+}
+
+#[cfg(not(nightly))]
+type Output = std::pin::Pin<Box<dyn Stream<Item = Result<WatRecord, io::Error>> + Send>>;
+#[cfg(nightly)]
+type Output = impl Stream<Item = Result<WatRecord, io::Error>> + Send;
+
+FnMutNamed! {
+	pub type Closure<> = |self|url=> String| -> Output where {
+		#[allow(clippy::let_and_return)]
+		let ret = async move {
+				// Each file starts with a warcinfo record, followed by a metadata record per
+				// record of the corresponding WARC file
+				let body = get(&url).await?;
+				Ok(RecordParser::new(body, Some(vec![RecordType::Metadata])))
+			}
+			.map(ResultExpandIter::new)
+			.flatten_stream()
+			.filter_map(|record: Result<Result<WarcRecord, io::Error>, io::Error>| {
+				let record = record.and_then(|record| record).and_then(|record| {
+					WatRecord::from_record(record)
+						.map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message))
+				});
+				future::ready(record.transpose())
+			});
+		#[cfg(not(nightly))]
+		let ret = ret.boxed();
+		ret
+	}
+}
+
+impl Source for CommonCrawlWat {
+	type Item = WatRecord;
+	type Error = io::Error;
+
+	type ParStream = DistParStream<Self::DistStream>;
+	#[cfg(not(nightly))]
+	#[allow(clippy::type_complexity)]
+	type DistStream = amadeus_core::par_stream::FlatMap<
+		amadeus_core::into_par_stream::PartitionsDistStream<std::vec::IntoIter<String>>,
+		Closure,
+	>;
+	#[cfg(nightly)]
+	type DistStream = impl DistributedStream<Item = Result<Self::Item, Self::Error>>;
+
+	fn par_stream(self) -> Self::ParStream {
+		DistParStream::new(self.dist_stream())
+	}
+	#[allow(clippy::let_and_return)]
+	fn dist_stream(self) -> Self::DistStream {
+		self.urls
+			.into_iter()
+			.dist_partitions()
+			.flat_map(Closure::new())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use futures::{executor::block_on, io::Cursor, StreamExt};
+
+	use super::*;
+
+	fn record(uri: &str, metadata: &str) -> String {
+		format!(
+			"WARC/1.0\r\nWARC-Type: metadata\r\nWARC-Target-URI: {}\r\nWARC-Record-ID: <urn:uuid:0>\r\nWARC-Date: 2020-05-25T03:15:21Z\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}\r\n\r\n",
+			uri,
+			metadata.len(),
+			metadata
+		)
+	}
+
+	const RESPONSE: &str = r#"{"Container":{"Filename":"a.warc.gz","Compressed":true},"Envelope":{"Format":"WARC","WARC-Header-Metadata":{"WARC-Type":"response","WARC-Target-URI":"http://example.com/"},"Payload-Metadata":{"Actual-Content-Type":"application/http; msgtype=response","HTTP-Response-Metadata":{"Response-Message":{"Version":"HTTP/1.1","Status":"200","Reason":"OK"},"Headers":{"Content-Type":"text/html","Server":"nginx","X-Count":3},"HTML-Metadata":{"Head":{"Title":"Example"},"Links":[{"path":"A@/href","url":"/about"},{"path":"IMG@/src","url":"logo.png"},{"path":"A@/href","title":"no url"}]}}}}}"#;
+	const REQUEST: &str = r#"{"Envelope":{"WARC-Header-Metadata":{"WARC-Type":"request","WARC-Target-URI":"http://example.com/"},"Payload-Metadata":{"HTTP-Request-Metadata":{"Request-Message":{"Method":"GET"}}}}}"#;
+	const METADATA: &str = r#"{"Envelope":{"WARC-Header-Metadata":{"WARC-Type":"metadata"}}}"#;
+	const REDIRECT: &str = r#"{"Envelope":{"WARC-Header-Metadata":{"WARC-Type":"response"},"Payload-Metadata":{"HTTP-Response-Metadata":{"Response-Message":{"Status":"301"},"Headers":{"Location":"https://example.com/"}}}}}"#;
+
+	fn records(wat: String) -> Vec<Result<Option<WatRecord>, String>> {
+		block_on(
+			RecordParser::new(Cursor::new(wat), Some(vec![RecordType::Metadata]))
+				.map(|record| WatRecord::from_record(record.unwrap()))
+				.collect(),
+		)
+	}
+
+	#[test]
+	fn from_record() {
+		let wat = record("http://example.com/", REQUEST)
+			+ &record("http://example.com/", RESPONSE)
+			+ &record("http://example.com/", METADATA)
+			+ &record("http://example.com/redirect", REDIRECT);
+		let records = records(wat)
+			.into_iter()
+			.filter_map(Result::unwrap)
+			.collect::<Vec<_>>();
+		assert_eq!(records.len(), 2);
+
+		assert_eq!(records[0].url, "http://example.com/");
+		assert_eq!(records[0].http_status, Some(200));
+		let headers = |headers: &[(&str, &str)]| {
+			headers
+				.iter()
+				.map(|&(key, value)| (key.to_owned(), value.to_owned()))
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(
+			records[0].http_headers,
+			headers(&[
+				("Content-Type", "text/html"),
+				("Server", "nginx"),
+				("X-Count", "3")
+			])
+		);
+		assert_eq!(records[0].outlinks, ["/about", "logo.png"]);
+		assert_eq!(records[0].metadata, Json::from(String::from(RESPONSE)));
+
+		assert_eq!(records[1].url, "http://example.com/redirect");
+		assert_eq!(records[1].http_status, Some(301));
+		assert_eq!(
+			records[1].http_headers,
+			headers(&[("Location", "https://example.com/")])
+		);
+		assert!(records[1].outlinks.is_empty());
+	}
+
+	#[test]
+	fn invalid() {
+		let invalid = records(record("http://example.com/", "{"));
+		assert!(invalid[0]
+			.as_ref()
+			.unwrap_err()
+			.starts_with("invalid WAT metadata"));
+		let status = RESPONSE.replace(r#""Status":"200""#, r#""Status":"OK""#);
+		assert_eq!(
+			records(record("http://example.com/", &status)),
+			[Err(String::from("invalid response status \"OK\""))]
+		);
+	}
+}
@@ -0,0 +1,162 @@
+use futures::{FutureExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_closure::FnMutNamed;
+use std::io;
+
+use amadeus_core::{
+	into_par_stream::IteratorExt, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
+};
+use amadeus_types::{Data, DateTime};
+
+use super::{commoncrawl::RecordParser, get, parser::RecordType, paths, WarcRecord};
+
+/// The plain text of the webpages of a crawl, from its WET files. These are much smaller than the
+/// WARC files that [`CommonCrawl`](super::CommonCrawl) reads.
+#[derive(Clone, Debug)]
+pub struct CommonCrawlWet {
+	urls: Vec<String>,
+}
+impl CommonCrawlWet {
+	/// CC-MAIN-2020-24
+	pub async fn new(id: &str) -> Result<Self, reqwest::Error> {
+		let urls = paths(id, "wet").await?;
+		Ok(Self { urls })
+	}
+}
+
+/// The text Common Crawl extracted from a webpage.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct WetRecord {
+	pub url: String,
+	pub date: DateTime,
+	/// The languages identified in the text, most prevalent first, as comma-separated ISO 639-3
+	/// codes such as `eng,deu`. Only recorded by crawls since August 2018.
+	pub language: Option<String>,
+	pub text: String,
+}
+impl Data for WetRecord {
+	type Vec = Vec<Self>;
+	type DynamicType = ();
+
+	fn new_vec(_type: Self::DynamicType) -> Self::Vec {
+		Vec::new()
+	}
+}
+impl WetRecord {
+	fn from_record(record: WarcRecord) -> Result<Self, String> {
+		let language = record
+			.headers
+			.iter()
+			.find(|(key, _)| key.eq_ignore_ascii_case("WARC-Identified-Content-Language"))
+			.map(|(_, value)| value.clone());
+		Ok(Self {
+			url: record.target_uri.ok_or("missing WARC-Target-URI")?,
+			date: record.date,
+			language,
+			text: String::from_utf8_lossy(&record.payload).into_owned(),
+		})
+	}
+}
+
+#[cfg(not(nightly))]
+type Output = std::pin::Pin<Box<dyn Stream<Item = Result<WetRecord, io::Error>> + Send>>;
+#[cfg(nightly)]
+type Output = impl Stream<Item = Result<WetRecord, io::Error>> + Send;
+
+FnMutNamed! {
+	pub type Closure<> = |self|url=> String| -> Output where {
+		#[allow(clippy::let_and_return)]
+		let ret = async move {
+				// Each file starts with a warcinfo record, followed by a conversion record per page
+				let body = get(&url).await?;
+				Ok(RecordParser::new(body, Some(vec![RecordType::Conversion])))
+			}
+			.map(ResultExpandIter::new)
+			.flatten_stream()
+			.map(|record: Result<Result<WarcRecord, io::Error>, io::Error>| {
+				WetRecord::from_record(record??)
+					.map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message))
+			});
+		#[cfg(not(nightly))]
+		let ret = ret.boxed();
+		ret
+	}
+}
+
+impl Source for CommonCrawlWet {
+	type Item = WetRecord;
+	type Error = io::Error;
+
+	type ParStream = DistParStream<Self::DistStream>;
+	#[cfg(not(nightly))]
+	#[allow(clippy::type_complexity)]
+	type DistStream = amadeus_core::par_stream::FlatMap<
+		amadeus_core::into_par_stream::PartitionsDistStream<std::vec::IntoIter<String>>,
+		Closure,
+	>;
+	#[cfg(nightly)]
+	type DistStream = impl DistributedStream<Item = Result<Self::Item, Self::Error>>;
+
+	fn par_stream(self) -> Self::ParStream {
+		DistParStream::new(self.dist_stream())
+	}
+	#[allow(clippy::let_and_return)]
+	fn dist_stream(self) -> Self::DistStream {
+		self.urls
+			.into_iter()
+			.dist_partitions()
+			.flat_map(Closure::new())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use futures::{executor::block_on, io::Cursor, StreamExt};
+
+	use super::*;
+
+	fn record(type_: &str, headers: &str, content: &str) -> String {
+		format!(
+			"WARC/1.0\r\nWARC-Type: {}\r\nWARC-Record-ID: <urn:uuid:0>\r\nWARC-Date: 2020-05-25T03:15:21Z\r\n{}Content-Length: {}\r\n\r\n{}\r\n\r\n",
+			type_,
+			headers,
+			content.len(),
+			content
+		)
+	}
+
+	#[test]
+	fn from_record() {
+		let wet = record("warcinfo", "", "software: amadeus\r\n")
+			+ &record(
+				"conversion",
+				"WARC-Target-URI: http://example.com/\r\nWARC-Identified-Content-Language: eng,deu\r\nContent-Type: text/plain\r\n",
+				"Example Domain\nThis domain is for use in examples.",
+			) + &record(
+			"conversion",
+			"WARC-Target-URI: http://example.com/old\r\nContent-Type: text/plain\r\n",
+			"caf\u{e9}",
+		);
+		let records = block_on(
+			RecordParser::new(Cursor::new(wet), Some(vec![RecordType::Conversion]))
+				.map(|record| WetRecord::from_record(record.unwrap()).unwrap())
+				.collect::<Vec<_>>(),
+		);
+		assert_eq!(records.len(), 2);
+		assert_eq!(records[0].url, "http://example.com/");
+		assert_eq!(records[0].language.as_deref(), Some("eng,deu"));
+		assert_eq!(
+			records[0].text,
+			"Example Domain\nThis domain is for use in examples."
+		);
+		assert_eq!(records[1].language, None);
+		assert_eq!(records[1].text, "caf\u{e9}");
+
+		let missing = block_on(
+			RecordParser::new(Cursor::new(record("conversion", "", "text")), None)
+				.map(|record| WetRecord::from_record(record.unwrap()))
+				.collect::<Vec<_>>(),
+		);
+		assert_eq!(missing, [Err(String::from("missing WARC-Target-URI"))]);
+	}
+}
@@ -5,6 +5,7 @@ use futures::{future, future::LocalBoxFuture, stream, FutureExt, StreamExt, TryS
 use std::{
 	ffi::{OsStr, OsString}, fs, future::Future, io, path::{Path, PathBuf}, sync::Arc
 };
+use tracing::{debug_span, field, Instrument};
 use walkdir::WalkDir;
 
 #[cfg(unix)]
@@ -23,7 +24,9 @@ use {
 use super::{Directory, File, Page, Partition};
 #[cfg(target_arch = "wasm32")]
 use crate::util::{f64_to_u64, u64_to_f64};
-use crate::util::{IoError, ResultExpand};
+use crate::{
+	par_stream::record_bytes_read, util::{IoError, ResultExpand}
+};
 
 #[async_trait(?Send)]
 impl<F> File for Vec<F>
@@ -216,10 +219,13 @@ impl<Fut: Future> FutureOrOutput<Fut> {
 #[cfg(not(target_arch = "wasm32"))]
 struct LocalFileInner {
 	file: fs::File,
+	/// The path it was opened from, if any, for tracing.
+	path: Option<PathBuf>,
 }
 #[cfg(target_arch = "wasm32")]
 struct LocalFileInner {
 	file: Mutex<FutureOrOutput<LocalBoxFuture<'static, Blob>>>,
+	path: Option<PathBuf>,
 }
 pub struct LocalFile {
 	inner: Arc<LocalFileInner>,
@@ -230,10 +236,16 @@ impl LocalFile {
 	pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
 		#[cfg(not(target_arch = "wasm32"))]
 		{
-			Self::from_file(fs::File::open(path)?)
+			let file = fs::File::open(&path)?;
+			let inner = Arc::new(LocalFileInner {
+				file,
+				path: Some(path.as_ref().to_owned()),
+			});
+			Ok(Self { inner })
 		}
 		#[cfg(target_arch = "wasm32")]
 		{
+			let path_ = path.as_ref().to_owned();
 			let path = path.as_ref().to_string_lossy().into_owned();
 			let file = Mutex::new(FutureOrOutput::Future(
 				async move {
@@ -246,7 +258,10 @@ impl LocalFile {
 				}
 				.boxed_local(),
 			));
-			let inner = Arc::new(LocalFileInner { file });
+			let inner = Arc::new(LocalFileInner {
+				file,
+				path: Some(path_),
+			});
 			Ok(Self { inner })
 		}
 	}
@@ -280,7 +295,7 @@ impl LocalFile {
 
 	#[cfg(not(target_arch = "wasm32"))]
 	fn from_file(file: fs::File) -> io::Result<Self> {
-		let inner = Arc::new(LocalFileInner { file });
+		let inner = Arc::new(LocalFileInner { file, path: None });
 		Ok(Self { inner })
 	}
 	#[cfg(target_arch = "wasm32")]
@@ -418,7 +433,18 @@ impl Page for LocalFile {
 		&self, mut offset: u64, len: usize,
 	) -> LocalBoxFuture<'static, Result<Box<[u8]>, Self::Error>> {
 		let self_ = self.clone();
-		Box::pin(async move {
+		let span = debug_span!(
+			"page_read",
+			path = field::Empty,
+			offset,
+			len,
+			bytes = field::Empty
+		);
+		if let Some(path) = &self.inner.path {
+			let _ = span.record("path", field::display(path.display()));
+		}
+		let span_ = span.clone();
+		async move {
 			let mut buf_ = vec![0; len];
 			let mut buf = &mut *buf_;
 			while !buf.is_empty() {
@@ -435,8 +461,12 @@ impl Page for LocalFile {
 			}
 			let len = len - buf.len();
 			buf_.truncate(len);
+			let _ = span_.record("bytes", len);
+			record_bytes_read(len);
 			Ok(buf_.into_boxed_slice())
-		})
+		}
+		.instrument(span)
+		.boxed_local()
 	}
 	fn write(
 		&self, mut offset: u64, buf: Box<[u8]>,
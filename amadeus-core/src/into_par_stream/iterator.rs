@@ -2,7 +2,7 @@ use futures::Stream;
 use pin_project::pin_project;
 use serde::{Deserialize, Serialize};
 use std::{
-	ops::{Range, RangeFrom, RangeInclusive}, pin::Pin, task::{Context, Poll}
+	fmt::Debug, ops::{Range, RangeFrom, RangeInclusive}, pin::Pin, task::{Context, Poll}
 };
 
 use super::{
@@ -19,6 +19,18 @@ pub trait IteratorExt: Iterator + Sized {
 	fn dist(self) -> IterDistStream<Self> {
 		IterDistStream(self)
 	}
+	/// Like `par`, but each item is a source partition, whose `Debug` representation names the
+	/// task reading it in errors.
+	#[inline]
+	fn par_partitions(self) -> PartitionsParStream<Self> {
+		PartitionsParStream(self)
+	}
+	/// Like `dist`, but each item is a source partition, whose `Debug` representation names the
+	/// task reading it in errors.
+	#[inline]
+	fn dist_partitions(self) -> PartitionsDistStream<Self> {
+		PartitionsDistStream(self)
+	}
 }
 impl<I: Iterator + Sized> IteratorExt for I {}
 
@@ -44,13 +56,38 @@ impl_par_dist_rename! {
 	}
 }
 
+impl_par_dist_rename! {
+	#[pin_project]
+	pub struct PartitionsParStream<I>(pub(crate) I);
+
+	impl<I: Iterator> ParallelStream for PartitionsParStream<I>
+	where
+		I::Item: Debug + Send + 'static,
+	{
+		type Item = I::Item;
+		type Task = IterStreamTask<I::Item>;
+
+		#[inline]
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			self.0.size_hint()
+		}
+		#[inline]
+		fn next_task(mut self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Task>> {
+			Poll::Ready(self.0.next().map(|partition| {
+				let name = format!("{:?}", partition);
+				IterStreamTask(Some(partition), Some(name))
+			}))
+		}
+	}
+}
+
 #[pin_project]
-#[derive(Serialize, Deserialize)]
-pub struct IterStreamTask<T>(Option<T>);
+#[derive(Clone, Serialize, Deserialize)]
+pub struct IterStreamTask<T>(Option<T>, Option<String>);
 impl<T> IterStreamTask<T> {
 	#[inline]
 	fn new(t: T) -> Self {
-		Self(Some(t))
+		Self(Some(t), None)
 	}
 }
 
@@ -58,6 +95,14 @@ impl<T> StreamTask for IterStreamTask<T> {
 	type Item = T;
 	type Async = IterStreamTask<T>;
 
+	fn partition(&self) -> Option<String> {
+		self.1.clone()
+	}
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let len = usize::from(self.0.is_some());
+		(len, Some(len))
+	}
 	#[inline]
 	fn into_async(self) -> Self::Async {
 		self
@@ -67,6 +67,7 @@ macro_rules! impl_par_dist_rename {
 			ParStream DistStream
 			Send ProcessSend
 			IterParStream IterDistStream
+			PartitionsParStream PartitionsDistStream
 			into_par_stream into_dist_stream
 			par_stream dist_stream
 			assert_parallel_sink assert_distributed_sink
@@ -72,6 +72,33 @@ macro_rules! pipe {
 				$assert_pipe(Filter::new(self, f))
 			}
 
+			#[inline]
+			fn batch<B>(self, size: usize) -> Batch<Self, B>
+			where
+				B: Default + Extend<Self::Output> + 'static,
+				Self: Sized,
+			{
+				$assert_pipe(Batch::new(self, size))
+			}
+
+			#[inline]
+			fn chunks(self, size: usize) -> Batch<Self, Vec<Self::Output>>
+			where
+				Self::Output: 'static,
+				Self: Sized,
+			{
+				$assert_pipe(Batch::new(self, size))
+			}
+
+			#[inline]
+			fn unbatch(self) -> Unbatch<Self>
+			where
+				Self::Output: IntoIterator,
+				Self: Sized,
+			{
+				$assert_pipe(Unbatch::new(self))
+			}
+
 			#[inline]
 			fn cloned<'a, T>(self) -> Cloned<Self, T, Input>
 			where
@@ -220,6 +247,30 @@ macro_rules! pipe {
 				$assert_sink(StdDev::new(self))
 			}
 
+			#[inline]
+			fn quantiles(self, quantiles: &[f64]) -> Quantiles<Self>
+			where
+				Self: $pipe<Input, Output = f64> + Sized,
+			{
+				assert!(
+					quantiles.iter().all(|q| (0.0..=1.0).contains(q)),
+					"quantiles must be within 0.0..=1.0"
+				);
+				$assert_sink(Quantiles::new(self, quantiles.to_vec()))
+			}
+
+			#[inline]
+			fn percentile(self, percentile: f64) -> Percentile<Self>
+			where
+				Self: $pipe<Input, Output = f64> + Sized,
+			{
+				assert!(
+					(0.0..=100.0).contains(&percentile),
+					"percentile must be within 0.0..=100.0"
+				);
+				$assert_sink(Percentile::new(self, percentile))
+			}
+
 			#[inline]
 			fn combine<F>(self, f: F) -> Combine<Self, F>
 			where
@@ -13,7 +13,9 @@ mod histogram;
 mod max;
 mod mean;
 mod pipe;
+mod quantile;
 mod sample;
+mod sink_map;
 mod stddev;
 mod sum;
 mod tuple;
@@ -22,7 +24,7 @@ use super::par_pipe::*;
 use crate::{pipe::Sink, pool::ProcessSend};
 
 pub use self::{
-	all::*, any::*, collect::*, combine::*, combiner::*, count::*, fold::*, folder::*, for_each::*, fork::*, group_by::*, histogram::*, max::*, mean::*, pipe::*, sample::*, stddev::*, sum::*, tuple::*
+	all::*, any::*, collect::*, combine::*, combiner::*, count::*, fold::*, folder::*, for_each::*, fork::*, group_by::*, histogram::*, max::*, mean::*, pipe::*, quantile::*, sample::*, sink_map::*, stddev::*, sum::*, tuple::*
 };
 
 #[must_use]
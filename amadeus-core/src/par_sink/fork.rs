@@ -153,6 +153,9 @@ where
 	type Item = Sum2<B::Output, C::Output>;
 	type Async = JoinStreamTaskAsync<A::Async, B::Async, C::Async, RefAItem, A::Item>;
 
+	fn partition(&self) -> Option<String> {
+		self.stream.partition()
+	}
 	fn into_async(self) -> Self::Async {
 		JoinStreamTaskAsync {
 			stream: self.stream.into_async(),
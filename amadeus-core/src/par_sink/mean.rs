@@ -81,6 +81,9 @@ impl FolderSync<State> for MeanFolder<StepB> {
 
 	#[inline(always)]
 	fn push(&mut self, state: &mut Self::State, item: State) {
+		if item.count == 0 {
+			return;
+		}
 		state.correction = (state.correction * u64_to_f64(state.count))
 			+ (item.correction * u64_to_f64(item.count)) / u64_to_f64(state.count + item.count);
 		state.mean = ((state.mean * u64_to_f64(state.count))
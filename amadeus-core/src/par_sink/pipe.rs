@@ -84,7 +84,7 @@ impl<A: DistributedPipe<Item>, B: DistributedSink<A::Output>, Item> DistributedS
 }
 
 #[pin_project]
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct JoinTask<A, B> {
 	#[pin]
 	a: A,
@@ -96,6 +96,9 @@ impl<A: StreamTask, B: PipeTask<A::Item>> StreamTask for JoinTask<A, B> {
 	type Item = B::Output;
 	type Async = StreamPipe<A::Async, B::Async>;
 
+	fn partition(&self) -> Option<String> {
+		self.a.partition()
+	}
 	#[inline(always)]
 	fn into_async(self) -> Self::Async {
 		self.a.into_async().pipe(self.b.into_async())
@@ -0,0 +1,133 @@
+use amadeus_streaming::TDigest;
+use derive_new::new;
+use serde::{Deserialize, Serialize};
+
+use super::{folder_par_sink, FolderSync, FolderSyncReducer, ParallelPipe, ParallelSink};
+
+/// The default compression of the digests, trading accuracy for memory.
+const COMPRESSION: f64 = 100.0;
+
+#[derive(new)]
+#[must_use]
+pub struct Quantiles<P> {
+	pipe: P,
+	quantiles: Vec<f64>,
+	#[new(value = "COMPRESSION")]
+	compression: f64,
+}
+impl<P> Quantiles<P> {
+	/// Set the compression of the digest, 100 by default. Higher is more accurate but uses more
+	/// memory.
+	pub fn with_compression(mut self, compression: f64) -> Self {
+		assert!(compression > 0.0, "compression must be positive");
+		self.compression = compression;
+		self
+	}
+}
+
+impl_par_dist! {
+	impl<P: ParallelPipe<Item, Output = f64>, Item> ParallelSink<Item> for Quantiles<P> {
+		folder_par_sink!(
+			TDigestFolder,
+			QuantilesFolder,
+			self,
+			TDigestFolder::new(self.compression),
+			QuantilesFolder::new(self.quantiles, self.compression)
+		);
+	}
+}
+
+#[derive(new)]
+#[must_use]
+pub struct Percentile<P> {
+	pipe: P,
+	percentile: f64,
+	#[new(value = "COMPRESSION")]
+	compression: f64,
+}
+impl<P> Percentile<P> {
+	/// Set the compression of the digest, 100 by default. Higher is more accurate but uses more
+	/// memory.
+	pub fn with_compression(mut self, compression: f64) -> Self {
+		assert!(compression > 0.0, "compression must be positive");
+		self.compression = compression;
+		self
+	}
+}
+
+impl_par_dist! {
+	impl<P: ParallelPipe<Item, Output = f64>, Item> ParallelSink<Item> for Percentile<P> {
+		folder_par_sink!(
+			TDigestFolder,
+			PercentileFolder,
+			self,
+			TDigestFolder::new(self.compression),
+			PercentileFolder::new(self.percentile, self.compression)
+		);
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize, new)]
+pub struct TDigestFolder {
+	compression: f64,
+}
+
+impl FolderSync<f64> for TDigestFolder {
+	type State = TDigest;
+	type Done = Self::State;
+
+	#[inline(always)]
+	fn zero(&mut self) -> Self::State {
+		TDigest::new(self.compression)
+	}
+	#[inline(always)]
+	fn push(&mut self, state: &mut Self::State, item: f64) {
+		state.push(item)
+	}
+	#[inline(always)]
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize, new)]
+pub struct QuantilesFolder {
+	quantiles: Vec<f64>,
+	compression: f64,
+}
+
+impl FolderSync<TDigest> for QuantilesFolder {
+	type State = TDigest;
+	type Done = Vec<Option<f64>>;
+
+	fn zero(&mut self) -> Self::State {
+		TDigest::new(self.compression)
+	}
+	fn push(&mut self, state: &mut Self::State, item: TDigest) {
+		state.union(&item)
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state.quantiles(&self.quantiles)
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize, new)]
+pub struct PercentileFolder {
+	percentile: f64,
+	compression: f64,
+}
+
+impl FolderSync<TDigest> for PercentileFolder {
+	type State = TDigest;
+	type Done = Option<f64>;
+
+	fn zero(&mut self) -> Self::State {
+		TDigest::new(self.compression)
+	}
+	fn push(&mut self, state: &mut Self::State, item: TDigest) {
+		state.union(&item)
+	}
+	fn done(&mut self, state: Self::State) -> Self::Done {
+		state.quantile(self.percentile / 100.0)
+	}
+}
@@ -4,7 +4,6 @@ use amadeus_streaming::{
 	HyperLogLogMagnitude, SampleUnstable as SASampleUnstable, Sort as SASort, Top
 };
 use derive_new::new;
-use rand::thread_rng;
 use serde::{Deserialize, Serialize};
 use serde_closure::traits;
 use std::{cmp::Ordering, hash::Hash};
@@ -12,6 +11,7 @@ use std::{cmp::Ordering, hash::Hash};
 use super::{
 	folder_par_sink, FolderSync, FolderSyncReducer, ParallelPipe, ParallelSink, SumFolder, SumZeroFolder
 };
+use crate::pool::with_rng;
 
 #[derive(new)]
 #[must_use]
@@ -48,7 +48,7 @@ impl<Item> FolderSync<Item> for SampleUnstableFolder {
 		SASampleUnstable::new(self.samples)
 	}
 	fn push(&mut self, state: &mut Self::State, item: Item) {
-		state.push(item, &mut thread_rng())
+		with_rng(|mut rng| state.push(item, &mut rng))
 	}
 	fn done(&mut self, state: Self::State) -> Self::Done {
 		state
@@ -0,0 +1,734 @@
+#![allow(clippy::type_complexity)]
+
+use educe::Educe;
+use futures::{pin_mut, ready, stream, Stream, StreamExt as _};
+use indexmap::IndexMap;
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
+use std::{
+	any::Any, hash::Hash, marker::PhantomData, mem, pin::Pin, task::{Context, Poll}
+};
+
+use super::{
+	DistributedPipe, DistributedSink, ParallelPipe, ParallelSink, PipeTask, Reducer, ReducerProcessSend, ReducerSend
+};
+use crate::{
+	par_stream::Identity, pipe::{Pipe, Sink}, pool::ProcessSend
+};
+
+/// A dynamically-sized set of named sinks, all of which are fed every item in a single pass.
+///
+/// Unlike tuples of sinks, the sinks can be added one at a time, and their outputs are converted
+/// into a common type `V` (for example `Value`) and collected into an `IndexMap` in insertion
+/// order. Each item is cloned for each sink but the last, which it's moved into.
+///
+/// As the sinks are boxed, `SinkMap` is only usable with a `ThreadPool`. [`DistSinkMap`] is its
+/// counterpart for distributed streams.
+///
+/// ```ignore
+/// let metrics = SinkMap::<_, _, Value>::new()
+///     .add("sum", Identity.sum::<u64>())
+///     .add("max", Identity.max());
+/// let metrics: IndexMap<&'static str, Value> = stream.pipe(&pool, metrics).await;
+/// ```
+#[must_use]
+pub struct SinkMap<K, Item, V> {
+	sinks: IndexMap<K, (Box<dyn DynReducerA<Item>>, Box<dyn DynReducerC<V>>)>,
+}
+
+impl<K, Item, V> SinkMap<K, Item, V>
+where
+	K: Eq + Hash,
+	Item: Clone + 'static,
+	V: 'static,
+{
+	/// Create an empty `SinkMap`.
+	pub fn new() -> Self {
+		Self {
+			sinks: IndexMap::new(),
+		}
+	}
+
+	/// Add a sink, replacing any sink previously added under the same key.
+	pub fn insert<S>(&mut self, key: K, sink: S)
+	where
+		S: ParallelSink<Item>,
+		<S::Pipe as ParallelPipe<Item>>::Task: Clone + Send + 'static,
+		<<S::Pipe as ParallelPipe<Item>>::Task as PipeTask<Item>>::Async: 'static,
+		S::ReduceA: 'static,
+		<S::ReduceA as Reducer<<S::Pipe as ParallelPipe<Item>>::Output>>::Async: 'static,
+		S::ReduceC: 'static,
+		<S::ReduceC as Reducer<
+			<S::ReduceA as ReducerSend<<S::Pipe as ParallelPipe<Item>>::Output>>::Done,
+		>>::Async: 'static,
+		S::Done: Into<V>,
+	{
+		let (pipe, reduce_a, reduce_c) = sink.reducers();
+		let _ = self.sinks.insert(
+			key,
+			(
+				Box::new(ErasedReducerA(pipe.task(), reduce_a)),
+				Box::new(ErasedReducerC(reduce_c, PhantomData)),
+			),
+		);
+	}
+
+	/// Add a sink, replacing any sink previously added under the same key, and return `self` for
+	/// chaining.
+	pub fn add<S>(mut self, key: K, sink: S) -> Self
+	where
+		S: ParallelSink<Item>,
+		<S::Pipe as ParallelPipe<Item>>::Task: Clone + Send + 'static,
+		<<S::Pipe as ParallelPipe<Item>>::Task as PipeTask<Item>>::Async: 'static,
+		S::ReduceA: 'static,
+		<S::ReduceA as Reducer<<S::Pipe as ParallelPipe<Item>>::Output>>::Async: 'static,
+		S::ReduceC: 'static,
+		<S::ReduceC as Reducer<
+			<S::ReduceA as ReducerSend<<S::Pipe as ParallelPipe<Item>>::Output>>::Done,
+		>>::Async: 'static,
+		S::Done: Into<V>,
+	{
+		self.insert(key, sink);
+		self
+	}
+
+	/// The number of sinks.
+	pub fn len(&self) -> usize {
+		self.sinks.len()
+	}
+
+	/// Returns whether there are no sinks.
+	pub fn is_empty(&self) -> bool {
+		self.sinks.is_empty()
+	}
+}
+
+impl<K, Item, V> Default for SinkMap<K, Item, V>
+where
+	K: Eq + Hash,
+	Item: Clone + 'static,
+	V: 'static,
+{
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<K, Item, V> ParallelSink<Item> for SinkMap<K, Item, V>
+where
+	K: Eq + Hash,
+	Item: Clone + 'static,
+	V: 'static,
+{
+	type Done = IndexMap<K, V>;
+	type Pipe = Identity;
+	type ReduceA = SinkMapReducerA<Item>;
+	type ReduceC = SinkMapReducerC<K, V>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceC) {
+		let (keys, (reduce_a, reduce_c)): (Vec<_>, (Vec<_>, Vec<_>)) =
+			self.sinks.into_iter().unzip();
+		(
+			Identity,
+			SinkMapReducerA(reduce_a),
+			SinkMapReducerC(keys, reduce_c),
+		)
+	}
+}
+
+type Erased = Box<dyn Any + Send>;
+
+trait DynSink<Item, Done> {
+	fn poll_forward_dyn(
+		self: Pin<&mut Self>, cx: &mut Context, stream: Pin<&mut dyn Stream<Item = Item>>,
+	) -> Poll<Done>;
+}
+
+trait DynReducerA<Item>: Send {
+	fn clone_box(&self) -> Box<dyn DynReducerA<Item>>;
+	fn into_async(self: Box<Self>) -> Pin<Box<dyn DynSink<Item, Erased>>>;
+}
+
+trait DynReducerC<V> {
+	fn into_async(self: Box<Self>) -> Pin<Box<dyn DynSink<Erased, V>>>;
+}
+
+struct ErasedReducerA<P, R>(P, R);
+
+impl<P, R, Item> DynReducerA<Item> for ErasedReducerA<P, R>
+where
+	P: PipeTask<Item> + Clone + Send + 'static,
+	P::Async: 'static,
+	R: ReducerSend<P::Output> + Clone + Send + 'static,
+	R::Async: 'static,
+	Item: 'static,
+{
+	fn clone_box(&self) -> Box<dyn DynReducerA<Item>> {
+		Box::new(ErasedReducerA(self.0.clone(), self.1.clone()))
+	}
+	fn into_async(self: Box<Self>) -> Pin<Box<dyn DynSink<Item, Erased>>> {
+		let self_ = *self;
+		Box::pin(ErasedSinkA(self_.0.into_async().sink(self_.1.into_async())))
+	}
+}
+
+#[pin_project]
+struct ErasedSinkA<S>(#[pin] S);
+
+impl<S: Sink<Item>, Item> DynSink<Item, Erased> for ErasedSinkA<S>
+where
+	S::Done: Send + 'static,
+{
+	fn poll_forward_dyn(
+		self: Pin<&mut Self>, cx: &mut Context, stream: Pin<&mut dyn Stream<Item = Item>>,
+	) -> Poll<Erased> {
+		let mut stream = stream;
+		self.project()
+			.0
+			.poll_forward(cx, Pin::new(&mut stream))
+			.map(|done| Box::new(done) as Erased)
+	}
+}
+
+struct ErasedReducerC<R, A>(R, PhantomData<fn() -> A>);
+
+impl<R, A, V> DynReducerC<V> for ErasedReducerC<R, A>
+where
+	R: Reducer<A> + 'static,
+	R::Async: 'static,
+	R::Done: Into<V>,
+	A: 'static,
+{
+	fn into_async(self: Box<Self>) -> Pin<Box<dyn DynSink<Erased, V>>> {
+		Box::pin(ErasedSinkC(self.0.into_async(), PhantomData))
+	}
+}
+
+#[pin_project]
+struct ErasedSinkC<S, A>(#[pin] S, PhantomData<fn() -> A>);
+
+impl<S: Sink<A>, A: 'static, V> DynSink<Erased, V> for ErasedSinkC<S, A>
+where
+	S::Done: Into<V>,
+{
+	fn poll_forward_dyn(
+		self: Pin<&mut Self>, cx: &mut Context, stream: Pin<&mut dyn Stream<Item = Erased>>,
+	) -> Poll<V> {
+		let stream = stream.map(|item| *item.downcast::<A>().unwrap());
+		pin_mut!(stream);
+		self.project().0.poll_forward(cx, stream).map(Into::into)
+	}
+}
+
+pub struct SinkMapReducerA<Item>(Vec<Box<dyn DynReducerA<Item>>>);
+
+impl<Item> Clone for SinkMapReducerA<Item> {
+	fn clone(&self) -> Self {
+		Self(self.0.iter().map(|reducer| reducer.clone_box()).collect())
+	}
+}
+
+impl<Item> Reducer<Item> for SinkMapReducerA<Item>
+where
+	Item: Clone,
+{
+	type Done = Vec<Erased>;
+	type Async = SinkMapReducerAAsync<Item>;
+
+	fn into_async(self) -> Self::Async {
+		SinkMapReducerAAsync(SinkMapAsync::new(
+			self.0.into_iter().map(DynReducerA::into_async).collect(),
+		))
+	}
+}
+impl<Item> ReducerSend<Item> for SinkMapReducerA<Item>
+where
+	Item: Clone,
+{
+	type Done = Vec<Erased>;
+}
+
+#[pin_project]
+pub struct SinkMapReducerAAsync<Item>(#[pin] SinkMapAsync<Item, Erased>);
+
+impl<Item> Sink<Item> for SinkMapReducerAAsync<Item>
+where
+	Item: Clone,
+{
+	type Done = Vec<Erased>;
+
+	#[inline(always)]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, stream: Pin<&mut impl Stream<Item = Item>>,
+	) -> Poll<Self::Done> {
+		self.project()
+			.0
+			.poll_forward_with(cx, stream, |item, slots, done| {
+				// Clone the item for each sink still running, moving it into the last
+				let mut slots = slots
+					.iter_mut()
+					.zip(done)
+					.filter(|(_, done)| done.is_none())
+					.map(|(slot, _)| slot);
+				if let Some(mut last) = slots.next() {
+					for slot in slots {
+						*last = Some(item.clone());
+						last = slot;
+					}
+					*last = Some(item);
+				}
+			})
+	}
+}
+
+pub struct SinkMapReducerC<K, V>(Vec<K>, Vec<Box<dyn DynReducerC<V>>>);
+
+impl<K, V> Reducer<Vec<Erased>> for SinkMapReducerC<K, V>
+where
+	K: Eq + Hash,
+{
+	type Done = IndexMap<K, V>;
+	type Async = SinkMapReducerCAsync<K, V>;
+
+	fn into_async(self) -> Self::Async {
+		SinkMapReducerCAsync(
+			Some(self.0),
+			SinkMapAsync::new(self.1.into_iter().map(DynReducerC::into_async).collect()),
+		)
+	}
+}
+
+#[pin_project]
+pub struct SinkMapReducerCAsync<K, V>(Option<Vec<K>>, #[pin] SinkMapAsync<Erased, V>);
+
+impl<K, V> Sink<Vec<Erased>> for SinkMapReducerCAsync<K, V>
+where
+	K: Eq + Hash,
+{
+	type Done = IndexMap<K, V>;
+
+	#[inline(always)]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, stream: Pin<&mut impl Stream<Item = Vec<Erased>>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		let done = ready!(self_.1.poll_forward(cx, stream));
+		Poll::Ready(self_.0.take().unwrap().into_iter().zip(done).collect())
+	}
+}
+
+/// Forwards the `i`th element of each incoming `Vec` to the `i`th sink, until every sink is done.
+#[pin_project]
+pub struct SinkMapAsync<T, D> {
+	sinks: Vec<Pin<Box<dyn DynSink<T, D>>>>,
+	pending: Vec<Option<T>>,
+	done: Vec<Option<D>>,
+	finished: bool,
+}
+impl<T, D> SinkMapAsync<T, D> {
+	fn new(sinks: Vec<Pin<Box<dyn DynSink<T, D>>>>) -> Self {
+		let pending = sinks.iter().map(|_| None).collect();
+		let done = sinks.iter().map(|_| None).collect();
+		Self {
+			sinks,
+			pending,
+			done,
+			finished: false,
+		}
+	}
+}
+
+impl<T, D> SinkMapAsync<T, D> {
+	/// Forward the items of `stream` to the sinks, with `split` filling the pending slot of each
+	/// sink that isn't yet done from each incoming item.
+	#[inline(always)]
+	fn poll_forward_with<I>(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = I>>,
+		mut split: impl FnMut(I, &mut [Option<T>], &[Option<D>]),
+	) -> Poll<Vec<D>> {
+		let self_ = self.project();
+		loop {
+			let mut pending = false;
+			for ((sink, item), done) in self_
+				.sinks
+				.iter_mut()
+				.zip(self_.pending.iter_mut())
+				.zip(self_.done.iter_mut())
+			{
+				if done.is_some() || (item.is_none() && !*self_.finished) {
+					continue;
+				}
+				let waker = cx.waker();
+				let finished = *self_.finished;
+				let stream = stream::poll_fn(|cx| match item.take() {
+					Some(item) => Poll::Ready(Some(item)),
+					None if finished => Poll::Ready(None),
+					None => {
+						let waker_ = cx.waker();
+						if !waker.will_wake(waker_) {
+							waker_.wake_by_ref();
+						}
+						Poll::Pending
+					}
+				});
+				pin_mut!(stream);
+				if let Poll::Ready(done_) = sink.as_mut().poll_forward_dyn(cx, stream) {
+					*done = Some(done_);
+					*item = None;
+				} else if item.is_some() || *self_.finished {
+					pending = true;
+				}
+			}
+			if self_.done.iter().all(Option::is_some) {
+				return Poll::Ready(
+					self_
+						.done
+						.iter_mut()
+						.map(|done| done.take().unwrap())
+						.collect(),
+				);
+			}
+			if pending {
+				return Poll::Pending;
+			}
+			if let Some(item) = ready!(stream.as_mut().poll_next(cx)) {
+				split(item, self_.pending, self_.done);
+			} else {
+				*self_.finished = true;
+			}
+		}
+	}
+}
+
+impl<T, D> Sink<Vec<T>> for SinkMapAsync<T, D> {
+	type Done = Vec<D>;
+
+	#[inline(always)]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, stream: Pin<&mut impl Stream<Item = Vec<T>>>,
+	) -> Poll<Self::Done> {
+		self.poll_forward_with(cx, stream, |items, slots, done| {
+			for ((item, slot), done) in items.into_iter().zip(slots).zip(done) {
+				if done.is_none() {
+					*slot = Some(item);
+				}
+			}
+		})
+	}
+}
+
+/// The distributed counterpart of [`SinkMap`], usable with a `ProcessPool`.
+///
+/// As every process runs the same binary, each boxed reducer is sent as its bincode-serialized
+/// self alongside the offset of a function that knows how to deserialize it.
+#[must_use]
+pub struct DistSinkMap<K, Item, V> {
+	sinks: IndexMap<K, DistSinkMapEntry<Item, V>>,
+}
+
+struct DistSinkMapEntry<Item, V> {
+	reduce_a: Serialized<Pin<Box<dyn DynSink<Item, Erased>>>>,
+	reduce_b: Serialized<Pin<Box<dyn DynSink<Erased, Vec<u8>>>>>,
+	reduce_c: Box<dyn DynReducerC<V>>,
+	decode: fn(&[u8]) -> Erased,
+}
+
+impl<K, Item, V> DistSinkMap<K, Item, V>
+where
+	K: Eq + Hash,
+	Item: Clone + 'static,
+	V: 'static,
+{
+	/// Create an empty `DistSinkMap`.
+	pub fn new() -> Self {
+		Self {
+			sinks: IndexMap::new(),
+		}
+	}
+
+	/// Add a sink, replacing any sink previously added under the same key.
+	pub fn insert<S>(&mut self, key: K, sink: S)
+	where
+		S: DistributedSink<Item>,
+		<S::Pipe as DistributedPipe<Item>>::Task: 'static,
+		<<S::Pipe as DistributedPipe<Item>>::Task as PipeTask<Item>>::Async: 'static,
+		S::ReduceA: 'static,
+		<S::ReduceA as Reducer<<S::Pipe as DistributedPipe<Item>>::Output>>::Async: 'static,
+		S::ReduceB: 'static,
+		<S::ReduceB as Reducer<
+			<S::ReduceA as ReducerSend<<S::Pipe as DistributedPipe<Item>>::Output>>::Done,
+		>>::Async: 'static,
+		S::ReduceC: 'static,
+		<S::ReduceC as Reducer<
+			<S::ReduceB as ReducerProcessSend<
+				<S::ReduceA as ReducerSend<<S::Pipe as DistributedPipe<Item>>::Output>>::Done,
+			>>::Done,
+		>>::Async: 'static,
+		S::Done: Into<V>,
+	{
+		let (pipe, reduce_a, reduce_b, reduce_c) = sink.reducers();
+		let _ = self.sinks.insert(
+			key,
+			DistSinkMapEntry {
+				reduce_a: Serialized::new(
+					&(pipe.task(), reduce_a),
+					rebuild_a::<DistTask<S, Item>, S::ReduceA, Item>,
+				),
+				reduce_b: Serialized::new(&reduce_b, rebuild_b::<S::ReduceB, DistDoneA<S, Item>>),
+				reduce_c: Box::new(ErasedReducerC(reduce_c, PhantomData)),
+				decode: decode::<DistDoneB<S, Item>>,
+			},
+		);
+	}
+
+	/// Add a sink, replacing any sink previously added under the same key, and return `self` for
+	/// chaining.
+	pub fn add<S>(mut self, key: K, sink: S) -> Self
+	where
+		S: DistributedSink<Item>,
+		<S::Pipe as DistributedPipe<Item>>::Task: 'static,
+		<<S::Pipe as DistributedPipe<Item>>::Task as PipeTask<Item>>::Async: 'static,
+		S::ReduceA: 'static,
+		<S::ReduceA as Reducer<<S::Pipe as DistributedPipe<Item>>::Output>>::Async: 'static,
+		S::ReduceB: 'static,
+		<S::ReduceB as Reducer<
+			<S::ReduceA as ReducerSend<<S::Pipe as DistributedPipe<Item>>::Output>>::Done,
+		>>::Async: 'static,
+		S::ReduceC: 'static,
+		<S::ReduceC as Reducer<
+			<S::ReduceB as ReducerProcessSend<
+				<S::ReduceA as ReducerSend<<S::Pipe as DistributedPipe<Item>>::Output>>::Done,
+			>>::Done,
+		>>::Async: 'static,
+		S::Done: Into<V>,
+	{
+		self.insert(key, sink);
+		self
+	}
+
+	/// The number of sinks.
+	pub fn len(&self) -> usize {
+		self.sinks.len()
+	}
+
+	/// Returns whether there are no sinks.
+	pub fn is_empty(&self) -> bool {
+		self.sinks.is_empty()
+	}
+}
+
+impl<K, Item, V> Default for DistSinkMap<K, Item, V>
+where
+	K: Eq + Hash,
+	Item: Clone + 'static,
+	V: 'static,
+{
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<K, Item, V> DistributedSink<Item> for DistSinkMap<K, Item, V>
+where
+	K: Eq + Hash,
+	Item: Clone + 'static,
+	V: 'static,
+{
+	type Done = IndexMap<K, V>;
+	type Pipe = Identity;
+	type ReduceA = DistSinkMapReducerA<Item>;
+	type ReduceB = DistSinkMapReducerB;
+	type ReduceC = DistSinkMapReducerC<K, V>;
+
+	fn reducers(self) -> (Self::Pipe, Self::ReduceA, Self::ReduceB, Self::ReduceC) {
+		let (mut reduce_a, mut reduce_b, mut keys, mut reduce_c, mut decode) =
+			(vec![], vec![], vec![], vec![], vec![]);
+		for (key, entry) in self.sinks {
+			reduce_a.push(entry.reduce_a);
+			reduce_b.push(entry.reduce_b);
+			keys.push(key);
+			reduce_c.push(entry.reduce_c);
+			decode.push(entry.decode);
+		}
+		(
+			Identity,
+			DistSinkMapReducerA(reduce_a),
+			DistSinkMapReducerB(reduce_b),
+			DistSinkMapReducerC(SinkMapReducerC(keys, reduce_c), decode),
+		)
+	}
+}
+
+type DistTask<S, Item> = <<S as DistributedSink<Item>>::Pipe as DistributedPipe<Item>>::Task;
+type DistDoneA<S, Item> = <<S as DistributedSink<Item>>::ReduceA as ReducerSend<
+	<<S as DistributedSink<Item>>::Pipe as DistributedPipe<Item>>::Output,
+>>::Done;
+type DistDoneB<S, Item> =
+	<<S as DistributedSink<Item>>::ReduceB as ReducerProcessSend<DistDoneA<S, Item>>>::Done;
+
+/// Reducers are sent as offsets from this function, as the binary may be loaded at a different
+/// address in each process.
+#[inline(never)]
+fn base() {}
+
+/// A value serialized alongside the offset of the function that rebuilds it as a `T`.
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone)]
+#[serde(bound = "")]
+struct Serialized<T> {
+	rebuild: usize,
+	bytes: Vec<u8>,
+	marker: PhantomData<fn() -> T>,
+}
+impl<T> Serialized<T> {
+	fn new<U: Serialize>(value: &U, rebuild: fn(&[u8]) -> T) -> Self {
+		Self {
+			rebuild: (rebuild as *const () as usize).wrapping_sub(base as *const () as usize),
+			bytes: bincode::serialize(value).unwrap(),
+			marker: PhantomData,
+		}
+	}
+	fn rebuild(&self) -> T {
+		// SAFETY: the offset was taken from a `fn(&[u8]) -> T` in a process running this same
+		// binary.
+		#[allow(unsafe_code)]
+		let rebuild = unsafe {
+			mem::transmute::<usize, fn(&[u8]) -> T>(
+				(base as *const () as usize).wrapping_add(self.rebuild),
+			)
+		};
+		rebuild(&self.bytes)
+	}
+}
+
+fn rebuild_a<P, R, Item>(bytes: &[u8]) -> Pin<Box<dyn DynSink<Item, Erased>>>
+where
+	P: PipeTask<Item> + ProcessSend + 'static,
+	P::Async: 'static,
+	R: ReducerSend<P::Output> + ProcessSend + 'static,
+	R::Async: 'static,
+	Item: 'static,
+{
+	let (pipe, reduce_a): (P, R) = bincode::deserialize(bytes).unwrap();
+	Box::pin(ErasedSinkA(pipe.into_async().sink(reduce_a.into_async())))
+}
+
+fn rebuild_b<R, A>(bytes: &[u8]) -> Pin<Box<dyn DynSink<Erased, Vec<u8>>>>
+where
+	R: ReducerProcessSend<A> + ProcessSend + 'static,
+	R::Async: 'static,
+	A: 'static,
+{
+	let reduce_b: R = bincode::deserialize(bytes).unwrap();
+	Box::pin(ErasedSinkB(reduce_b.into_async(), PhantomData))
+}
+
+fn decode<A: ProcessSend + 'static>(bytes: &[u8]) -> Erased {
+	Box::new(bincode::deserialize::<A>(bytes).unwrap())
+}
+
+#[pin_project]
+struct ErasedSinkB<S, A>(#[pin] S, PhantomData<fn() -> A>);
+
+impl<S: Sink<A>, A: 'static> DynSink<Erased, Vec<u8>> for ErasedSinkB<S, A>
+where
+	S::Done: Serialize,
+{
+	fn poll_forward_dyn(
+		self: Pin<&mut Self>, cx: &mut Context, stream: Pin<&mut dyn Stream<Item = Erased>>,
+	) -> Poll<Vec<u8>> {
+		let stream = stream.map(|item| *item.downcast::<A>().unwrap());
+		pin_mut!(stream);
+		self.project()
+			.0
+			.poll_forward(cx, stream)
+			.map(|done| bincode::serialize(&done).unwrap())
+	}
+}
+
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub struct DistSinkMapReducerA<Item>(Vec<Serialized<Pin<Box<dyn DynSink<Item, Erased>>>>>);
+
+impl<Item> Reducer<Item> for DistSinkMapReducerA<Item>
+where
+	Item: Clone,
+{
+	type Done = Vec<Erased>;
+	type Async = SinkMapReducerAAsync<Item>;
+
+	fn into_async(self) -> Self::Async {
+		SinkMapReducerAAsync(SinkMapAsync::new(
+			self.0.iter().map(Serialized::rebuild).collect(),
+		))
+	}
+}
+impl<Item> ReducerSend<Item> for DistSinkMapReducerA<Item>
+where
+	Item: Clone,
+{
+	type Done = Vec<Erased>;
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DistSinkMapReducerB(Vec<Serialized<Pin<Box<dyn DynSink<Erased, Vec<u8>>>>>>);
+
+impl Reducer<Vec<Erased>> for DistSinkMapReducerB {
+	type Done = Vec<Vec<u8>>;
+	type Async = SinkMapAsync<Erased, Vec<u8>>;
+
+	fn into_async(self) -> Self::Async {
+		SinkMapAsync::new(self.0.iter().map(Serialized::rebuild).collect())
+	}
+}
+impl ReducerProcessSend<Vec<Erased>> for DistSinkMapReducerB {
+	type Done = Vec<Vec<u8>>;
+}
+impl ReducerSend<Vec<Erased>> for DistSinkMapReducerB {
+	type Done = Vec<Vec<u8>>;
+}
+
+pub struct DistSinkMapReducerC<K, V>(SinkMapReducerC<K, V>, Vec<fn(&[u8]) -> Erased>);
+
+impl<K, V> Reducer<Vec<Vec<u8>>> for DistSinkMapReducerC<K, V>
+where
+	K: Eq + Hash,
+{
+	type Done = IndexMap<K, V>;
+	type Async = DistSinkMapReducerCAsync<K, V>;
+
+	fn into_async(self) -> Self::Async {
+		DistSinkMapReducerCAsync(self.0.into_async(), self.1)
+	}
+}
+
+#[pin_project]
+pub struct DistSinkMapReducerCAsync<K, V>(
+	#[pin] SinkMapReducerCAsync<K, V>,
+	Vec<fn(&[u8]) -> Erased>,
+);
+
+impl<K, V> Sink<Vec<Vec<u8>>> for DistSinkMapReducerCAsync<K, V>
+where
+	K: Eq + Hash,
+{
+	type Done = IndexMap<K, V>;
+
+	#[inline(always)]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context, stream: Pin<&mut impl Stream<Item = Vec<Vec<u8>>>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		let decode = &*self_.1;
+		let stream = stream.map(|items| {
+			items
+				.iter()
+				.zip(decode)
+				.map(|(item, decode)| decode(item))
+				.collect::<Vec<_>>()
+		});
+		pin_mut!(stream);
+		self_.0.poll_forward(cx, stream)
+	}
+}
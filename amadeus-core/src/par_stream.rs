@@ -3,7 +3,10 @@
 
 #![allow(clippy::too_many_lines, unused_qualifications)]
 
+mod batch;
+mod cancel;
 mod chain;
+mod checkpoint;
 mod cloned;
 mod filter;
 mod filter_map_sync;
@@ -14,25 +17,37 @@ mod inspect;
 mod join;
 mod map;
 mod map_sync;
+mod memory;
+mod monitor;
+mod repartition;
+mod retry;
+mod skip;
 mod sum_type;
+mod take;
+mod task_timeout;
 mod update;
 
 use async_trait::async_trait;
 use either::Either;
-use futures::{future, pin_mut, stream::StreamExt as _, Stream};
+use futures::{
+	future, pin_mut, stream::{self, FuturesUnordered, StreamExt as _}, task::AtomicWaker, Stream
+};
 use indexmap::IndexMap;
 use serde_closure::{traits, FnOnce};
 use std::{
-	cmp::Ordering, hash::Hash, iter, ops, pin::Pin, task::{Context, Poll}, vec
+	cmp::Ordering, collections::VecDeque, hash::Hash, iter, ops, pin::Pin, sync::{Arc, Mutex}, task::{Context, Poll}, time::Duration
 };
+use tracing::{debug_span, info_span, Instrument, Span};
 
 use super::{par_pipe::*, par_sink::*};
 use crate::{
 	into_par_stream::{IntoDistributedStream, IntoParallelStream}, pipe::{Sink, StreamExt}, pool::{ProcessPool, ProcessSend, ThreadPool}
 };
 
+use self::take::{Progress, Take, TakeFinalReducer, TakeMergeReducer, TakeReducer};
+
 pub use self::{
-	chain::*, cloned::*, filter::*, filter_map_sync::*, flat_map::*, flat_map_sync::*, identity::*, inspect::*, join::*, map::*, map_sync::*, update::*
+	batch::*, cancel::*, chain::*, checkpoint::Checkpoint, cloned::*, filter::*, filter_map_sync::*, flat_map::*, flat_map_sync::*, identity::*, inspect::*, join::*, map::*, map_sync::*, memory::*, monitor::*, repartition::*, retry::*, skip::*, task_timeout::*, update::*
 };
 
 #[must_use]
@@ -40,6 +55,14 @@ pub trait StreamTask {
 	type Item;
 	type Async: Stream<Item = Self::Item>;
 
+	/// The source partition this task reads, if known, to name the task in errors.
+	fn partition(&self) -> Option<String> {
+		None
+	}
+	/// Bounds on the number of items this task yields, as for [`Iterator::size_hint()`].
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(0, None)
+	}
 	fn into_async(self) -> Self::Async;
 }
 
@@ -103,6 +126,70 @@ macro_rules! stream {
 				$assert_stream(Filter::new(self, f))
 			}
 
+			#[inline]
+			fn batch<B>(self, size: usize) -> Batch<Self, B>
+			where
+				B: Default + Extend<Self::Item> + 'static,
+				Self: Sized,
+			{
+				$assert_stream(Batch::new(self, size))
+			}
+
+			#[inline]
+			fn chunks(self, size: usize) -> Batch<Self, Vec<Self::Item>>
+			where
+				Self::Item: 'static,
+				Self: Sized,
+			{
+				$assert_stream(Batch::new(self, size))
+			}
+
+			#[inline]
+			fn unbatch(self) -> Unbatch<Self>
+			where
+				Self::Item: IntoIterator,
+				Self: Sized,
+			{
+				$assert_stream(Unbatch::new(self))
+			}
+
+			/// Re-run tasks whose stream panics, up to `retries` times each. A retried task is run
+			/// again from the start of its partition, with the items it already yielded read again
+			/// and discarded, so a late failure in a large partition costs a re-read of all of it.
+			#[inline]
+			fn retry(self, retries: usize) -> Retry<Self>
+			where
+				Self::Task: Clone,
+				Self: Sized,
+			{
+				$assert_stream(Retry::new(self, retries))
+			}
+
+			#[inline]
+			fn skip(self, n: usize) -> Skip<Self, Self::Task>
+			where
+				Self: Sized,
+			{
+				$assert_stream(Skip::new(self, n))
+			}
+
+			#[inline]
+			fn task_timeout(self, timeout: Duration) -> TaskTimeout<Self>
+			where
+				Self: Sized,
+			{
+				$assert_stream(TaskTimeout::new(self, timeout))
+			}
+
+			#[inline]
+			fn monitor(self, metrics: &Metrics) -> Monitor<Self>
+			where
+				Self: Sized,
+			{
+				let expected = self.size_hint().1;
+				$assert_stream(Monitor::new(self, metrics.clone(), expected))
+			}
+
 			#[inline]
 			fn left_join<K, V1, V2>(self, right: impl IntoIterator<Item = (K, V2)>) -> LeftJoin<Self, K, V1, V2>
 			where
@@ -239,6 +326,30 @@ macro_rules! stream {
 				.await
 			}
 
+			#[inline]
+			async fn quantiles<P>(self, pool: &P, quantiles: &[f64]) -> Vec<Option<f64>>
+			where
+				P: $pool,
+				Self::Item: 'static,
+				Self::Task: 'static,
+				Self: $stream<Item = f64> + Sized,
+			{
+				self.pipe(pool, $pipe::<Self::Item>::quantiles(Identity, quantiles))
+					.await
+			}
+
+			#[inline]
+			async fn percentile<P>(self, pool: &P, percentile: f64) -> Option<f64>
+			where
+				P: $pool,
+				Self::Item: 'static,
+				Self::Task: 'static,
+				Self: $stream<Item = f64> + Sized,
+			{
+				self.pipe(pool, $pipe::<Self::Item>::percentile(Identity, percentile))
+					.await
+			}
+
 			#[inline]
 			async fn combine<P, F>(self, pool: &P, f: F) -> Option<Self::Item>
 			where
@@ -422,6 +533,188 @@ macro_rules! stream {
 	}
 }
 
+/// Spawns workers that pull `tasks` from a shared queue as they become free, so a worker that gets
+/// through its tasks quickly goes on to take more, rather than sitting idle. The queue is topped up
+/// from `tasks` as it's drained, holding at most one task per thread, and workers are spawned, up to
+/// one per thread, while it's non-empty. Workers never wait on the queue: one that finds it empty
+/// finishes, so this works with pools that run work to completion as soon as it's spawned.
+///
+/// Each task runs in a `task` span, a child of the span current when this is called.
+fn spawn_workers<P, S, T, R>(
+	pool: &P, mut tasks: S, reduce_a: R,
+) -> impl Stream<Item = <R as ReducerSend<T::Item>>::Done>
+where
+	P: ThreadPool,
+	S: Stream<Item = T> + Unpin,
+	T: StreamTask + Send + 'static,
+	R: ReducerSend<T::Item> + Clone + Send + 'static,
+{
+	let threads = pool.threads();
+	let queue = Arc::new(Mutex::new(VecDeque::<(usize, T)>::with_capacity(threads)));
+	let waker = Arc::new(AtomicWaker::new());
+	let parent = Span::current();
+	let spawn = {
+		let (pool, queue, waker) = (pool.clone(), queue.clone(), waker.clone());
+		move || {
+			let (queue, waker, reduce_a, parent) = (
+				queue.clone(),
+				waker.clone(),
+				reduce_a.clone(),
+				parent.clone(),
+			);
+			// The partition of the task the worker is running, to name it if it panics.
+			let current = Arc::new(Mutex::new(None));
+			let current_ = current.clone();
+			let worker = pool.spawn(move || async move {
+				let sink = reduce_a.into_async();
+				pin_mut!(sink);
+				loop {
+					let task = queue.lock().unwrap().pop_front();
+					let (index, task) = if let Some((index, task)) = task {
+						waker.wake();
+						*current_.lock().unwrap() = task.partition();
+						(index, task.into_async())
+					} else {
+						break;
+					};
+					pin_mut!(task);
+					let span = debug_span!(parent: &parent, "task", index);
+					if let Some(ret) = sink.send_all(&mut task).instrument(span).await {
+						return ret;
+					}
+				}
+				sink.done().await
+			});
+			async move {
+				worker.await.unwrap_or_else(|err| {
+					let partition = current.lock().unwrap().take();
+					panic!(
+						"Amadeus: task '{}' panicked at '{}'",
+						partition.as_deref().unwrap_or("<unnamed>"),
+						err
+					)
+				})
+			}
+		}
+	};
+	let mut workers = FuturesUnordered::new();
+	let (mut next, mut exhausted) = (0, false);
+	stream::poll_fn(move |cx| {
+		waker.register(cx.waker());
+		loop {
+			let mut queue_ = queue.lock().unwrap();
+			while !exhausted && queue_.len() < threads {
+				match tasks.poll_next_unpin(cx) {
+					Poll::Ready(Some(task)) => {
+						queue_.push_back((next, task));
+						next += 1;
+					}
+					Poll::Ready(None) => exhausted = true,
+					Poll::Pending => break,
+				}
+			}
+			let queued = !queue_.is_empty();
+			drop(queue_);
+			if queued && workers.len() < threads {
+				workers.push(spawn());
+			} else {
+				break;
+			}
+		}
+		match workers.poll_next_unpin(cx) {
+			Poll::Ready(Some(done)) => Poll::Ready(Some(done)),
+			Poll::Ready(None) if exhausted && queue.lock().unwrap().is_empty() => Poll::Ready(None),
+			_ => Poll::Pending,
+		}
+	})
+}
+
+/// Hands out the tasks of `stream` to the processes of `pool` in batches, each reduced on its
+/// process by `reduce_a` and `reduce_b`, yielding each batch's result along with the indices of its
+/// tasks. Batches are only made as processes become free, and shrink as the stream is drained so
+/// that the processes finish at around the same time. Tasks whose index `include` rejects are
+/// skipped.
+///
+/// Each batch runs in a `process_task` span, a child of `job`, and sends back the counts of any
+/// [`Metrics`] its tasks report to.
+fn spawn_batches<'a, S, P, R1, R2>(
+	stream: Pin<&'a mut S>, pool: &'a P, reduce_a: R1, reduce_b: R2, job: &'a Span,
+	include: impl FnMut(usize) -> bool + 'a,
+) -> impl Stream<
+	Item = (
+		Vec<usize>,
+		<R2 as ReducerProcessSend<<R1 as ReducerSend<S::Item>>::Done>>::Done,
+	),
+> + 'a
+where
+	S: DistributedStream,
+	P: ProcessPool,
+	R1: ReducerSend<S::Item> + Clone + ProcessSend + 'static,
+	R2: ReducerProcessSend<<R1 as ReducerSend<S::Item>>::Done> + Clone + ProcessSend + 'static,
+	S::Task: 'static,
+{
+	let processes = pool.processes();
+	let batches = stream::unfold(
+		(stream, 0, include),
+		move |(mut stream, mut index, mut include)| async move {
+			let size = (stream.size_hint().0 / (2 * processes)).max(1);
+			let (mut indices, mut batch) = (Vec::with_capacity(size), Vec::with_capacity(size));
+			while batch.len() < size {
+				if let Some(task) = future::poll_fn(|cx| stream.as_mut().next_task(cx)).await {
+					if include(index) {
+						indices.push(index);
+						batch.push(task);
+					}
+					index += 1;
+				} else {
+					break;
+				}
+			}
+			if !batch.is_empty() {
+				Some(((indices, batch), (stream, index, include)))
+			} else {
+				None
+			}
+		},
+	);
+	batches
+		.map(move |(indices, tasks)| {
+			let reduce_b = reduce_b.clone();
+			let reduce_a = reduce_a.clone();
+			let span = debug_span!(parent: job, "process_task", tasks = tasks.len());
+			// The partitions of the batch, to name it if its process fails.
+			let partitions = tasks
+				.iter()
+				.filter_map(StreamTask::partition)
+				.collect::<Vec<_>>()
+				.join(", ");
+			let state = pool.spawn(FnOnce!(move |pool: &P::ThreadPool| {
+				let stream = spawn_workers(pool, futures::stream::iter(tasks), reduce_a);
+				let reduce_b = reduce_b.into_async();
+				async move {
+					pin_mut!(reduce_b);
+					let state = stream.sink(reduce_b).await;
+					(state, monitor::take_remote(), memory::take_remote())
+				}
+			}));
+			async move { (indices, partitions, state.await) }.instrument(span)
+		})
+		.buffer_unordered(processes)
+		.map(|(indices, partitions, state)| {
+			let (state, metrics, exceeded) = state.unwrap_or_else(|err| {
+				let partitions = if partitions.is_empty() {
+					"<unnamed>"
+				} else {
+					&partitions
+				};
+				panic!("Amadeus: task '{}' panicked at '{}'", partitions, err)
+			});
+			monitor::add_remote(metrics);
+			memory::add_remote(exceeded);
+			(indices, state)
+		})
+}
+
 stream!(ParallelStream ParallelPipe ParallelSink FromParallelStream IntoParallelStream into_par_stream ParStream ThreadPool Send ops assert_parallel_stream {
 	async fn reduce<P, B, R1, R3>(mut self, pool: &P, reduce_a: R1, reduce_c: R3) -> B
 	where
@@ -433,73 +726,16 @@ stream!(ParallelStream ParallelPipe ParallelSink FromParallelStream IntoParallel
 	{
 		let self_ = self;
 		pin_mut!(self_);
-		// TODO: don't buffer tasks before sending. requires changes to ThreadPool
-		let mut tasks = (0..pool.threads()).map(|_| vec![]).collect::<Vec<_>>();
-		let mut allocated = 0;
-		'a: loop {
-			for i in 0..tasks.len() {
-				loop {
-					let (mut lower, _upper) = self_.size_hint();
-					if lower == 0 {
-						lower = 1;
-					}
-					let mut batch = (allocated + lower) / tasks.len();
-					if i < (allocated + lower) % tasks.len() {
-						batch += 1;
-					}
-					batch -= tasks[i].len();
-					if batch == 0 {
-						break;
-					}
-					for _ in 0..batch {
-						if let Some(task) = future::poll_fn(|cx| self_.as_mut().next_task(cx)).await {
-							tasks[i].push(task);
-							allocated += 1;
-						} else {
-							break 'a;
-						}
-					}
-				}
-			}
+		let span = info_span!("job", threads = pool.threads());
+		async move {
+			let tasks = stream::poll_fn(|cx| self_.as_mut().next_task(cx));
+			let stream = spawn_workers(pool, tasks, reduce_a);
+			let reduce_c = reduce_c.into_async();
+			pin_mut!(reduce_c);
+			stream.sink(reduce_c).await
 		}
-		for (i, task) in tasks.iter().enumerate() {
-			let mut count = allocated / tasks.len();
-			if i < allocated % tasks.len() {
-				count += 1;
-			}
-			assert_eq!(
-				task.len(),
-				count,
-				"alloc: {:#?}",
-				tasks.iter().map(Vec::len).collect::<Vec<_>>()
-			);
-		}
-
-		let handles = tasks
-			.into_iter()
-			.filter(|tasks| !tasks.is_empty())
-			.map(|tasks| {
-				let reduce_a = reduce_a.clone();
-				pool.spawn(move || async move {
-					let sink = reduce_a.into_async();
-					pin_mut!(sink);
-					// this is faster than stream::iter(tasks.into_iter().map(StreamTask::into_async)).flatten().sink(sink).await
-					for task in tasks.into_iter().map(StreamTask::into_async) {
-							pin_mut!(task);
-							if let Some(ret) = sink.send_all(&mut task).await {
-									return ret;
-							}
-					}
-					sink.done().await
-				})
-			})
-			.collect::<futures::stream::FuturesUnordered<_>>();
-		let stream = handles.map(|item| {
-			item.unwrap_or_else(|err| panic!("Amadeus: task '<unnamed>' panicked at '{}'", err))
-		});
-		let reduce_c = reduce_c.into_async();
-		pin_mut!(reduce_c);
-		stream.sink(reduce_c).await
+		.instrument(span)
+		.await
 	}
 
 	async fn pipe<P, ParSink, A>(self, pool: &P, sink: ParSink) -> A
@@ -574,6 +810,88 @@ stream!(ParallelStream ParallelPipe ParallelSink FromParallelStream IntoParallel
 		self.pipe(pool, ParallelPipe::<Self::Item>::collect(Identity))
 			.await
 	}
+
+	/// The first `n` items, in the partition ordering of the stream. Use `take(pool, 1)` for just
+	/// the first.
+	async fn take<P>(self, pool: &P, n: usize) -> Vec<Self::Item>
+	where
+		P: ThreadPool,
+		Self::Item: Send + 'static,
+		Self::Task: 'static,
+		Self: Sized,
+	{
+		let progress = Progress::new(n);
+		Take::new(self, n, progress.clone())
+			.reduce(
+				pool,
+				TakeReducer::new(n),
+				TakeFinalReducer::new(n, progress),
+			)
+			.await
+	}
+
+	/// Redistributes the items into new tasks of `size` items each, so that work after this stage
+	/// is spread evenly over `pool` however unevenly the items were spread between the source's
+	/// partitions. The stream up to this point is run on `pool`, with its items passing through
+	/// this process a few tasks' worth at a time.
+	fn repartition<P>(self, pool: &P, size: usize) -> Repartition<Self, P, Self::Item>
+	where
+		P: ThreadPool,
+		Self::Item: Send + 'static,
+		Self::Task: 'static,
+		Self: Sized,
+	{
+		Repartition::new(self, pool.clone(), pool.threads(), Some(size))
+	}
+
+	/// As `repartition`, but splitting the items of each task evenly between the threads of
+	/// `pool`.
+	fn rebalance<P>(self, pool: &P) -> Repartition<Self, P, Self::Item>
+	where
+		P: ThreadPool,
+		Self::Item: Send + 'static,
+		Self::Task: 'static,
+		Self: Sized,
+	{
+		Repartition::new(self, pool.clone(), pool.threads(), None)
+	}
+
+	/// As `pipe`, but stopping early and returning [`Cancelled`] if `token` is cancelled before
+	/// the job finishes.
+	async fn cancellable<P, ParSink, A>(
+		self, pool: &P, token: &CancellationToken, sink: ParSink,
+	) -> Result<A, Cancelled>
+	where
+		P: ThreadPool,
+		ParSink: ParallelSink<Self::Item, Done = A>,
+		<ParSink::Pipe as ParallelPipe<Self::Item>>::Task: 'static,
+		ParSink::ReduceA: 'static,
+		Self::Task: 'static,
+		Self: Sized,
+	{
+		let stream = Cancellable::new(self, token.clone());
+		cancel::run(token, stream.pipe(pool, sink)).await
+	}
+
+	/// As `pipe`, but keeping within `budget`, reserving `task_memory` bytes for each task while
+	/// it runs, and returning [`MemoryExceeded`] if the job exceeds it regardless.
+	async fn memory_bounded<P, ParSink, A>(
+		self, pool: &P, budget: &MemoryBudget, task_memory: usize, sink: ParSink,
+	) -> Result<A, MemoryExceeded>
+	where
+		P: ThreadPool,
+		ParSink: ParallelSink<Self::Item, Done = A>,
+		<ParSink::Pipe as ParallelPipe<Self::Item>>::Task: 'static,
+		ParSink::ReduceA: 'static,
+		Self::Task: 'static,
+		Self: Sized,
+	{
+		budget.start();
+		let output = MemoryBounded::new(self, budget.clone(), task_memory)
+			.pipe(pool, sink)
+			.await;
+		budget.result(output)
+	}
 });
 
 stream!(DistributedStream DistributedPipe DistributedSink FromDistributedStream IntoDistributedStream into_dist_stream DistStream ProcessPool ProcessSend traits assert_distributed_stream cfg_attr(not(nightly), serde_closure::desugar) {
@@ -594,138 +912,18 @@ stream!(DistributedStream DistributedPipe DistributedSink FromDistributedStream
 		Self::Task: 'static,
 		Self: Sized,
 	{
+		let processes = pool.processes();
+		let job = info_span!("job", processes);
 		let self_ = self;
 		pin_mut!(self_);
-		// TODO: don't buffer tasks before sending. requires changes to ProcessPool
-		let mut tasks = (0..pool.processes()).map(|_| vec![]).collect::<Vec<_>>();
-		let mut allocated = 0;
-		'a: loop {
-			for i in 0..tasks.len() {
-				loop {
-					let (mut lower, _upper) = self_.size_hint();
-					if lower == 0 {
-						lower = 1;
-					}
-					let mut batch = (allocated + lower) / tasks.len();
-					if i < (allocated + lower) % tasks.len() {
-						batch += 1;
-					}
-					batch -= tasks[i].len();
-					if batch == 0 {
-						break;
-					}
-					for _ in 0..batch {
-						if let Some(task) = future::poll_fn(|cx| self_.as_mut().next_task(cx)).await {
-							tasks[i].push(task);
-							allocated += 1;
-						} else {
-							break 'a;
-						}
-					}
-				}
-			}
-		}
-		for (i, task) in tasks.iter().enumerate() {
-			let mut count = allocated / tasks.len();
-			if i < allocated % tasks.len() {
-				count += 1;
-			}
-			assert_eq!(
-				task.len(),
-				count,
-				"alloc: {:#?}",
-				tasks.iter().map(Vec::len).collect::<Vec<_>>()
-			);
-		}
-
-		let handles = tasks
-			.into_iter()
-			.filter(|tasks| !tasks.is_empty())
-			.map(|tasks| {
-				let reduce_b = reduce_b.clone();
-				let reduce_a = reduce_a.clone();
-				pool.spawn(FnOnce!(move |pool: &P::ThreadPool| {
-					let mut process_tasks = tasks.into_iter();
-
-					let mut tasks = (0..pool.threads()).map(|_| vec![]).collect::<Vec<_>>();
-					let mut allocated = 0;
-					'a: loop {
-						for i in 0..tasks.len() {
-							loop {
-								let (mut lower, _upper) = process_tasks.size_hint();
-								if lower == 0 {
-									lower = 1;
-								}
-								let mut batch = (allocated + lower) / tasks.len();
-								if i < (allocated + lower) % tasks.len() {
-									batch += 1;
-								}
-								batch -= tasks[i].len();
-								if batch == 0 {
-									break;
-								}
-								for _ in 0..batch {
-									if let Some(task) = process_tasks.next() {
-										tasks[i].push(task);
-										allocated += 1;
-									} else {
-										break 'a;
-									}
-								}
-							}
-						}
-					}
-					for (i, task) in tasks.iter().enumerate() {
-						let mut count = allocated / tasks.len();
-						if i < allocated % tasks.len() {
-							count += 1;
-						}
-						assert_eq!(
-							task.len(),
-							count,
-							"alloc: {:#?}",
-							tasks.iter().map(Vec::len).collect::<Vec<_>>()
-						);
-					}
-					let handles = tasks
-						.into_iter()
-						.filter(|tasks| !tasks.is_empty())
-						.map(|tasks| {
-							let reduce_a = reduce_a.clone();
-							pool.spawn(move || async move {
-								let sink = reduce_a.into_async();
-								pin_mut!(sink);
-								// this is faster than stream::iter(tasks.into_iter().map(StreamTask::into_async)).flatten().sink(sink).await
-								for task in tasks.into_iter().map(StreamTask::into_async) {
-										pin_mut!(task);
-										if let Some(ret) = sink.send_all(&mut task).await {
-												return ret;
-										}
-								}
-								sink.done().await
-							})
-						})
-						.collect::<futures::stream::FuturesUnordered<_>>();
-
-					let stream = handles.map(|item| {
-						item.unwrap_or_else(|err| {
-							panic!("Amadeus: task '<unnamed>' panicked at '{}'", err)
-						})
-					});
-					let reduce_b = reduce_b.into_async();
-					async move {
-						pin_mut!(reduce_b);
-						stream.sink(reduce_b).await
-					}
-				}))
-			})
-			.collect::<futures::stream::FuturesUnordered<_>>();
-		let stream = handles.map(|item| {
-			item.unwrap_or_else(|err| panic!("Amadeus: task '<unnamed>' panicked at '{}'", err))
-		});
+		let stream = spawn_batches(self_, pool, reduce_a, reduce_b, &job, |_| true);
 		let reduce_c = reduce_c.into_async();
 		pin_mut!(reduce_c);
-		stream.sink(reduce_c).await
+		stream
+			.map(|(_, state)| state)
+			.sink(reduce_c)
+			.instrument(job.clone())
+			.await
 	}
 
 	async fn pipe<P, DistSink, A>(self, pool: &P, sink: DistSink) -> A
@@ -744,6 +942,71 @@ stream!(DistributedStream DistributedPipe DistributedSink FromDistributedStream
 			.await
 	}
 
+	/// As `pipe`, but saving partial results to `checkpoint` as the job runs, and skipping the
+	/// tasks whose results were saved by an earlier, interrupted run of the job.
+	async fn checkpointed<P, DistSink, A>(
+		self, pool: &P, checkpoint: &Checkpoint, sink: DistSink,
+	) -> A
+	where
+		P: ProcessPool,
+		DistSink: DistributedSink<Self::Item, Done = A>,
+		<DistSink::Pipe as DistributedPipe<Self::Item>>::Task: 'static,
+		DistSink::ReduceA: 'static,
+		DistSink::ReduceB: 'static,
+		Self::Task: 'static,
+		Self: Sized,
+	{
+		let (iterator, reducer_a, reducer_b, reducer_c) = sink.reducers();
+		checkpoint::reduce(
+			Pipe::new(self, iterator),
+			pool,
+			checkpoint,
+			reducer_a,
+			reducer_b,
+			reducer_c,
+		)
+		.await
+	}
+
+	/// As `pipe`, but stopping early and returning [`Cancelled`] if `token` is cancelled before
+	/// the job finishes.
+	async fn cancellable<P, DistSink, A>(
+		self, pool: &P, token: &CancellationToken, sink: DistSink,
+	) -> Result<A, Cancelled>
+	where
+		P: ProcessPool,
+		DistSink: DistributedSink<Self::Item, Done = A>,
+		<DistSink::Pipe as DistributedPipe<Self::Item>>::Task: 'static,
+		DistSink::ReduceA: 'static,
+		DistSink::ReduceB: 'static,
+		Self::Task: 'static,
+		Self: Sized,
+	{
+		let stream = Cancellable::new(self, token.clone());
+		cancel::run(token, stream.pipe(pool, sink)).await
+	}
+
+	/// As `pipe`, but keeping within `budget` in each process, reserving `task_memory` bytes for
+	/// each task while it runs, and returning [`MemoryExceeded`] if the job exceeds it regardless.
+	async fn memory_bounded<P, DistSink, A>(
+		self, pool: &P, budget: &MemoryBudget, task_memory: usize, sink: DistSink,
+	) -> Result<A, MemoryExceeded>
+	where
+		P: ProcessPool,
+		DistSink: DistributedSink<Self::Item, Done = A>,
+		<DistSink::Pipe as DistributedPipe<Self::Item>>::Task: 'static,
+		DistSink::ReduceA: 'static,
+		DistSink::ReduceB: 'static,
+		Self::Task: 'static,
+		Self: Sized,
+	{
+		budget.start();
+		let output = MemoryBounded::new(self, budget.clone(), task_memory)
+			.pipe(pool, sink)
+			.await;
+		budget.result(output)
+	}
+
 	// These messy bounds are unfortunately necessary as requiring 'static in DistributedSink breaks sink_b being e.g. Identity.count()
 	async fn fork<P, DistSinkA, DistSinkB, A, B>(
 		self, pool: &P, sink_a: DistSinkA, sink_b: DistSinkB,
@@ -809,4 +1072,50 @@ stream!(DistributedStream DistributedPipe DistributedSink FromDistributedStream
 		self.pipe(pool, DistributedPipe::<Self::Item>::collect(Identity))
 			.await
 	}
+
+	/// The first `n` items, in the partition ordering of the stream. Use `take(pool, 1)` for just
+	/// the first.
+	async fn take<P>(self, pool: &P, n: usize) -> Vec<Self::Item>
+	where
+		P: ProcessPool,
+		Self::Item: ProcessSend + 'static,
+		Self::Task: 'static,
+		Self: Sized,
+	{
+		let progress = Progress::new(n);
+		Take::new(self, n, progress.clone())
+			.reduce(
+				pool,
+				TakeReducer::new(n),
+				TakeMergeReducer::new(n),
+				TakeFinalReducer::new(n, progress),
+			)
+			.await
+	}
+
+	/// Redistributes the items into new tasks of `size` items each, so that work after this stage
+	/// is spread evenly over `pool` however unevenly the items were spread between the source's
+	/// partitions. The stream up to this point is run on `pool`, with its items sent back to this
+	/// process a few tasks' worth at a time, and sent on to the processes in the new tasks.
+	fn repartition<P>(self, pool: &P, size: usize) -> Repartition<Self, P, Self::Item>
+	where
+		P: ProcessPool,
+		Self::Item: ProcessSend + 'static,
+		Self::Task: 'static,
+		Self: Sized,
+	{
+		Repartition::new(self, pool.clone(), pool.processes(), Some(size))
+	}
+
+	/// As `repartition`, but splitting the items of each task evenly between the processes of
+	/// `pool`.
+	fn rebalance<P>(self, pool: &P) -> Repartition<Self, P, Self::Item>
+	where
+		P: ProcessPool,
+		Self::Item: ProcessSend + 'static,
+		Self::Task: 'static,
+		Self: Sized,
+	{
+		Repartition::new(self, pool.clone(), pool.processes(), None)
+	}
 });
@@ -0,0 +1,188 @@
+use educe::Educe;
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
+use std::{
+	marker::PhantomData, pin::Pin, task::{Context, Poll}
+};
+
+use super::{ParallelPipe, ParallelStream, PipeTask, StreamTask};
+
+/// Groups items into batches of up to `size` items, such as `Vec<T>` or `List<T>`. Created by
+/// `batch` and `chunks`.
+///
+/// Batches don't span tasks, so the final batch of each task may be smaller than `size`.
+#[pin_project]
+#[must_use]
+pub struct Batch<P, B> {
+	#[pin]
+	pipe: P,
+	size: usize,
+	marker: PhantomData<fn() -> B>,
+}
+impl<P, B> Batch<P, B> {
+	pub(crate) fn new(pipe: P, size: usize) -> Self {
+		assert!(size > 0, "batch size must be at least one");
+		Self {
+			pipe,
+			size,
+			marker: PhantomData,
+		}
+	}
+}
+
+impl_par_dist! {
+	impl<P: ParallelStream, B> ParallelStream for Batch<P, B>
+	where
+		B: Default + Extend<P::Item> + 'static,
+	{
+		type Item = B;
+		type Task = BatchTask<P::Task, B>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			self.pipe.size_hint()
+		}
+		fn next_task(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let self_ = self.project();
+			let size = *self_.size;
+			self_
+				.pipe
+				.next_task(cx)
+				.map(|task| task.map(|task| BatchTask::new(task, size)))
+		}
+	}
+
+	impl<P: ParallelPipe<Input>, B, Input> ParallelPipe<Input> for Batch<P, B>
+	where
+		B: Default + Extend<P::Output> + 'static,
+	{
+		type Output = B;
+		type Task = BatchTask<P::Task, B>;
+
+		fn task(&self) -> Self::Task {
+			BatchTask::new(self.pipe.task(), self.size)
+		}
+	}
+}
+
+#[derive(Educe, Serialize, Deserialize)]
+#[educe(Clone(bound = "C: Clone"))]
+#[serde(
+	bound(serialize = "C: Serialize"),
+	bound(deserialize = "C: Deserialize<'de>")
+)]
+pub struct BatchTask<C, B> {
+	task: C,
+	size: usize,
+	marker: PhantomData<fn() -> B>,
+}
+impl<C, B> BatchTask<C, B> {
+	fn new(task: C, size: usize) -> Self {
+		Self {
+			task,
+			size,
+			marker: PhantomData,
+		}
+	}
+}
+impl<C: StreamTask, B> StreamTask for BatchTask<C, B>
+where
+	B: Default + Extend<C::Item>,
+{
+	type Item = B;
+	type Async = crate::pipe::Batch<C::Async, C::Item, B>;
+
+	fn partition(&self) -> Option<String> {
+		self.task.partition()
+	}
+	fn into_async(self) -> Self::Async {
+		crate::pipe::Batch::new(self.task.into_async(), self.size)
+	}
+}
+impl<C: PipeTask<Input>, B, Input> PipeTask<Input> for BatchTask<C, B>
+where
+	B: Default + Extend<C::Output>,
+{
+	type Output = B;
+	type Async = crate::pipe::Batch<C::Async, C::Output, B>;
+
+	fn into_async(self) -> Self::Async {
+		crate::pipe::Batch::new(self.task.into_async(), self.size)
+	}
+}
+
+/// Flattens batches back into their items. Created by `unbatch`.
+#[pin_project]
+#[must_use]
+pub struct Unbatch<P> {
+	#[pin]
+	pipe: P,
+}
+impl<P> Unbatch<P> {
+	pub(crate) fn new(pipe: P) -> Self {
+		Self { pipe }
+	}
+}
+
+impl_par_dist! {
+	impl<P: ParallelStream> ParallelStream for Unbatch<P>
+	where
+		P::Item: IntoIterator,
+	{
+		type Item = <P::Item as IntoIterator>::Item;
+		type Task = UnbatchTask<P::Task>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			self.pipe.size_hint()
+		}
+		fn next_task(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let self_ = self.project();
+			self_
+				.pipe
+				.next_task(cx)
+				.map(|task| task.map(|task| UnbatchTask { task }))
+		}
+	}
+
+	impl<P: ParallelPipe<Input>, Input> ParallelPipe<Input> for Unbatch<P>
+	where
+		P::Output: IntoIterator,
+	{
+		type Output = <P::Output as IntoIterator>::Item;
+		type Task = UnbatchTask<P::Task>;
+
+		fn task(&self) -> Self::Task {
+			let task = self.pipe.task();
+			UnbatchTask { task }
+		}
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UnbatchTask<C> {
+	task: C,
+}
+impl<C: StreamTask> StreamTask for UnbatchTask<C>
+where
+	C::Item: IntoIterator,
+{
+	type Item = <C::Item as IntoIterator>::Item;
+	type Async = crate::pipe::Unbatch<C::Async, <C::Item as IntoIterator>::IntoIter>;
+
+	fn partition(&self) -> Option<String> {
+		self.task.partition()
+	}
+	fn into_async(self) -> Self::Async {
+		crate::pipe::Unbatch::new(self.task.into_async())
+	}
+}
+impl<C: PipeTask<Input>, Input> PipeTask<Input> for UnbatchTask<C>
+where
+	C::Output: IntoIterator,
+{
+	type Output = <C::Output as IntoIterator>::Item;
+	type Async = crate::pipe::Unbatch<C::Async, <C::Output as IntoIterator>::IntoIter>;
+
+	fn into_async(self) -> Self::Async {
+		crate::pipe::Unbatch::new(self.task.into_async())
+	}
+}
@@ -0,0 +1,282 @@
+use futures::{future, pin_mut, Stream};
+use futures_timer::Delay;
+use once_cell::sync::Lazy;
+use pin_project::{pin_project, pinned_drop};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+	collections::HashMap, error::Error, fmt, future::Future, mem, pin::Pin, sync::{
+		atomic::{AtomicBool, Ordering}, Arc, Mutex, Weak
+	}, task::{Context, Poll, Waker}, time::{Duration, Instant}
+};
+
+use super::{ParallelStream, StreamTask};
+
+/// The [`CancellationToken`]s in this process, by id.
+static TOKENS: Lazy<Mutex<HashMap<u64, Weak<CancellationTokenInner>>>> =
+	Lazy::new(Default::default);
+
+/// A handle for cooperatively cancelling running jobs.
+///
+/// Pass it to a stream's `cancellable` terminal. Once cancelled, no more tasks are scheduled,
+/// tasks running in this process stop at their next item, and the terminal returns a
+/// [`Cancelled`] error rather than a partial result.
+///
+/// Tasks sent to another process share a copy of the token there, which carries its deadline but
+/// can't see a later `cancel`: they're left to run, and their results are discarded when they
+/// arrive.
+#[derive(Clone, Debug)]
+pub struct CancellationToken(Arc<CancellationTokenInner>);
+
+#[derive(Debug)]
+struct CancellationTokenInner {
+	id: u64,
+	cancelled: AtomicBool,
+	deadline: Option<Instant>,
+	waiting: Mutex<Waiting>,
+}
+
+/// The terminals waiting for cancellation, by id.
+#[derive(Default, Debug)]
+struct Waiting {
+	next: usize,
+	wakers: HashMap<usize, Waker>,
+}
+
+impl CancellationToken {
+	pub fn new() -> Self {
+		Self::with_id(rand::random(), false, None)
+	}
+
+	/// A token that cancels itself once `timeout` has elapsed.
+	pub fn with_timeout(timeout: Duration) -> Self {
+		Self::with_id(rand::random(), false, Some(Instant::now() + timeout))
+	}
+
+	fn with_id(id: u64, cancelled: bool, deadline: Option<Instant>) -> Self {
+		let inner = Arc::new(CancellationTokenInner {
+			id,
+			cancelled: AtomicBool::new(cancelled),
+			deadline,
+			waiting: Mutex::default(),
+		});
+		let mut registry = TOKENS.lock().unwrap();
+		registry.retain(|_, token| token.strong_count() > 0);
+		let _ = registry.insert(id, Arc::downgrade(&inner));
+		Self(inner)
+	}
+
+	pub fn cancel(&self) {
+		self.0.cancelled.store(true, Ordering::SeqCst);
+		let wakers = mem::take(&mut self.0.waiting.lock().unwrap().wakers);
+		for (_, waker) in wakers {
+			waker.wake();
+		}
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.0.cancelled.load(Ordering::SeqCst)
+			|| self
+				.0
+				.deadline
+				.map_or(false, |deadline| Instant::now() >= deadline)
+	}
+
+	/// Resolves once this token is cancelled.
+	fn cancelled(&self) -> WaitCancelled {
+		WaitCancelled {
+			token: self.clone(),
+			id: None,
+			deadline: self
+				.0
+				.deadline
+				.map(|deadline| Delay::new(deadline.saturating_duration_since(Instant::now()))),
+		}
+	}
+}
+
+impl Default for CancellationToken {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+// Tokens are sent to other processes by id, along with whether they're cancelled and the time left
+// until their deadline. The tasks of a job in each process share a copy of the token.
+impl Serialize for CancellationToken {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		let timeout = self
+			.0
+			.deadline
+			.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+		(self.0.id, self.0.cancelled.load(Ordering::SeqCst), timeout).serialize(serializer)
+	}
+}
+impl<'de> Deserialize<'de> for CancellationToken {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let (id, cancelled, timeout) = <(u64, bool, Option<Duration>)>::deserialize(deserializer)?;
+		let token = TOKENS.lock().unwrap().get(&id).and_then(Weak::upgrade);
+		Ok(match token {
+			Some(token) => {
+				let token = Self(token);
+				if cancelled {
+					token.cancel();
+				}
+				token
+			}
+			None => Self::with_id(
+				id,
+				cancelled,
+				timeout.map(|timeout| Instant::now() + timeout),
+			),
+		})
+	}
+}
+
+#[pin_project(PinnedDrop)]
+struct WaitCancelled {
+	token: CancellationToken,
+	id: Option<usize>,
+	#[pin]
+	deadline: Option<Delay>,
+}
+impl Future for WaitCancelled {
+	type Output = ();
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+		let mut self_ = self.project();
+		if let Some(deadline) = self_.deadline.as_mut().as_pin_mut() {
+			if deadline.poll(cx).is_ready() {
+				return Poll::Ready(());
+			}
+		}
+		let mut waiting = self_.token.0.waiting.lock().unwrap();
+		// Checked with the lock held so that a `cancel` can't slip by between checking and waiting.
+		if self_.token.is_cancelled() {
+			return Poll::Ready(());
+		}
+		let id = *self_.id.get_or_insert_with(|| {
+			waiting.next += 1;
+			waiting.next
+		});
+		let _ = waiting.wakers.insert(id, cx.waker().clone());
+		Poll::Pending
+	}
+}
+#[pinned_drop]
+impl PinnedDrop for WaitCancelled {
+	fn drop(self: Pin<&mut Self>) {
+		if let Some(id) = self.id {
+			let _ = self.token.0.waiting.lock().unwrap().wakers.remove(&id);
+		}
+	}
+}
+
+/// Await `job`, the reduction of a [`Cancellable`] stream, returning [`Cancelled`] as soon as
+/// `token` is cancelled, or if `job` was cut short by it. Returning drops `job`, and with it the
+/// work it has in flight.
+pub(crate) async fn run<F: Future>(
+	token: &CancellationToken, job: F,
+) -> Result<F::Output, Cancelled> {
+	let cancelled = token.cancelled();
+	pin_mut!(job);
+	pin_mut!(cancelled);
+	match future::select(job, cancelled).await {
+		// Tasks are only cut short once the token is cancelled, so checking it catches them.
+		future::Either::Left((output, _)) if !token.is_cancelled() => Ok(output),
+		_ => Err(Cancelled),
+	}
+}
+
+/// The error returned by the `cancellable` terminal for a cancelled job.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Cancelled;
+impl fmt::Display for Cancelled {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("job cancelled")
+	}
+}
+impl Error for Cancelled {}
+
+/// Stops a stream when its [`CancellationToken`] is cancelled. Used by `cancellable`.
+#[pin_project]
+pub(crate) struct Cancellable<P> {
+	#[pin]
+	pipe: P,
+	token: CancellationToken,
+}
+impl<P> Cancellable<P> {
+	pub(crate) fn new(pipe: P, token: CancellationToken) -> Self {
+		Self { pipe, token }
+	}
+}
+
+impl_par_dist! {
+	impl<P: ParallelStream> ParallelStream for Cancellable<P> {
+		type Item = P::Item;
+		type Task = CancellableTask<P::Task>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			self.pipe.size_hint()
+		}
+		fn next_task(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let self_ = self.project();
+			if self_.token.is_cancelled() {
+				return Poll::Ready(None);
+			}
+			let token = self_.token;
+			self_.pipe.next_task(cx).map(|task| {
+				task.map(|task| CancellableTask {
+					task,
+					token: token.clone(),
+				})
+			})
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct CancellableTask<C> {
+	task: C,
+	token: CancellationToken,
+}
+impl<C: StreamTask> StreamTask for CancellableTask<C> {
+	type Item = C::Item;
+	type Async = CancellableTaskAsync<C::Async>;
+
+	fn partition(&self) -> Option<String> {
+		self.task.partition()
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.task.size_hint()
+	}
+	fn into_async(self) -> Self::Async {
+		CancellableTaskAsync {
+			task: self.task.into_async(),
+			token: self.token,
+		}
+	}
+}
+
+#[pin_project]
+pub(crate) struct CancellableTaskAsync<T> {
+	#[pin]
+	task: T,
+	token: CancellationToken,
+}
+impl<T: Stream> Stream for CancellableTaskAsync<T> {
+	type Item = T::Item;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let self_ = self.project();
+		if self_.token.is_cancelled() {
+			return Poll::Ready(None);
+		}
+		self_.task.poll_next(cx)
+	}
+}
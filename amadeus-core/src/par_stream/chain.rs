@@ -56,6 +56,18 @@ impl<A: StreamTask, B: StreamTask<Item = A::Item>> StreamTask for ChainTask<A, B
 	type Item = A::Item;
 	type Async = ChainTask<A::Async, B::Async>;
 
+	fn partition(&self) -> Option<String> {
+		match self {
+			ChainTask::A(a) => a.partition(),
+			ChainTask::B(b) => b.partition(),
+		}
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		match self {
+			ChainTask::A(a) => a.size_hint(),
+			ChainTask::B(b) => b.size_hint(),
+		}
+	}
 	fn into_async(self) -> Self::Async {
 		match self {
 			ChainTask::A(a) => ChainTask::A(a.into_async()),
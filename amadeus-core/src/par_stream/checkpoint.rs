@@ -0,0 +1,144 @@
+use futures::{
+	pin_mut, stream::{self, StreamExt as _}
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	collections::HashSet, ffi::OsStr, fs, io::{self, Write}, path::{Path, PathBuf}
+};
+use tracing::{info_span, Instrument};
+
+use super::{spawn_batches, DistributedStream};
+use crate::{
+	par_sink::{Reducer, ReducerProcessSend, ReducerSend}, pipe::StreamExt, pool::{ProcessPool, ProcessSend}
+};
+
+/// A directory in which the partial results of a job are saved as it runs, so that it can be
+/// resumed if it's interrupted. Used with `checkpointed`.
+///
+/// Tasks are identified by the order the stream yields them in, so the stream must yield the same
+/// tasks in the same order each time it's run – as file-based sources do if the files are
+/// unchanged. The job is identified by `job_id` alone, so the id must change whenever the stream
+/// or reducers do, or saved results that don't belong together will be merged.
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+	dir: PathBuf,
+	job_id: String,
+}
+impl Checkpoint {
+	/// Save and load checkpoints for the job `job_id` in the directory `dir`, which is created if
+	/// it doesn't exist.
+	pub fn new(dir: impl AsRef<Path>, job_id: &str) -> io::Result<Self> {
+		let dir = dir.as_ref().join(job_id);
+		fs::create_dir_all(&dir)?;
+		Ok(Self {
+			dir,
+			job_id: job_id.to_owned(),
+		})
+	}
+
+	/// Delete the saved partial results, so the next run starts from scratch.
+	pub fn clear(&self) -> io::Result<()> {
+		fs::remove_dir_all(&self.dir)?;
+		fs::create_dir_all(&self.dir)
+	}
+
+	async fn load<T: DeserializeOwned>(&self) -> io::Result<Vec<(Vec<usize>, T)>> {
+		let dir = self.dir.clone();
+		let files = blocking(move || {
+			let mut files = Vec::new();
+			for entry in fs::read_dir(&dir)? {
+				let path = entry?.path();
+				if path.extension() == Some(OsStr::new("bin")) {
+					let file = fs::read(&path)?;
+					files.push((path, file));
+				}
+			}
+			Ok::<_, io::Error>(files)
+		})
+		.await?;
+		files
+			.into_iter()
+			.map(|(path, file)| {
+				bincode::deserialize(&file).map_err(|err| {
+					io::Error::new(
+						io::ErrorKind::InvalidData,
+						format!("couldn't load checkpoint {}: {}", path.display(), err),
+					)
+				})
+			})
+			.collect()
+	}
+
+	/// Saves the state of a batch of tasks, writing and syncing a temporary file first so an
+	/// interrupted save isn't mistaken for a finished batch.
+	async fn save<T: Serialize>(&self, tasks: &[usize], state: &T) -> io::Result<()> {
+		let path = self.dir.join(format!("{}.bin", tasks[0]));
+		let file = bincode::serialize(&(tasks, state)).unwrap();
+		blocking(move || {
+			let tmp = path.with_extension("tmp");
+			let mut tmp_file = fs::File::create(&tmp)?;
+			tmp_file.write_all(&file)?;
+			tmp_file.sync_all()?;
+			fs::rename(tmp, &path)?;
+			// the rename is only durable once the directory is synced
+			#[cfg(unix)]
+			fs::File::open(path.parent().unwrap())?.sync_all()?;
+			Ok(())
+		})
+		.await
+	}
+}
+
+/// Runs `f`, which blocks on the filesystem, without blocking the executor.
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+	#[cfg(not(target_arch = "wasm32"))]
+	return tokio::task::spawn_blocking(f).await.unwrap();
+	#[cfg(target_arch = "wasm32")]
+	f()
+}
+
+/// As [`DistributedStream::reduce`], but skipping tasks whose results were saved by an earlier
+/// run, and saving the results of each batch of tasks as it finishes.
+pub(crate) async fn reduce<S, P, B, R1, R2, R3>(
+	stream: S, pool: &P, checkpoint: &Checkpoint, reduce_a: R1, reduce_b: R2, reduce_c: R3,
+) -> B
+where
+	S: DistributedStream,
+	P: ProcessPool,
+	R1: ReducerSend<S::Item> + Clone + ProcessSend + 'static,
+	R2: ReducerProcessSend<<R1 as ReducerSend<S::Item>>::Done> + Clone + ProcessSend + 'static,
+	R3: Reducer<<R2 as ReducerProcessSend<<R1 as ReducerSend<S::Item>>::Done>>::Done, Done = B>,
+	S::Task: 'static,
+{
+	let processes = pool.processes();
+	let job = info_span!("job", processes, checkpoint = %checkpoint.job_id);
+	let saved = checkpoint
+		.load::<<R2 as ReducerProcessSend<_>>::Done>()
+		.await
+		.unwrap_or_else(|err| panic!("Amadeus: {}", err));
+	let skip = saved
+		.iter()
+		.flat_map(|(tasks, _)| tasks.iter().copied())
+		.collect::<HashSet<_>>();
+	let saved = stream::iter(saved.into_iter().map(|(_, state)| state));
+
+	let stream_ = stream;
+	pin_mut!(stream_);
+	let stream = spawn_batches(stream_, pool, reduce_a, reduce_b, &job, |index| {
+		!skip.contains(&index)
+	})
+	.then(|(indices, state)| async move {
+		checkpoint
+			.save(&indices, &state)
+			.await
+			.unwrap_or_else(|err| panic!("Amadeus: couldn't save checkpoint: {}", err));
+		state
+	});
+	let reduce_c = reduce_c.into_async();
+	pin_mut!(reduce_c);
+	saved
+		.chain(stream)
+		.sink(reduce_c)
+		.instrument(job.clone())
+		.await
+}
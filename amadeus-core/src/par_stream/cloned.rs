@@ -33,7 +33,7 @@ impl_par_dist! {
 }
 
 #[pin_project]
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ClonedTask<T> {
 	#[pin]
 	task: T,
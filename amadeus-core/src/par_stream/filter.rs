@@ -55,7 +55,7 @@ impl_par_dist! {
 	}
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct FilterTask<C, F> {
 	task: C,
 	f: F,
@@ -68,6 +68,12 @@ where
 	type Item = C::Item;
 	type Async = crate::pipe::Filter<C::Async, F>;
 
+	fn partition(&self) -> Option<String> {
+		self.task.partition()
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(0, self.task.size_hint().1)
+	}
 	fn into_async(self) -> Self::Async {
 		crate::pipe::Filter::new(self.task.into_async(), self.f)
 	}
@@ -56,7 +56,7 @@ impl_par_dist! {
 }
 
 #[pin_project]
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct FilterMapSyncTask<C, F> {
 	#[pin]
 	task: C,
@@ -70,6 +70,12 @@ where
 	type Item = R;
 	type Async = crate::pipe::FilterMapSync<C::Async, F>;
 
+	fn partition(&self) -> Option<String> {
+		self.task.partition()
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(0, self.task.size_hint().1)
+	}
 	fn into_async(self) -> Self::Async {
 		crate::pipe::FilterMapSync::new(self.task.into_async(), self.f)
 	}
@@ -56,7 +56,7 @@ impl_par_dist! {
 	}
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct FlatMapTask<C, F> {
 	task: C,
 	f: F,
@@ -67,6 +67,9 @@ impl<C: StreamTask, F: FnMut<(C::Item,), Output = R> + Clone, R: Stream> StreamT
 	type Item = R::Item;
 	type Async = crate::pipe::FlatMap<C::Async, F, R>;
 
+	fn partition(&self) -> Option<String> {
+		self.task.partition()
+	}
 	fn into_async(self) -> Self::Async {
 		crate::pipe::FlatMap::new(self.task.into_async(), self.f)
 	}
@@ -55,7 +55,7 @@ impl_par_dist! {
 	}
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct FlatMapSyncTask<C, F> {
 	task: C,
 	f: F,
@@ -6,7 +6,7 @@ use std::{
 };
 
 use super::{
-	All, Any, Collect, Combine, Count, Filter, FlatMap, Fold, ForEach, Fork, GroupBy, Histogram, Inspect, Map, Max, MaxBy, MaxByKey, Mean, Min, MinBy, MinByKey, MostDistinct, MostFrequent, ParallelPipe, Pipe, PipeTask, SampleUnstable, StdDev, Sum, Update
+	All, Any, Batch, Collect, Combine, Count, Filter, FlatMap, Fold, ForEach, Fork, GroupBy, Histogram, Inspect, Map, Max, MaxBy, MaxByKey, Mean, Min, MinBy, MinByKey, MostDistinct, MostFrequent, ParallelPipe, Pipe, PipeTask, SampleUnstable, StdDev, Sum, Unbatch, Update
 };
 
 // TODO: add type parameter to Identity when type the type system includes HRTB in the ParallelPipe impl https://github.com/dtolnay/ghost/
@@ -83,6 +83,21 @@ mod workaround {
 			Filter::new(self, f)
 		}
 
+		#[inline]
+		pub fn batch<B>(self, size: usize) -> Batch<Self, B> {
+			Batch::new(self, size)
+		}
+
+		#[inline]
+		pub fn chunks<T>(self, size: usize) -> Batch<Self, Vec<T>> {
+			Batch::new(self, size)
+		}
+
+		#[inline]
+		pub fn unbatch(self) -> Unbatch<Self> {
+			Unbatch::new(self)
+		}
+
 		// #[must_use]
 		// #[inline]
 		// pub fn chain<C>(self, chain: C) -> Chain<Self, C::Iter>
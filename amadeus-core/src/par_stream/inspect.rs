@@ -58,7 +58,7 @@ impl_par_dist! {
 }
 
 #[pin_project]
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct InspectTask<T, F> {
 	#[pin]
 	task: T,
@@ -72,6 +72,12 @@ where
 	type Item = C::Item;
 	type Async = InspectTask<C::Async, F>;
 
+	fn partition(&self) -> Option<String> {
+		self.task.partition()
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.task.size_hint()
+	}
 	fn into_async(self) -> Self::Async {
 		InspectTask {
 			task: self.task.into_async(),
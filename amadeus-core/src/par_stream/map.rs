@@ -56,7 +56,7 @@ impl_par_dist! {
 }
 
 #[pin_project]
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct MapTask<C, F> {
 	#[pin]
 	task: C,
@@ -70,6 +70,12 @@ where
 	type Item = R;
 	type Async = crate::pipe::Map<C::Async, F>;
 
+	fn partition(&self) -> Option<String> {
+		self.task.partition()
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.task.size_hint()
+	}
 	fn into_async(self) -> Self::Async {
 		crate::pipe::Map::new(self.task.into_async(), self.f)
 	}
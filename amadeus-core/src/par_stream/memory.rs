@@ -0,0 +1,391 @@
+use futures::{ready, Stream};
+use once_cell::sync::Lazy;
+use pin_project::{pin_project, pinned_drop};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+	collections::HashMap, error::Error, fmt, mem, pin::Pin, sync::{
+		atomic::{AtomicBool, AtomicUsize, Ordering}, Arc, Mutex, Weak
+	}, task::{Context, Poll, Waker}, time::{Duration, Instant}
+};
+
+use super::{ParallelStream, StreamTask};
+
+/// How often a budget's resident memory is measured at most, as measuring it means reading and
+/// parsing a file.
+const MEASURE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The [`MemoryBudget`]s in this process, by id.
+static BUDGETS: Lazy<Mutex<HashMap<u64, Weak<MemoryBudgetInner>>>> = Lazy::new(Default::default);
+/// The ids of budgets from another process that were exceeded in this one, to be sent back.
+static EXCEEDED: Lazy<Mutex<Vec<u64>>> = Lazy::new(Default::default);
+
+/// A memory budget for a job, shared between its tasks.
+///
+/// Pass it to a stream's `memory_bounded` terminal. The budget is of the growth in each process's
+/// resident memory from when the job started there, which covers buffered pages and reducer state
+/// alike. Each task reserves an estimate of its working memory before it starts, waiting while
+/// that would exceed the budget. If the budget is exceeded regardless, the job winds down and the
+/// terminal returns [`MemoryExceeded`] rather than risking the process being killed.
+///
+/// Resident memory is measured on Linux; elsewhere only reservations are counted. As it's the
+/// memory of the whole process, anything else running in it is counted too. A budget is for a
+/// single job.
+#[derive(Clone, Debug)]
+pub struct MemoryBudget(Arc<MemoryBudgetInner>);
+
+#[derive(Debug)]
+struct MemoryBudgetInner {
+	id: u64,
+	limit: usize,
+	/// Whether this is a copy of a budget from another process, which is told if it's exceeded.
+	remote: bool,
+	/// The growth in resident memory since the job started in this process, as last measured.
+	growth: AtomicUsize,
+	measured: Mutex<Measured>,
+	/// The bytes reserved by running tasks, and the number of them.
+	reserved: AtomicUsize,
+	reservations: AtomicUsize,
+	exceeded: AtomicBool,
+	waiting: Mutex<Vec<Waker>>,
+}
+
+#[derive(Debug)]
+struct Measured {
+	/// The resident memory of this process when the job started here.
+	baseline: Option<usize>,
+	at: Instant,
+}
+impl Measured {
+	fn start() -> Self {
+		Self {
+			baseline: resident_memory(),
+			at: Instant::now(),
+		}
+	}
+}
+
+impl MemoryBudget {
+	/// A budget of `limit` bytes.
+	pub fn new(limit: usize) -> Self {
+		Self::with_id(rand::random(), limit, false)
+	}
+
+	fn with_id(id: u64, limit: usize, remote: bool) -> Self {
+		let inner = Arc::new(MemoryBudgetInner {
+			id,
+			limit,
+			remote,
+			growth: AtomicUsize::new(0),
+			measured: Mutex::new(Measured::start()),
+			reserved: AtomicUsize::new(0),
+			reservations: AtomicUsize::new(0),
+			exceeded: AtomicBool::new(false),
+			waiting: Mutex::new(Vec::new()),
+		});
+		let mut registry = BUDGETS.lock().unwrap();
+		registry.retain(|_, budget| budget.strong_count() > 0);
+		let _ = registry.insert(id, Arc::downgrade(&inner));
+		Self(inner)
+	}
+
+	pub fn limit(&self) -> usize {
+		self.0.limit
+	}
+
+	/// The bytes currently reserved by running tasks, plus the growth in this process's resident
+	/// memory as last measured.
+	pub fn used(&self) -> usize {
+		self.0.reserved.load(Ordering::SeqCst) + self.0.growth.load(Ordering::SeqCst)
+	}
+
+	pub fn is_exceeded(&self) -> bool {
+		self.0.exceeded.load(Ordering::SeqCst)
+	}
+
+	/// Take the resident memory of this process as the baseline for the job about to start. Copies
+	/// of the budget in other processes take theirs when they're made, as the job starts there.
+	pub(crate) fn start(&self) {
+		*self.0.measured.lock().unwrap() = Measured::start();
+		self.0.growth.store(0, Ordering::SeqCst);
+	}
+
+	/// `output` if the job it's the result of kept within this budget.
+	pub(crate) fn result<T>(&self, output: T) -> Result<T, MemoryExceeded> {
+		self.measure(true);
+		if self.is_exceeded() {
+			Err(MemoryExceeded {
+				limit: self.0.limit,
+			})
+		} else {
+			Ok(output)
+		}
+	}
+
+	/// Measure the growth in resident memory, if it hasn't been lately or `now` is set, marking
+	/// the budget exceeded if it's over.
+	fn measure(&self, now: bool) {
+		// Whoever holds the lock is measuring, so there's no need to wait for them.
+		let mut measured = match self.0.measured.try_lock() {
+			Ok(measured) => measured,
+			Err(_) => return,
+		};
+		let baseline = match measured.baseline {
+			Some(baseline) => baseline,
+			None => return,
+		};
+		if !now && measured.at.elapsed() < MEASURE_INTERVAL {
+			return;
+		}
+		measured.at = Instant::now();
+		if let Some(resident) = resident_memory() {
+			let growth = resident.saturating_sub(baseline);
+			self.0.growth.store(growth, Ordering::SeqCst);
+			if growth > self.0.limit {
+				self.exceed();
+			}
+		}
+	}
+
+	fn try_reserve(&self, bytes: usize) -> bool {
+		let (limit, growth) = (self.0.limit, self.0.growth.load(Ordering::SeqCst));
+		let reserved = self
+			.0
+			.reserved
+			.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |reserved| {
+				reserved
+					.checked_add(bytes)
+					.filter(|&reserved| reserved.saturating_add(growth) <= limit)
+			})
+			.is_ok();
+		if reserved {
+			let _ = self.0.reservations.fetch_add(1, Ordering::SeqCst);
+		}
+		reserved
+	}
+
+	/// Reserve `bytes` for a task, resolving to false if the budget has been exceeded.
+	fn poll_reserve(&self, bytes: usize, cx: &mut Context) -> Poll<bool> {
+		self.measure(false);
+		if self.is_exceeded() {
+			return Poll::Ready(false);
+		}
+		if self.try_reserve(bytes) {
+			return Poll::Ready(true);
+		}
+		let mut waiting = self.0.waiting.lock().unwrap();
+		// Check again now that a release can't slip by between checking and waiting.
+		if self.try_reserve(bytes) {
+			Poll::Ready(true)
+		} else if self.0.reservations.load(Ordering::SeqCst) == 0 {
+			// Nothing is going to be released, so waiting would never end.
+			drop(waiting);
+			self.exceed();
+			Poll::Ready(false)
+		} else {
+			waiting.push(cx.waker().clone());
+			Poll::Pending
+		}
+	}
+
+	fn release(&self, bytes: usize) {
+		let _ = self.0.reserved.fetch_sub(bytes, Ordering::SeqCst);
+		let _ = self.0.reservations.fetch_sub(1, Ordering::SeqCst);
+		self.wake();
+	}
+
+	fn exceed(&self) {
+		if !self.0.exceeded.swap(true, Ordering::SeqCst) && self.0.remote {
+			EXCEEDED.lock().unwrap().push(self.0.id);
+		}
+		self.wake();
+	}
+
+	fn wake(&self) {
+		let waiting = mem::take(&mut *self.0.waiting.lock().unwrap());
+		for waker in waiting {
+			waker.wake();
+		}
+	}
+}
+
+// Budgets are sent to other processes by id, where the tasks of a job share a copy measuring that
+// process's memory.
+impl Serialize for MemoryBudget {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		(self.0.id, self.0.limit).serialize(serializer)
+	}
+}
+impl<'de> Deserialize<'de> for MemoryBudget {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let (id, limit) = <(u64, usize)>::deserialize(deserializer)?;
+		let budget = BUDGETS.lock().unwrap().get(&id).and_then(Weak::upgrade);
+		Ok(budget.map_or_else(|| Self::with_id(id, limit, true), Self))
+	}
+}
+
+/// Take the ids of the budgets from another process that were exceeded in this one, to be sent
+/// back along with the results of a batch of tasks.
+pub(crate) fn take_remote() -> Vec<u64> {
+	mem::take(&mut *EXCEEDED.lock().unwrap())
+}
+
+/// Mark the budgets that were exceeded in another process as exceeded.
+pub(crate) fn add_remote(exceeded: Vec<u64>) {
+	for id in exceeded {
+		let budget = BUDGETS.lock().unwrap().get(&id).and_then(Weak::upgrade);
+		if let Some(budget) = budget {
+			MemoryBudget(budget).exceed();
+		}
+	}
+}
+
+/// The resident memory of this process, if it can be measured here.
+#[cfg(target_os = "linux")]
+fn resident_memory() -> Option<usize> {
+	let status = std::fs::read_to_string("/proc/self/status").ok()?;
+	let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+	let kb = line.split_whitespace().nth(1)?.parse::<usize>().ok()?;
+	Some(kb * 1024)
+}
+#[cfg(not(target_os = "linux"))]
+fn resident_memory() -> Option<usize> {
+	None
+}
+
+/// The error returned by the `memory_bounded` terminal for a job that exceeded its budget.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct MemoryExceeded {
+	pub limit: usize,
+}
+impl fmt::Display for MemoryExceeded {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "job exceeded its memory budget of {} bytes", self.limit)
+	}
+}
+impl Error for MemoryExceeded {}
+
+/// Bounds the memory of a stream by a [`MemoryBudget`]. Used by `memory_bounded`.
+#[pin_project]
+pub(crate) struct MemoryBounded<P> {
+	#[pin]
+	pipe: P,
+	budget: MemoryBudget,
+	task_memory: usize,
+}
+impl<P> MemoryBounded<P> {
+	pub(crate) fn new(pipe: P, budget: MemoryBudget, task_memory: usize) -> Self {
+		Self {
+			pipe,
+			budget,
+			task_memory,
+		}
+	}
+}
+
+impl_par_dist! {
+	impl<P: ParallelStream> ParallelStream for MemoryBounded<P> {
+		type Item = P::Item;
+		type Task = MemoryBoundedTask<P::Task>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			self.pipe.size_hint()
+		}
+		fn next_task(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let self_ = self.project();
+			self_.budget.measure(false);
+			if self_.budget.is_exceeded() {
+				return Poll::Ready(None);
+			}
+			let (budget, task_memory) = (self_.budget, *self_.task_memory);
+			self_.pipe.next_task(cx).map(|task| {
+				task.map(|task| MemoryBoundedTask {
+					task,
+					budget: budget.clone(),
+					task_memory,
+				})
+			})
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MemoryBoundedTask<C> {
+	task: C,
+	budget: MemoryBudget,
+	task_memory: usize,
+}
+impl<C: StreamTask> StreamTask for MemoryBoundedTask<C> {
+	type Item = C::Item;
+	type Async = MemoryBoundedTaskAsync<C::Async>;
+
+	fn partition(&self) -> Option<String> {
+		self.task.partition()
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.task.size_hint()
+	}
+	fn into_async(self) -> Self::Async {
+		MemoryBoundedTaskAsync {
+			task: self.task.into_async(),
+			budget: self.budget,
+			task_memory: self.task_memory,
+			reserved: false,
+			done: false,
+		}
+	}
+}
+
+#[pin_project(PinnedDrop)]
+pub(crate) struct MemoryBoundedTaskAsync<T> {
+	#[pin]
+	task: T,
+	budget: MemoryBudget,
+	task_memory: usize,
+	reserved: bool,
+	done: bool,
+}
+impl<T: Stream> Stream for MemoryBoundedTaskAsync<T> {
+	type Item = T::Item;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let self_ = self.project();
+		if *self_.done {
+			return Poll::Ready(None);
+		}
+		if !*self_.reserved {
+			if !ready!(self_.budget.poll_reserve(*self_.task_memory, cx)) {
+				*self_.done = true;
+				return Poll::Ready(None);
+			}
+			*self_.reserved = true;
+		}
+		self_.budget.measure(false);
+		let item = if self_.budget.is_exceeded() {
+			None
+		} else {
+			ready!(self_.task.poll_next(cx))
+		};
+		if item.is_none() {
+			*self_.done = true;
+			*self_.reserved = false;
+			self_.budget.release(*self_.task_memory);
+		}
+		Poll::Ready(item)
+	}
+}
+
+#[pinned_drop]
+impl<T> PinnedDrop for MemoryBoundedTaskAsync<T> {
+	fn drop(self: Pin<&mut Self>) {
+		let self_ = self.project();
+		if *self_.reserved {
+			self_.budget.release(*self_.task_memory);
+		}
+	}
+}
@@ -0,0 +1,561 @@
+use futures::{channel::mpsc, Stream};
+use once_cell::sync::Lazy;
+use pin_project::{pin_project, pinned_drop};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+	cell::RefCell, collections::HashMap, convert::TryFrom, fmt, io::{self, Write}, pin::Pin, sync::{
+		atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering}, Arc, Mutex, Weak
+	}, task::{Context, Poll}, thread, time::{Duration, Instant}
+};
+
+use super::{ParallelStream, StreamTask};
+
+/// Items are added to the shared count in batches of this size, to avoid contention.
+const ITEMS_BATCH: u64 = 1024;
+
+type Subscriber = Box<dyn FnMut(&MetricsSnapshot) + Send>;
+
+/// The [`Metrics`] in this process, by id.
+static METRICS: Lazy<Mutex<HashMap<u64, Weak<MetricsInner>>>> = Lazy::new(Default::default);
+/// Counts reported by tasks run in this process for [`Metrics`] in another, by id.
+static REMOTE: Lazy<Mutex<HashMap<u64, Arc<Counts>>>> = Lazy::new(Default::default);
+
+thread_local! {
+	/// The monitored tasks being polled on this thread, which bytes read are charged to.
+	static RUNNING: RefCell<Vec<Recorder>> = RefCell::new(Vec::new());
+}
+
+/// Counters for monitoring the progress of a job, shared between the driver and the tasks.
+///
+/// Attach it to a stream with `monitor`, and either poll it with [`snapshot()`](Self::snapshot)
+/// or subscribe to updates, which are sent whenever a task is scheduled, completes, fails or is
+/// dropped. Tasks run in another process report back as each batch of tasks sent to it completes;
+/// those still running when a job ends early, as with `take`, aren't counted.
+#[derive(Clone)]
+pub struct Metrics(Arc<MetricsInner>);
+
+struct MetricsInner {
+	id: u64,
+	scheduled: AtomicUsize,
+	/// The expected number of tasks plus one, or zero if unknown.
+	expected: AtomicUsize,
+	exhausted: AtomicBool,
+	counts: Counts,
+	subscribers: Mutex<Vec<Subscriber>>,
+}
+
+/// What tasks report, which for tasks run in another process is sent back in batches.
+#[derive(Default)]
+struct Counts {
+	completed: AtomicUsize,
+	failed: AtomicUsize,
+	dropped: AtomicUsize,
+	items: AtomicU64,
+	bytes: AtomicU64,
+	task_time: AtomicU64,
+	max_task_time: AtomicU64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Delta {
+	completed: usize,
+	failed: usize,
+	dropped: usize,
+	items: u64,
+	bytes: u64,
+	task_time: u64,
+	max_task_time: u64,
+}
+
+impl Counts {
+	fn take(&self) -> Delta {
+		Delta {
+			completed: self.completed.swap(0, Ordering::SeqCst),
+			failed: self.failed.swap(0, Ordering::SeqCst),
+			dropped: self.dropped.swap(0, Ordering::SeqCst),
+			items: self.items.swap(0, Ordering::SeqCst),
+			bytes: self.bytes.swap(0, Ordering::SeqCst),
+			task_time: self.task_time.swap(0, Ordering::SeqCst),
+			max_task_time: self.max_task_time.swap(0, Ordering::SeqCst),
+		}
+	}
+	fn add(&self, delta: &Delta) {
+		let _ = self.items.fetch_add(delta.items, Ordering::SeqCst);
+		let _ = self.bytes.fetch_add(delta.bytes, Ordering::SeqCst);
+		let _ = self.task_time.fetch_add(delta.task_time, Ordering::SeqCst);
+		let _ = self
+			.max_task_time
+			.fetch_max(delta.max_task_time, Ordering::SeqCst);
+		// last, so that a job isn't seen as finished before the rest is added
+		let _ = self.failed.fetch_add(delta.failed, Ordering::SeqCst);
+		let _ = self.dropped.fetch_add(delta.dropped, Ordering::SeqCst);
+		let _ = self.completed.fetch_add(delta.completed, Ordering::SeqCst);
+	}
+}
+
+/// The counts reported by tasks run in a process for [`Metrics`] in another, taken with
+/// [`take_remote()`] and sent back along with the results of a batch of tasks.
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct RemoteCounts(Vec<(u64, Delta)>);
+
+/// Take the counts reported by tasks run in this process for [`Metrics`] in another.
+pub(crate) fn take_remote() -> RemoteCounts {
+	let mut counts = Vec::new();
+	REMOTE.lock().unwrap().retain(|&id, counts_| {
+		// checked first, as once no task holds them nothing more can be added after the take
+		let held = Arc::strong_count(counts_) > 1;
+		counts.push((id, counts_.take()));
+		held
+	});
+	RemoteCounts(counts)
+}
+
+/// Add the counts sent back from another process to the [`Metrics`] they belong to.
+pub(crate) fn add_remote(counts: RemoteCounts) {
+	for (id, delta) in counts.0 {
+		let metrics = METRICS.lock().unwrap().get(&id).and_then(Weak::upgrade);
+		if let Some(metrics) = metrics {
+			metrics.counts.add(&delta);
+			Metrics(metrics).update();
+		}
+	}
+}
+
+/// Charge `bytes` read from a source to the [`Metrics`] of the monitored tasks being run on this
+/// thread, if any. Sources call this as they read.
+pub fn record_bytes_read(bytes: usize) {
+	let bytes = u64::try_from(bytes).unwrap();
+	RUNNING.with(|running| {
+		for recorder in &*running.borrow() {
+			let _ = recorder.counts().bytes.fetch_add(bytes, Ordering::SeqCst);
+		}
+	});
+}
+
+/// A point-in-time copy of a job's [`Metrics`].
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub struct MetricsSnapshot {
+	/// Tasks handed to the pool so far.
+	pub scheduled: usize,
+	/// Tasks that have run to completion.
+	pub completed: usize,
+	/// Tasks that panicked.
+	pub failed: usize,
+	/// Tasks dropped without being run, for example as `take` had no more need of them.
+	pub dropped: usize,
+	/// The number of tasks the stream expects to produce, if known.
+	pub expected: Option<usize>,
+	/// Whether the stream has run out of tasks, or been dropped, and all scheduled tasks have
+	/// finished.
+	pub finished: bool,
+	/// Items yielded by finished tasks, and running tasks to within a small batch.
+	pub items: u64,
+	/// Bytes read by the tasks from their sources.
+	pub bytes: u64,
+	/// The combined time spent running tasks.
+	pub task_time: Duration,
+	/// The time spent running the slowest task.
+	pub max_task_time: Duration,
+}
+
+impl Metrics {
+	pub fn new() -> Self {
+		let inner = Arc::new(MetricsInner {
+			id: rand::random(),
+			scheduled: AtomicUsize::new(0),
+			expected: AtomicUsize::new(0),
+			exhausted: AtomicBool::new(false),
+			counts: Counts::default(),
+			subscribers: Mutex::new(Vec::new()),
+		});
+		let mut registry = METRICS.lock().unwrap();
+		registry.retain(|_, metrics| metrics.strong_count() > 0);
+		let _ = registry.insert(inner.id, Arc::downgrade(&inner));
+		Self(inner)
+	}
+
+	pub fn snapshot(&self) -> MetricsSnapshot {
+		let inner = &*self.0;
+		let counts = &inner.counts;
+		// completed first, as it's added last, so the rest of a finished job is in hand
+		let completed = counts.completed.load(Ordering::SeqCst);
+		let failed = counts.failed.load(Ordering::SeqCst);
+		let dropped = counts.dropped.load(Ordering::SeqCst);
+		let scheduled = inner.scheduled.load(Ordering::SeqCst);
+		let expected = inner.expected.load(Ordering::SeqCst);
+		MetricsSnapshot {
+			scheduled,
+			completed,
+			failed,
+			dropped,
+			expected: expected.checked_sub(1),
+			finished: inner.exhausted.load(Ordering::SeqCst)
+				&& completed + failed + dropped == scheduled,
+			items: counts.items.load(Ordering::SeqCst),
+			bytes: counts.bytes.load(Ordering::SeqCst),
+			task_time: Duration::from_nanos(counts.task_time.load(Ordering::SeqCst)),
+			max_task_time: Duration::from_nanos(counts.max_task_time.load(Ordering::SeqCst)),
+		}
+	}
+
+	/// Call `f` with a fresh snapshot whenever a task is scheduled, completes, fails or is
+	/// dropped, and whenever counts arrive from another process.
+	///
+	/// This is called from the threads running the tasks, so it should be quick.
+	pub fn on_update<F>(&self, f: F)
+	where
+		F: FnMut(&MetricsSnapshot) + Send + 'static,
+	{
+		self.0.subscribers.lock().unwrap().push(Box::new(f));
+	}
+
+	/// Returns a channel of snapshots, sent whenever a task is scheduled, completes, fails or is
+	/// dropped, and whenever counts arrive from another process.
+	pub fn subscribe(&self) -> mpsc::UnboundedReceiver<MetricsSnapshot> {
+		let (sender, receiver) = mpsc::unbounded();
+		self.on_update(move |snapshot| {
+			let _ = sender.unbounded_send(*snapshot);
+		});
+		receiver
+	}
+
+	/// Draw a progress bar on stderr, redrawn at most every `interval`.
+	pub fn progress_bar(&self, interval: Duration) {
+		let start = Instant::now();
+		let mut last: Option<Instant> = None;
+		self.on_update(move |snapshot| {
+			let now = Instant::now();
+			if !snapshot.finished && last.map_or(false, |last| now - last < interval) {
+				return;
+			}
+			last = Some(now);
+			let mut stderr = io::stderr();
+			let _ = write!(stderr, "\r{} {:.1?}", snapshot, now - start);
+			if snapshot.finished {
+				let _ = writeln!(stderr);
+			}
+			let _ = stderr.flush();
+		});
+	}
+
+	fn update(&self) {
+		let mut subscribers = self.0.subscribers.lock().unwrap();
+		if !subscribers.is_empty() {
+			let snapshot = self.snapshot();
+			for subscriber in &mut *subscribers {
+				subscriber(&snapshot);
+			}
+		}
+	}
+}
+
+impl Default for Metrics {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl fmt::Debug for Metrics {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::Debug::fmt(&self.snapshot(), f)
+	}
+}
+
+impl fmt::Display for MetricsSnapshot {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		const WIDTH: usize = 30;
+		let done = self.completed + self.failed + self.dropped;
+		if let Some(expected) = self.expected.filter(|&expected| expected > 0) {
+			let filled = WIDTH * done.min(expected) / expected;
+			write!(
+				f,
+				"[{}{}] {}/{} tasks",
+				"#".repeat(filled),
+				" ".repeat(WIDTH - filled),
+				done,
+				expected
+			)?;
+		} else {
+			write!(f, "{}/{} tasks", done, self.scheduled)?;
+		}
+		write!(f, ", {} items", self.items)?;
+		if self.bytes > 0 {
+			write!(f, ", {} bytes", self.bytes)?;
+		}
+		if self.failed > 0 {
+			write!(f, ", {} failed", self.failed)?;
+		}
+		Ok(())
+	}
+}
+
+/// Where a task reports to: the [`Metrics`] itself if it's in this process, and otherwise counts
+/// that are sent back to it with the results of the task's batch. It's serialized by id.
+#[derive(Clone)]
+enum Recorder {
+	Local(Metrics),
+	Remote(u64, Arc<Counts>),
+}
+impl Recorder {
+	fn counts(&self) -> &Counts {
+		match self {
+			Self::Local(metrics) => &metrics.0.counts,
+			Self::Remote(_, counts) => counts,
+		}
+	}
+	fn update(&self) {
+		if let Self::Local(metrics) = self {
+			metrics.update();
+		}
+	}
+	fn add_items(&self, items: u64) {
+		let _ = self.counts().items.fetch_add(items, Ordering::SeqCst);
+	}
+	fn finish_task(&self, time: Duration, failed: bool) {
+		let counts = self.counts();
+		let time = u64::try_from(time.as_nanos()).unwrap_or(u64::max_value());
+		let _ = counts.task_time.fetch_add(time, Ordering::SeqCst);
+		let _ = counts.max_task_time.fetch_max(time, Ordering::SeqCst);
+		let count = if failed {
+			&counts.failed
+		} else {
+			&counts.completed
+		};
+		let _ = count.fetch_add(1, Ordering::SeqCst);
+		self.update();
+	}
+	fn drop_task(&self) {
+		let _ = self.counts().dropped.fetch_add(1, Ordering::SeqCst);
+		self.update();
+	}
+}
+impl Serialize for Recorder {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		match self {
+			Self::Local(metrics) => metrics.0.id,
+			Self::Remote(id, _) => *id,
+		}
+		.serialize(serializer)
+	}
+}
+impl<'de> Deserialize<'de> for Recorder {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let id = u64::deserialize(deserializer)?;
+		let metrics = METRICS.lock().unwrap().get(&id).and_then(Weak::upgrade);
+		Ok(if let Some(metrics) = metrics {
+			Self::Local(Metrics(metrics))
+		} else {
+			let counts = REMOTE.lock().unwrap().entry(id).or_default().clone();
+			Self::Remote(id, counts)
+		})
+	}
+}
+
+/// Counts a task as dropped if it's dropped without being run. A task that's serialized is run by
+/// its deserialized copy, so serializing it hands this on.
+struct Unrun {
+	recorder: Recorder,
+	armed: AtomicBool,
+}
+impl Unrun {
+	fn new(recorder: Recorder) -> Self {
+		Self {
+			recorder,
+			armed: AtomicBool::new(true),
+		}
+	}
+	fn run(&self) -> Recorder {
+		self.armed.store(false, Ordering::SeqCst);
+		self.recorder.clone()
+	}
+}
+impl Drop for Unrun {
+	fn drop(&mut self) {
+		if *self.armed.get_mut() {
+			self.recorder.drop_task();
+		}
+	}
+}
+impl Serialize for Unrun {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		self.armed.store(false, Ordering::SeqCst);
+		self.recorder.serialize(serializer)
+	}
+}
+impl<'de> Deserialize<'de> for Unrun {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		Recorder::deserialize(deserializer).map(Self::new)
+	}
+}
+
+/// Marks a monitored task as being polled on this thread until dropped, so that bytes read are
+/// charged to it.
+struct Running;
+impl Running {
+	fn enter(recorder: &Recorder) -> Self {
+		RUNNING.with(|running| running.borrow_mut().push(recorder.clone()));
+		Self
+	}
+}
+impl Drop for Running {
+	fn drop(&mut self) {
+		let _ = RUNNING.with(|running| running.borrow_mut().pop());
+	}
+}
+
+/// Records the progress of a stream in a [`Metrics`]. Created by `monitor`.
+#[pin_project(PinnedDrop)]
+#[must_use]
+pub struct Monitor<P> {
+	#[pin]
+	pipe: P,
+	metrics: Metrics,
+}
+impl<P> Monitor<P> {
+	pub(crate) fn new(pipe: P, metrics: Metrics, expected: Option<usize>) -> Self {
+		if let Some(expected) = expected {
+			let expected = expected.saturating_add(1);
+			let _ = metrics.0.expected.compare_exchange(
+				0,
+				expected,
+				Ordering::SeqCst,
+				Ordering::SeqCst,
+			);
+		}
+		Self { pipe, metrics }
+	}
+}
+
+impl_par_dist! {
+	impl<P: ParallelStream> ParallelStream for Monitor<P> {
+		type Item = P::Item;
+		type Task = MonitorTask<P::Task>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			self.pipe.size_hint()
+		}
+		fn next_task(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let self_ = self.project();
+			let metrics = self_.metrics;
+			self_.pipe.next_task(cx).map(|task| {
+				if task.is_some() {
+					let _ = metrics.0.scheduled.fetch_add(1, Ordering::SeqCst);
+				} else {
+					metrics.0.exhausted.store(true, Ordering::SeqCst);
+				}
+				metrics.update();
+				task.map(|task| MonitorTask {
+					task,
+					unrun: Unrun::new(Recorder::Local(metrics.clone())),
+				})
+			})
+		}
+	}
+}
+
+#[pinned_drop]
+impl<P> PinnedDrop for Monitor<P> {
+	fn drop(self: Pin<&mut Self>) {
+		// No more tasks will be scheduled, even if the job ended before the stream ran out.
+		let metrics = self.project().metrics;
+		if !metrics.0.exhausted.swap(true, Ordering::SeqCst) {
+			metrics.update();
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MonitorTask<C> {
+	task: C,
+	unrun: Unrun,
+}
+impl<C: StreamTask> StreamTask for MonitorTask<C> {
+	type Item = C::Item;
+	type Async = MonitorTaskAsync<C::Async>;
+
+	fn partition(&self) -> Option<String> {
+		self.task.partition()
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.task.size_hint()
+	}
+	fn into_async(self) -> Self::Async {
+		MonitorTaskAsync {
+			task: self.task.into_async(),
+			recorder: self.unrun.run(),
+			start: None,
+			items: 0,
+			done: false,
+		}
+	}
+}
+
+#[pin_project(PinnedDrop)]
+pub struct MonitorTaskAsync<T> {
+	#[pin]
+	task: T,
+	recorder: Recorder,
+	start: Option<Instant>,
+	items: u64,
+	done: bool,
+}
+impl<T: Stream> Stream for MonitorTaskAsync<T> {
+	type Item = T::Item;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let self_ = self.project();
+		let start = *self_.start.get_or_insert_with(Instant::now);
+		let running = Running::enter(self_.recorder);
+		let item = self_.task.poll_next(cx);
+		drop(running);
+		match &item {
+			Poll::Ready(Some(_)) => {
+				*self_.items += 1;
+				if *self_.items == ITEMS_BATCH {
+					self_.recorder.add_items(ITEMS_BATCH);
+					*self_.items = 0;
+				}
+			}
+			Poll::Ready(None) if !*self_.done => {
+				*self_.done = true;
+				self_.recorder.add_items(*self_.items);
+				*self_.items = 0;
+				self_.recorder.finish_task(start.elapsed(), false);
+			}
+			_ => (),
+		}
+		item
+	}
+}
+
+#[pinned_drop]
+impl<T> PinnedDrop for MonitorTaskAsync<T> {
+	fn drop(self: Pin<&mut Self>) {
+		let self_ = self.project();
+		if *self_.done {
+			return;
+		}
+		if let Some(start) = self_.start {
+			// Dropped early, either because it panicked or because the reducer needed no more
+			// items, as with `any` or `first`.
+			self_.recorder.add_items(*self_.items);
+			self_
+				.recorder
+				.finish_task(start.elapsed(), thread::panicking());
+		} else {
+			self_.recorder.drop_task();
+		}
+	}
+}
@@ -0,0 +1,190 @@
+use futures::{
+	future::BoxFuture, stream::{self, FuturesUnordered}, FutureExt, Stream, StreamExt
+};
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
+use serde_closure::FnOnce;
+use std::{
+	collections::VecDeque, mem, pin::Pin, task::{Context, Poll}, vec
+};
+
+use super::{DistributedStream, ParallelStream, StreamTask};
+use crate::pool::{ProcessPool, ProcessSend, ThreadPool};
+
+/// Redistributes the items of a stream into new tasks of even size. Created by `repartition` and
+/// `rebalance`.
+///
+/// The tasks of the underlying stream are run on the pool, at most one per worker at a time, and
+/// their items are sent back to this process and split into new tasks, which are yielded as soon
+/// as they're full. So only a few tasks' worth of items are held here at once, rather than the
+/// whole stream, and work after this stage is spread evenly over the pool however unevenly the
+/// items were spread between the source's partitions. Items are yielded in no particular order.
+#[pin_project]
+#[must_use]
+pub struct Repartition<S, P, T> {
+	#[pin]
+	stream: S,
+	pool: P,
+	/// The number of items in each new task, or `None` to split the items of each task of the
+	/// underlying stream between the workers of the pool.
+	size: Option<usize>,
+	workers: usize,
+	running: FuturesUnordered<BoxFuture<'static, Vec<T>>>,
+	buffer: Vec<T>,
+	ready: VecDeque<Vec<T>>,
+	exhausted: bool,
+}
+impl<S, P, T> Repartition<S, P, T> {
+	pub(crate) fn new(stream: S, pool: P, workers: usize, size: Option<usize>) -> Self {
+		assert_ne!(
+			size,
+			Some(0),
+			"Amadeus: repartition size must be at least 1"
+		);
+		Self {
+			stream,
+			pool,
+			size,
+			workers: workers.max(1),
+			running: FuturesUnordered::new(),
+			buffer: Vec::new(),
+			ready: VecDeque::new(),
+			exhausted: false,
+		}
+	}
+
+	fn tasks_hint(&self) -> (usize, Option<usize>) {
+		let ready = self.ready.len() + usize::from(!self.buffer.is_empty());
+		if self.exhausted && self.running.is_empty() {
+			(ready, Some(ready))
+		} else {
+			(self.ready.len(), None)
+		}
+	}
+
+	/// Run the tasks of the underlying stream with `spawn` until the next new task is full.
+	fn poll_next_task<U>(
+		self: Pin<&mut Self>, cx: &mut Context,
+		mut next_task: impl FnMut(Pin<&mut S>, &mut Context) -> Poll<Option<U>>,
+		mut spawn: impl FnMut(&P, U) -> BoxFuture<'static, Vec<T>>,
+	) -> Poll<Option<RepartitionTask<T>>> {
+		let mut self_ = self.project();
+		loop {
+			if let Some(items) = self_.ready.pop_front() {
+				return Poll::Ready(Some(RepartitionTask(items)));
+			}
+			while !*self_.exhausted && self_.running.len() < *self_.workers {
+				match next_task(self_.stream.as_mut(), cx) {
+					Poll::Ready(Some(task)) => self_.running.push(spawn(self_.pool, task)),
+					Poll::Ready(None) => *self_.exhausted = true,
+					Poll::Pending => break,
+				}
+			}
+			match self_.running.poll_next_unpin(cx) {
+				Poll::Ready(Some(items)) => {
+					let size = self_.size.unwrap_or_else(|| {
+						// ceiling division, so that the new tasks differ in size by at most one
+						((items.len() + *self_.workers - 1) / *self_.workers).max(1)
+					});
+					for item in items {
+						self_.buffer.push(item);
+						if self_.buffer.len() == size {
+							let items = mem::replace(self_.buffer, Vec::with_capacity(size));
+							self_.ready.push_back(items);
+						}
+					}
+					if self_.size.is_none() && !self_.buffer.is_empty() {
+						self_.ready.push_back(mem::take(self_.buffer));
+					}
+				}
+				Poll::Ready(None) if *self_.exhausted => {
+					return Poll::Ready(if self_.buffer.is_empty() {
+						None
+					} else {
+						Some(RepartitionTask(mem::take(self_.buffer)))
+					});
+				}
+				Poll::Ready(None) | Poll::Pending => return Poll::Pending,
+			}
+		}
+	}
+}
+
+/// Wait for a task of the underlying stream, panicking if it did.
+fn wait<T: Send + 'static>(
+	partition: Option<String>,
+	task: BoxFuture<'static, Result<Vec<T>, Box<dyn std::error::Error + Send>>>,
+) -> BoxFuture<'static, Vec<T>> {
+	task.map(move |items| {
+		items.unwrap_or_else(|err| {
+			panic!(
+				"Amadeus: task '{}' panicked at '{}'",
+				partition.as_deref().unwrap_or("<unnamed>"),
+				err
+			)
+		})
+	})
+	.boxed()
+}
+
+impl<S, P> ParallelStream for Repartition<S, P, S::Item>
+where
+	S: ParallelStream,
+	P: ThreadPool,
+	S::Task: 'static,
+	S::Item: Send + 'static,
+{
+	type Item = S::Item;
+	type Task = RepartitionTask<S::Item>;
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.tasks_hint()
+	}
+	fn next_task(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Task>> {
+		self.poll_next_task(cx, S::next_task, |pool, task| {
+			let partition = task.partition();
+			let items = pool.spawn(move || task.into_async().collect::<Vec<_>>());
+			wait(partition, items)
+		})
+	}
+}
+
+#[cfg_attr(not(nightly), serde_closure::desugar)]
+impl<S, P> DistributedStream for Repartition<S, P, S::Item>
+where
+	S: DistributedStream,
+	P: ProcessPool,
+	S::Task: 'static,
+	S::Item: ProcessSend + 'static,
+{
+	type Item = S::Item;
+	type Task = RepartitionTask<S::Item>;
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.tasks_hint()
+	}
+	fn next_task(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Task>> {
+		self.poll_next_task(cx, S::next_task, |pool, task| {
+			let partition = task.partition();
+			let items = pool.spawn(FnOnce!(move |_pool: &P::ThreadPool| task
+				.into_async()
+				.collect::<Vec<_>>()));
+			wait(partition, items)
+		})
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RepartitionTask<T>(Vec<T>);
+
+impl<T> StreamTask for RepartitionTask<T> {
+	type Item = T;
+	type Async = stream::Iter<vec::IntoIter<T>>;
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.0.len(), Some(self.0.len()))
+	}
+	fn into_async(self) -> Self::Async {
+		stream::iter(self.0)
+	}
+}
@@ -0,0 +1,144 @@
+use futures::Stream;
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
+use std::{
+	any::Any, panic::{self, AssertUnwindSafe}, pin::Pin, task::{Context, Poll}
+};
+
+use super::{ParallelStream, StreamTask};
+
+/// Re-runs tasks whose stream panicked. Created by `retry`.
+///
+/// A task that panics is restarted from a copy of the original task, skipping the items it had
+/// already yielded, so the stream must yield the same items in the same order each time it's
+/// run, and each retry re-reads the partition from its start. Tasks are named in the error by
+/// their source partition if known, and by their index otherwise. Only panics from the stream up
+/// to this point are caught, for example from reading a flaky source; panics in later stages
+/// aren't retried.
+#[pin_project]
+#[must_use]
+pub struct Retry<P> {
+	#[pin]
+	pipe: P,
+	retries: usize,
+	index: usize,
+}
+impl<P> Retry<P> {
+	pub(crate) fn new(pipe: P, retries: usize) -> Self {
+		Self {
+			pipe,
+			retries,
+			index: 0,
+		}
+	}
+}
+
+impl_par_dist! {
+	impl<P: ParallelStream> ParallelStream for Retry<P>
+	where
+		P::Task: Clone,
+	{
+		type Item = P::Item;
+		type Task = RetryTask<P::Task>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			self.pipe.size_hint()
+		}
+		fn next_task(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let self_ = self.project();
+			let (retries, index) = (*self_.retries, self_.index);
+			self_.pipe.next_task(cx).map(|task| {
+				task.map(|task| {
+					let name = task.partition().map_or_else(
+						|| format!("task {}", index),
+						|partition| format!("partition {}", partition),
+					);
+					*index += 1;
+					RetryTask {
+						task,
+						retries,
+						name,
+					}
+				})
+			})
+		}
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RetryTask<C> {
+	task: C,
+	retries: usize,
+	name: String,
+}
+impl<C: StreamTask + Clone> StreamTask for RetryTask<C> {
+	type Item = C::Item;
+	type Async = RetryTaskAsync<C>;
+
+	fn partition(&self) -> Option<String> {
+		self.task.partition()
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.task.size_hint()
+	}
+	fn into_async(self) -> Self::Async {
+		RetryTaskAsync {
+			current: Box::pin(self.task.clone().into_async()),
+			task: self.task,
+			retries: self.retries,
+			name: self.name,
+			attempt: 0,
+			yielded: 0,
+			skip: 0,
+		}
+	}
+}
+
+#[pin_project]
+pub struct RetryTaskAsync<C: StreamTask> {
+	task: C,
+	current: Pin<Box<C::Async>>,
+	retries: usize,
+	name: String,
+	attempt: usize,
+	yielded: usize,
+	skip: usize,
+}
+
+impl<C: StreamTask + Clone> Stream for RetryTaskAsync<C> {
+	type Item = C::Item;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let self_ = self.project();
+		loop {
+			let current = &mut *self_.current;
+			match panic::catch_unwind(AssertUnwindSafe(|| current.as_mut().poll_next(cx))) {
+				Ok(Poll::Ready(Some(_))) if *self_.skip > 0 => *self_.skip -= 1,
+				Ok(Poll::Ready(Some(item))) => {
+					*self_.yielded += 1;
+					break Poll::Ready(Some(item));
+				}
+				Ok(poll) => break poll,
+				Err(err) => {
+					assert!(
+						*self_.attempt < *self_.retries,
+						"{} failed after {} attempts: {}",
+						self_.name,
+						*self_.attempt + 1,
+						panic_message(&*err)
+					);
+					*self_.attempt += 1;
+					*self_.current = Box::pin(self_.task.clone().into_async());
+					*self_.skip = *self_.yielded;
+				}
+			}
+		}
+	}
+}
+
+fn panic_message(err: &(dyn Any + Send)) -> &str {
+	err.downcast_ref::<String>()
+		.map(String::as_str)
+		.or_else(|| err.downcast_ref::<&str>().copied())
+		.unwrap_or("Box<Any>")
+}
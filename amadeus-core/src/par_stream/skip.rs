@@ -0,0 +1,189 @@
+use futures::{ready, Stream};
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
+use std::{
+	pin::Pin, task::{Context, Poll}, vec
+};
+
+use super::{ParallelStream, StreamTask};
+
+/// Skips the first `n` items, in the partition ordering of the underlying stream. Created by
+/// `skip`.
+///
+/// Tasks whose [`size_hint()`](StreamTask::size_hint) is exact are dropped whole while they hold
+/// only items to be skipped, without being run. Otherwise the tasks that may hold items to be
+/// skipped are sent to the pool as one, which runs them in order skipping the first items, until
+/// their lower bounds add up to the number left to skip.
+#[pin_project]
+#[must_use]
+pub struct Skip<P, T> {
+	#[pin]
+	pipe: P,
+	remaining: usize,
+	pending: Option<Pending<T>>,
+}
+impl<P, T> Skip<P, T> {
+	pub(crate) fn new(pipe: P, n: usize) -> Self {
+		Self {
+			pipe,
+			remaining: n,
+			pending: None,
+		}
+	}
+}
+
+/// Tasks to be run as one, skipping the first `skip` of their items.
+struct Pending<T> {
+	tasks: Vec<T>,
+	skip: usize,
+	lower: usize,
+	upper: Option<usize>,
+}
+
+impl_par_dist! {
+	impl<P: ParallelStream> ParallelStream for Skip<P, P::Task> {
+		type Item = P::Item;
+		type Task = SkipTask<P::Task>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			let (lower, upper) = self.pipe.size_hint();
+			let (lower, upper) = match &self.pending {
+				Some(pending) => (
+					lower + pending.lower,
+					upper.and_then(|upper| Some(upper + pending.upper?)),
+				),
+				None => (lower, upper),
+			};
+			(
+				lower.saturating_sub(self.remaining),
+				upper.map(|upper| upper.saturating_sub(self.remaining)),
+			)
+		}
+		fn next_task(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let mut self_ = self.project();
+			loop {
+				if *self_.remaining == 0 {
+					return self_.pipe.next_task(cx).map(|task| {
+						task.map(|task| SkipTask {
+							tasks: vec![task],
+							skip: 0,
+						})
+					});
+				}
+				let task = if let Some(task) = ready!(self_.pipe.as_mut().next_task(cx)) {
+					task
+				} else {
+					*self_.remaining = 0;
+					return Poll::Ready(self_.pending.take().map(Pending::into_task));
+				};
+				let (lower, upper) = task.size_hint();
+				if let Some(pending) = self_.pending {
+					pending.tasks.push(task);
+					pending.lower += lower;
+					pending.upper = pending.upper.and_then(|pending| Some(pending + upper?));
+					if pending.lower >= pending.skip {
+						*self_.remaining = 0;
+						return Poll::Ready(self_.pending.take().map(Pending::into_task));
+					}
+				} else if upper == Some(lower) && lower <= *self_.remaining {
+					*self_.remaining -= lower;
+				} else if lower >= *self_.remaining {
+					let skip = *self_.remaining;
+					*self_.remaining = 0;
+					return Poll::Ready(Some(SkipTask {
+						tasks: vec![task],
+						skip,
+					}));
+				} else {
+					*self_.pending = Some(Pending {
+						tasks: vec![task],
+						skip: *self_.remaining,
+						lower,
+						upper,
+					});
+				}
+			}
+		}
+	}
+}
+
+impl<T> Pending<T> {
+	fn into_task(self) -> SkipTask<T> {
+		SkipTask {
+			tasks: self.tasks,
+			skip: self.skip,
+		}
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SkipTask<T> {
+	tasks: Vec<T>,
+	skip: usize,
+}
+
+impl<C: StreamTask> StreamTask for SkipTask<C> {
+	type Item = C::Item;
+	type Async = SkipTaskAsync<C>;
+
+	fn partition(&self) -> Option<String> {
+		let partitions = self
+			.tasks
+			.iter()
+			.map(StreamTask::partition)
+			.collect::<Option<Vec<_>>>()?;
+		Some(partitions.join(", "))
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let (lower, upper) = self.tasks.iter().map(StreamTask::size_hint).fold(
+			(0, Some(0)),
+			|(lower, upper), (lower_, upper_)| {
+				(
+					lower + lower_,
+					upper.and_then(|upper| Some(upper + upper_?)),
+				)
+			},
+		);
+		(
+			lower.saturating_sub(self.skip),
+			upper.map(|upper| upper.saturating_sub(self.skip)),
+		)
+	}
+	fn into_async(self) -> Self::Async {
+		SkipTaskAsync {
+			tasks: self.tasks.into_iter(),
+			current: None,
+			skip: self.skip,
+		}
+	}
+}
+
+/// Runs the tasks one after another, skipping the first `skip` items.
+#[pin_project]
+pub struct SkipTaskAsync<C: StreamTask> {
+	tasks: vec::IntoIter<C>,
+	#[pin]
+	current: Option<C::Async>,
+	skip: usize,
+}
+
+impl<C: StreamTask> Stream for SkipTaskAsync<C> {
+	type Item = C::Item;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let mut self_ = self.project();
+		loop {
+			if self_.current.is_none() {
+				match self_.tasks.next() {
+					Some(task) => self_.current.set(Some(task.into_async())),
+					None => return Poll::Ready(None),
+				}
+			}
+			match ready!(self_.current.as_mut().as_pin_mut().unwrap().poll_next(cx)) {
+				Some(_) if *self_.skip > 0 => *self_.skip -= 1,
+				Some(item) => return Poll::Ready(Some(item)),
+				None => self_.current.set(None),
+			}
+		}
+	}
+}
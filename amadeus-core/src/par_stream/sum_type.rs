@@ -45,6 +45,18 @@ impl<A: StreamTask, B: StreamTask<Item = A::Item>> StreamTask for Sum2<A, B> {
 	type Item = A::Item;
 	type Async = Sum2<A::Async, B::Async>;
 
+	fn partition(&self) -> Option<String> {
+		match self {
+			Sum2::A(a) => a.partition(),
+			Sum2::B(b) => b.partition(),
+		}
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		match self {
+			Sum2::A(a) => a.size_hint(),
+			Sum2::B(b) => b.size_hint(),
+		}
+	}
 	fn into_async(self) -> Self::Async {
 		match self {
 			Sum2::A(a) => Sum2::A(a.into_async()),
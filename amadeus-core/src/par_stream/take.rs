@@ -0,0 +1,452 @@
+use derive_new::new;
+use educe::Educe;
+use futures::{ready, Stream};
+use once_cell::sync::Lazy;
+use pin_project::pin_project;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+	collections::HashMap, marker::PhantomData, mem, pin::Pin, sync::{
+		atomic::{AtomicUsize, Ordering}, Arc, Mutex, Weak
+	}, task::{Context, Poll}
+};
+
+use super::{ParallelStream, StreamTask};
+use crate::{
+	par_sink::{Reducer, ReducerProcessSend, ReducerSend}, pipe::Sink, pool::ProcessSend
+};
+
+/// Tracks which tasks have run to completion and how many items each yielded, so as to work out
+/// the first task that isn't needed: the one after the shortest prefix of tasks that yields `n`
+/// items.
+#[derive(Debug)]
+struct Completed {
+	n: usize,
+	next: usize,
+	sum: usize,
+	done: HashMap<usize, usize>,
+}
+impl Completed {
+	fn new(n: usize) -> Self {
+		Self {
+			n,
+			next: 0,
+			sum: 0,
+			done: HashMap::new(),
+		}
+	}
+	/// Record that task `index` completed having yielded `count` items, returning the first task
+	/// that isn't needed if that's now known.
+	fn complete(&mut self, index: usize, count: usize) -> Option<usize> {
+		if index >= self.next && self.sum < self.n {
+			let _ = self.done.insert(index, count);
+			while let Some(count) = self.done.remove(&self.next) {
+				self.sum += count;
+				self.next += 1;
+			}
+		}
+		self.cutoff()
+	}
+	fn cutoff(&self) -> Option<usize> {
+		if self.sum >= self.n {
+			Some(self.next)
+		} else {
+			None
+		}
+	}
+}
+
+/// Shared between the stream, its tasks and the final reducer of a `take`, so that once the first
+/// `n` items are known to be in hand no more tasks are scheduled and those running are cut short.
+///
+/// It's serialized by id, and deserialized to the instance with that id in this process if there
+/// is one, so that tasks sent to the same process share it.
+#[derive(Debug)]
+pub(crate) struct Progress {
+	id: u64,
+	completed: Mutex<Completed>,
+	cutoff: AtomicUsize,
+}
+
+static PROGRESS: Lazy<Mutex<HashMap<u64, Weak<Progress>>>> = Lazy::new(Default::default);
+
+impl Progress {
+	fn get(id: u64, n: usize) -> Arc<Self> {
+		let mut registry = PROGRESS.lock().unwrap();
+		if let Some(progress) = registry.get(&id).and_then(Weak::upgrade) {
+			return progress;
+		}
+		registry.retain(|_, progress| progress.strong_count() > 0);
+		let completed = Completed::new(n);
+		let cutoff = AtomicUsize::new(completed.cutoff().unwrap_or(usize::max_value()));
+		let progress = Arc::new(Self {
+			id,
+			completed: Mutex::new(completed),
+			cutoff,
+		});
+		let _ = registry.insert(id, Arc::downgrade(&progress));
+		progress
+	}
+	pub(crate) fn new(n: usize) -> Arc<Self> {
+		Self::get(rand::random(), n)
+	}
+	/// The first task that isn't needed, or `usize::max_value()` if that's not yet known.
+	fn cutoff(&self) -> usize {
+		self.cutoff.load(Ordering::Relaxed)
+	}
+	fn complete(&self, index: usize, count: usize) {
+		if let Some(cutoff) = self.completed.lock().unwrap().complete(index, count) {
+			self.cutoff.store(cutoff, Ordering::Relaxed);
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+struct Shared(Arc<Progress>);
+impl Serialize for Shared {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		let n = self.0.completed.lock().unwrap().n;
+		(self.0.id, n).serialize(serializer)
+	}
+}
+impl<'de> Deserialize<'de> for Shared {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		<(u64, usize)>::deserialize(deserializer).map(|(id, n)| Self(Progress::get(id, n)))
+	}
+}
+
+/// Tags each item with the index of the task it came from, i.e. its position in the partition
+/// ordering of the underlying stream, and ends each task that runs to completion with a `None`.
+/// This is what lets `take` be deterministic despite tasks completing in any order.
+///
+/// Each task stops after `n` items, as no more of them can be needed, and no more tasks are
+/// scheduled once the first `n` items are known to be in hand.
+#[pin_project]
+#[derive(new)]
+#[must_use]
+pub(crate) struct Take<S> {
+	#[pin]
+	stream: S,
+	n: usize,
+	progress: Arc<Progress>,
+	#[new(default)]
+	next: usize,
+}
+
+impl_par_dist! {
+	impl<S: ParallelStream> ParallelStream for Take<S> {
+		type Item = (usize, Option<S::Item>);
+		type Task = TakeTask<S::Task>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			let remaining = self.progress.cutoff().saturating_sub(self.next);
+			let (lower, upper) = self.stream.size_hint();
+			(
+				lower.min(remaining),
+				Some(upper.map_or(remaining, |upper| upper.min(remaining))),
+			)
+		}
+		fn next_task(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let self_ = self.project();
+			if *self_.next >= self_.progress.cutoff() {
+				return Poll::Ready(None);
+			}
+			let (next, n, progress) = (self_.next, *self_.n, self_.progress);
+			self_.stream.next_task(cx).map(|task| {
+				task.map(|task| {
+					let index = *next;
+					*next += 1;
+					TakeTask {
+						task,
+						index,
+						n,
+						progress: Shared(progress.clone()),
+						count: 0,
+						done: false,
+					}
+				})
+			})
+		}
+	}
+}
+
+#[pin_project]
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TakeTask<T> {
+	#[pin]
+	task: T,
+	index: usize,
+	n: usize,
+	progress: Shared,
+	count: usize,
+	done: bool,
+}
+
+impl<C: StreamTask> StreamTask for TakeTask<C> {
+	type Item = (usize, Option<C::Item>);
+	type Async = TakeTask<C::Async>;
+
+	fn partition(&self) -> Option<String> {
+		self.task.partition()
+	}
+	fn into_async(self) -> Self::Async {
+		TakeTask {
+			task: self.task.into_async(),
+			index: self.index,
+			n: self.n,
+			progress: self.progress,
+			count: self.count,
+			done: self.done,
+		}
+	}
+}
+
+impl<C: Stream> Stream for TakeTask<C> {
+	type Item = (usize, Option<C::Item>);
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let self_ = self.project();
+		let index = *self_.index;
+		if *self_.done || index >= self_.progress.0.cutoff() {
+			*self_.done = true;
+			return Poll::Ready(None);
+		}
+		let item = if *self_.count < *self_.n {
+			ready!(self_.task.poll_next(cx))
+		} else {
+			None
+		};
+		if item.is_some() {
+			*self_.count += 1;
+		} else {
+			*self_.done = true;
+			self_.progress.0.complete(index, *self_.count);
+		}
+		Poll::Ready(Some((index, item)))
+	}
+}
+
+/// The items held by a `take` reducer, along with the tasks it saw run to completion and how many
+/// items each yielded. Only the `n` items earliest in partition order are kept, as no more of them
+/// can be needed.
+#[derive(Serialize, Deserialize)]
+#[serde(bound(serialize = "Item: Serialize", deserialize = "Item: Deserialize<'de>"))]
+pub(crate) struct Taken<Item> {
+	completed: Vec<(usize, usize)>,
+	items: Vec<(usize, Item)>,
+}
+impl<Item> Taken<Item> {
+	fn new() -> Self {
+		Self {
+			completed: Vec::new(),
+			items: Vec::new(),
+		}
+	}
+	fn push(&mut self, n: usize, item: (usize, Item)) {
+		self.items.push(item);
+		if self.items.len() > n.max(1) * 2 {
+			self.truncate(n);
+		}
+	}
+	fn extend(&mut self, n: usize, other: Self) {
+		self.completed.extend(other.completed);
+		self.items.extend(other.items);
+		if self.items.len() > n.max(1) * 2 {
+			self.truncate(n);
+		}
+	}
+	fn truncate(&mut self, n: usize) {
+		// stable, so items from the same task keep their order
+		self.items.sort_by_key(|&(index, _)| index);
+		self.items.truncate(n);
+	}
+}
+
+/// Collects up to `n` indexed items on a worker, recording which tasks ran to completion.
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub(crate) struct TakeReducer<Item> {
+	n: usize,
+	marker: PhantomData<fn() -> Item>,
+}
+
+impl<Item> Reducer<(usize, Option<Item>)> for TakeReducer<Item> {
+	type Done = Taken<Item>;
+	type Async = TakeReducerAsync<Item>;
+
+	fn into_async(self) -> Self::Async {
+		TakeReducerAsync {
+			taken: Some(Taken::new()),
+			counts: HashMap::new(),
+			n: self.n,
+		}
+	}
+}
+impl<Item> ReducerProcessSend<(usize, Option<Item>)> for TakeReducer<Item>
+where
+	Item: ProcessSend + 'static,
+{
+	type Done = Taken<Item>;
+}
+impl<Item> ReducerSend<(usize, Option<Item>)> for TakeReducer<Item>
+where
+	Item: Send + 'static,
+{
+	type Done = Taken<Item>;
+}
+
+#[pin_project]
+pub(crate) struct TakeReducerAsync<Item> {
+	taken: Option<Taken<Item>>,
+	counts: HashMap<usize, usize>,
+	n: usize,
+}
+impl<Item> Sink<(usize, Option<Item>)> for TakeReducerAsync<Item> {
+	type Done = Taken<Item>;
+
+	#[inline(always)]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context,
+		mut stream: Pin<&mut impl Stream<Item = (usize, Option<Item>)>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		let taken = self_.taken.as_mut().unwrap();
+		while let Some((index, item)) = ready!(stream.as_mut().poll_next(cx)) {
+			if let Some(item) = item {
+				*self_.counts.entry(index).or_default() += 1;
+				taken.push(*self_.n, (index, item));
+			} else {
+				let count = self_.counts.remove(&index).unwrap_or(0);
+				taken.completed.push((index, count));
+			}
+		}
+		let mut taken = self_.taken.take().unwrap();
+		taken.truncate(*self_.n);
+		Poll::Ready(taken)
+	}
+}
+
+/// Merges the items held by each worker or process, keeping the `n` earliest in partition order.
+#[derive(Educe, Serialize, Deserialize, new)]
+#[educe(Clone)]
+#[serde(bound = "")]
+pub(crate) struct TakeMergeReducer<Item> {
+	n: usize,
+	marker: PhantomData<fn() -> Item>,
+}
+
+impl<Item> Reducer<Taken<Item>> for TakeMergeReducer<Item> {
+	type Done = Taken<Item>;
+	type Async = TakeMergeReducerAsync<Item>;
+
+	fn into_async(self) -> Self::Async {
+		TakeMergeReducerAsync {
+			taken: Some(Taken::new()),
+			n: self.n,
+		}
+	}
+}
+impl<Item> ReducerProcessSend<Taken<Item>> for TakeMergeReducer<Item>
+where
+	Item: ProcessSend + 'static,
+{
+	type Done = Taken<Item>;
+}
+impl<Item> ReducerSend<Taken<Item>> for TakeMergeReducer<Item>
+where
+	Item: Send + 'static,
+{
+	type Done = Taken<Item>;
+}
+
+#[pin_project]
+pub(crate) struct TakeMergeReducerAsync<Item> {
+	taken: Option<Taken<Item>>,
+	n: usize,
+}
+impl<Item> Sink<Taken<Item>> for TakeMergeReducerAsync<Item> {
+	type Done = Taken<Item>;
+
+	#[inline(always)]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context,
+		mut stream: Pin<&mut impl Stream<Item = Taken<Item>>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		let taken = self_.taken.as_mut().unwrap();
+		while let Some(other) = ready!(stream.as_mut().poll_next(cx)) {
+			taken.extend(*self_.n, other);
+		}
+		let mut taken = self_.taken.take().unwrap();
+		taken.truncate(*self_.n);
+		Poll::Ready(taken)
+	}
+}
+
+/// Merges the items held by each worker or process and returns the first `n` in partition order.
+/// It returns as soon as it holds every item from the tasks that yield them, dropping any tasks
+/// still running, and records which tasks have completed in the shared [`Progress`] so that no
+/// more are scheduled.
+#[derive(new)]
+pub(crate) struct TakeFinalReducer<Item> {
+	n: usize,
+	progress: Arc<Progress>,
+	marker: PhantomData<fn() -> Item>,
+}
+
+impl<Item> Reducer<Taken<Item>> for TakeFinalReducer<Item> {
+	type Done = Vec<Item>;
+	type Async = TakeFinalReducerAsync<Item>;
+
+	fn into_async(self) -> Self::Async {
+		let completed = Completed::new(self.n);
+		TakeFinalReducerAsync {
+			taken: Taken::new(),
+			cutoff: completed.cutoff(),
+			completed,
+			progress: self.progress,
+		}
+	}
+}
+
+#[pin_project]
+pub(crate) struct TakeFinalReducerAsync<Item> {
+	taken: Taken<Item>,
+	completed: Completed,
+	cutoff: Option<usize>,
+	progress: Arc<Progress>,
+}
+impl<Item> Sink<Taken<Item>> for TakeFinalReducerAsync<Item> {
+	type Done = Vec<Item>;
+
+	#[inline(always)]
+	fn poll_forward(
+		self: Pin<&mut Self>, cx: &mut Context,
+		mut stream: Pin<&mut impl Stream<Item = Taken<Item>>>,
+	) -> Poll<Self::Done> {
+		let self_ = self.project();
+		let n = self_.completed.n;
+		while self_.cutoff.is_none() {
+			if let Some(mut other) = ready!(stream.as_mut().poll_next(cx)) {
+				for (index, count) in mem::take(&mut other.completed) {
+					self_.progress.complete(index, count);
+					*self_.cutoff = self_.completed.complete(index, count);
+				}
+				self_.taken.extend(n, other);
+			} else {
+				break;
+			}
+		}
+		let cutoff = self_.cutoff.unwrap_or(usize::max_value());
+		let mut taken = mem::replace(self_.taken, Taken::new());
+		taken.items.retain(|&(index, _)| index < cutoff);
+		taken.truncate(n);
+		Poll::Ready(taken.items.into_iter().map(|(_, item)| item).collect())
+	}
+}
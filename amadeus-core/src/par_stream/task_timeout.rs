@@ -0,0 +1,115 @@
+use futures::{Future, Stream};
+use futures_timer::Delay;
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
+use std::{
+	error::Error, fmt, pin::Pin, task::{Context, Poll}, time::Duration
+};
+
+use super::{ParallelStream, StreamTask};
+
+/// Fails tasks that run for longer than a timeout. Created by `task_timeout`.
+///
+/// A timer wakes the task once it times out, so it fails even while waiting on an item, though not
+/// while blocking the thread it runs on. A timed out task yields a [`TimedOut`] error and ends, so
+/// items are wrapped in a `Result`.
+#[pin_project]
+#[must_use]
+pub struct TaskTimeout<P> {
+	#[pin]
+	pipe: P,
+	timeout: Duration,
+}
+impl<P> TaskTimeout<P> {
+	pub(crate) fn new(pipe: P, timeout: Duration) -> Self {
+		Self { pipe, timeout }
+	}
+}
+
+impl_par_dist! {
+	impl<P: ParallelStream> ParallelStream for TaskTimeout<P> {
+		type Item = Result<P::Item, TimedOut>;
+		type Task = TaskTimeoutTask<P::Task>;
+
+		fn size_hint(&self) -> (usize, Option<usize>) {
+			self.pipe.size_hint()
+		}
+		fn next_task(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Task>> {
+			let self_ = self.project();
+			let timeout = *self_.timeout;
+			self_
+				.pipe
+				.next_task(cx)
+				.map(|task| task.map(|task| TaskTimeoutTask { task, timeout }))
+		}
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TaskTimeoutTask<C> {
+	task: C,
+	timeout: Duration,
+}
+impl<C: StreamTask> StreamTask for TaskTimeoutTask<C> {
+	type Item = Result<C::Item, TimedOut>;
+	type Async = TaskTimeoutTaskAsync<C::Async>;
+
+	fn partition(&self) -> Option<String> {
+		self.task.partition()
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		// timing out cuts the task short, with an error item
+		let (lower, upper) = self.task.size_hint();
+		(lower.min(1), upper.and_then(|upper| upper.checked_add(1)))
+	}
+	fn into_async(self) -> Self::Async {
+		TaskTimeoutTaskAsync {
+			task: self.task.into_async(),
+			timeout: self.timeout,
+			delay: None,
+			done: false,
+		}
+	}
+}
+
+#[pin_project]
+pub struct TaskTimeoutTaskAsync<T> {
+	#[pin]
+	task: T,
+	timeout: Duration,
+	#[pin]
+	delay: Option<Delay>,
+	done: bool,
+}
+impl<T: Stream> Stream for TaskTimeoutTaskAsync<T> {
+	type Item = Result<T::Item, TimedOut>;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let mut self_ = self.project();
+		if *self_.done {
+			return Poll::Ready(None);
+		}
+		if self_.delay.is_none() {
+			self_.delay.set(Some(Delay::new(*self_.timeout)));
+		}
+		if self_.delay.as_pin_mut().unwrap().poll(cx).is_ready() {
+			*self_.done = true;
+			return Poll::Ready(Some(Err(TimedOut {
+				timeout: *self_.timeout,
+			})));
+		}
+		self_.task.poll_next(cx).map(|item| item.map(Ok))
+	}
+}
+
+/// The error yielded by a task of a `task_timeout` stream that ran for longer than `timeout`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct TimedOut {
+	pub timeout: Duration,
+}
+impl fmt::Display for TimedOut {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "task timed out after {:?}", self.timeout)
+	}
+}
+impl Error for TimedOut {}
@@ -1,3 +1,4 @@
+mod batch;
 mod filter;
 mod filter_map_sync;
 mod flat_map;
@@ -12,7 +13,9 @@ use std::{
 	marker::PhantomData, mem, ops::DerefMut, pin::Pin, task::{Context, Poll}
 };
 
-pub use self::{filter::*, filter_map_sync::*, flat_map::*, flat_map_sync::*, flatten::*, map::*};
+pub use self::{
+	batch::*, filter::*, filter_map_sync::*, flat_map::*, flat_map_sync::*, flatten::*, map::*
+};
 
 // Sink takes Input as an input parameter rather than associated type to accept
 // for<'a> &'a T, but this might not be necessary in future?
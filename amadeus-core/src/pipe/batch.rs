@@ -0,0 +1,170 @@
+use futures::{ready, Stream};
+use pin_project::pin_project;
+use std::{
+	marker::PhantomData, pin::Pin, task::{Context, Poll}
+};
+
+use super::Pipe;
+
+#[pin_project]
+pub struct Batch<P, T, B> {
+	#[pin]
+	pipe: P,
+	size: usize,
+	/// The items of the current batch, reserved up front and reused between batches.
+	buffer: Vec<T>,
+	done: bool,
+	marker: PhantomData<fn() -> B>,
+}
+impl<P, T, B> Batch<P, T, B> {
+	pub fn new(pipe: P, size: usize) -> Self {
+		Self {
+			pipe,
+			size,
+			buffer: Vec::new(),
+			done: false,
+			marker: PhantomData,
+		}
+	}
+
+	/// Add an item to the current batch, returning the batch if it's now full.
+	#[inline]
+	fn push(buffer: &mut Vec<T>, size: usize, item: T) -> Option<B>
+	where
+		B: Default + Extend<T>,
+	{
+		if buffer.capacity() == 0 {
+			buffer.reserve_exact(size);
+		}
+		buffer.push(item);
+		if buffer.len() == size {
+			Self::take(buffer)
+		} else {
+			None
+		}
+	}
+
+	/// Move the items of the current batch into a `B`, if there are any.
+	#[inline]
+	fn take(buffer: &mut Vec<T>) -> Option<B>
+	where
+		B: Default + Extend<T>,
+	{
+		if buffer.is_empty() {
+			return None;
+		}
+		let mut batch = B::default();
+		batch.extend(buffer.drain(..));
+		Some(batch)
+	}
+}
+
+impl<P: Stream, B> Stream for Batch<P, P::Item, B>
+where
+	B: Default + Extend<P::Item>,
+{
+	type Item = B;
+
+	#[inline]
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let mut self_ = self.project();
+		Poll::Ready(loop {
+			if *self_.done {
+				break None;
+			}
+			if let Some(item) = ready!(self_.pipe.as_mut().poll_next(cx)) {
+				if let Some(batch) = Self::push(self_.buffer, *self_.size, item) {
+					break Some(batch);
+				}
+			} else {
+				*self_.done = true;
+				break Self::take(self_.buffer);
+			}
+		})
+	}
+}
+
+impl<P: Pipe<Input>, B, Input> Pipe<Input> for Batch<P, P::Output, B>
+where
+	B: Default + Extend<P::Output>,
+{
+	type Output = B;
+
+	#[inline]
+	fn poll_next(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = Input>>,
+	) -> Poll<Option<Self::Output>> {
+		let mut self_ = self.project();
+		Poll::Ready(loop {
+			if *self_.done {
+				break None;
+			}
+			if let Some(item) = ready!(self_.pipe.as_mut().poll_next(cx, stream.as_mut())) {
+				if let Some(batch) = Self::push(self_.buffer, *self_.size, item) {
+					break Some(batch);
+				}
+			} else {
+				*self_.done = true;
+				break Self::take(self_.buffer);
+			}
+		})
+	}
+}
+
+#[pin_project]
+pub struct Unbatch<P, I> {
+	#[pin]
+	pipe: P,
+	next: Option<I>,
+}
+impl<P, I> Unbatch<P, I> {
+	pub fn new(pipe: P) -> Self {
+		Self { pipe, next: None }
+	}
+}
+
+impl<P: Stream> Stream for Unbatch<P, <P::Item as IntoIterator>::IntoIter>
+where
+	P::Item: IntoIterator,
+{
+	type Item = <P::Item as IntoIterator>::Item;
+
+	#[inline]
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let mut self_ = self.project();
+		Poll::Ready(loop {
+			if let Some(item) = self_.next.as_mut().and_then(Iterator::next) {
+				break Some(item);
+			} else if let Some(batch) = ready!(self_.pipe.as_mut().poll_next(cx)) {
+				*self_.next = Some(batch.into_iter());
+			} else {
+				*self_.next = None;
+				break None;
+			}
+		})
+	}
+}
+
+impl<P: Pipe<Input>, Input> Pipe<Input> for Unbatch<P, <P::Output as IntoIterator>::IntoIter>
+where
+	P::Output: IntoIterator,
+{
+	type Output = <P::Output as IntoIterator>::Item;
+
+	#[inline]
+	fn poll_next(
+		self: Pin<&mut Self>, cx: &mut Context, mut stream: Pin<&mut impl Stream<Item = Input>>,
+	) -> Poll<Option<Self::Output>> {
+		let mut self_ = self.project();
+		Poll::Ready(loop {
+			if let Some(item) = self_.next.as_mut().and_then(Iterator::next) {
+				break Some(item);
+			} else if let Some(batch) = ready!(self_.pipe.as_mut().poll_next(cx, stream.as_mut())) {
+				*self_.next = Some(batch.into_iter());
+			} else {
+				*self_.next = None;
+				break None;
+			}
+		})
+	}
+}
@@ -1,8 +1,9 @@
 use futures::future::BoxFuture;
+use rand::{rngs::StdRng, thread_rng, RngCore, SeedableRng};
 use serde::{Deserialize, Serialize};
 use serde_closure::traits;
 use std::{
-	error::Error, future::Future, panic::{RefUnwindSafe, UnwindSafe}
+	cell::RefCell, error::Error, future::Future, panic::{RefUnwindSafe, UnwindSafe}
 };
 
 pub trait ProcessSend: Send + Serialize + for<'de> Deserialize<'de> {}
@@ -119,3 +120,38 @@ where
 		(*self).spawn_unchecked(work)
 	}
 }
+
+thread_local! {
+	static SEEDED_RNG: RefCell<Option<StdRng>> = RefCell::new(None);
+}
+
+/// Run `f` with randomized sinks like `sample_unstable` drawing from a random number generator
+/// seeded with `seed` on this thread, rather than the thread's own. This lets pools run tasks
+/// reproducibly.
+pub fn with_seeded_rng<F, R>(seed: u64, f: F) -> R
+where
+	F: FnOnce() -> R,
+{
+	struct Restore(Option<StdRng>);
+	impl Drop for Restore {
+		fn drop(&mut self) {
+			let rng = self.0.take();
+			SEEDED_RNG.with(|seeded| *seeded.borrow_mut() = rng);
+		}
+	}
+	let rng = StdRng::seed_from_u64(seed);
+	let _restore = Restore(SEEDED_RNG.with(|seeded| seeded.borrow_mut().replace(rng)));
+	f()
+}
+
+/// Call `f` with the seeded random number generator if one's set on this thread by
+/// [`with_seeded_rng()`], and the thread's own otherwise.
+pub(crate) fn with_rng<F, R>(f: F) -> R
+where
+	F: FnOnce(&mut dyn RngCore) -> R,
+{
+	SEEDED_RNG.with(|seeded| match &mut *seeded.borrow_mut() {
+		Some(rng) => f(rng),
+		None => f(&mut thread_rng()),
+	})
+}
@@ -55,9 +55,10 @@ mod wrap {
 	use std::{
 		error, fmt::{self, Debug, Display}, io::Cursor, marker::PhantomData, ops::FnMut
 	};
+	use tracing::{debug_span, Instrument};
 
 	use amadeus_core::{
-		file::{Directory, File, Page, Partition, PathBuf}, into_par_stream::IntoDistributedStream, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
+		file::{Directory, File, Page, Partition, PathBuf}, into_par_stream::IteratorExt, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
 	};
 
 	pub use internal::record::ParquetData;
@@ -116,22 +117,30 @@ mod wrap {
 		#[allow(clippy::let_and_return)]
 		fn dist_stream(self) -> Self::DistStream {
 			self.partitions
-				.into_dist_stream()
+				.into_iter()
+				.dist_partitions()
 				.flat_map(FnMut!(|partition: F::Partition| async move {
+					let span = debug_span!("partition", ?partition);
 					Ok(stream::iter(
 						partition
 							.pages()
+							.instrument(span.clone())
 							.await
 							.map_err(ParquetError::Partition)?
 							.into_iter(),
 					)
-					.flat_map(|page| {
+					.flat_map(move |page| {
+						let span = span.clone();
 						async move {
 							let mut buf = Vec::with_capacity(10 * 1024 * 1024);
 							let reader = Page::reader(page);
 							pin_mut!(reader);
 							let buf = PassError::new(
-								reader.read_to_end(&mut buf).await.map(|_| Cursor::new(buf)),
+								reader
+									.read_to_end(&mut buf)
+									.instrument(span)
+									.await
+									.map(|_| Cursor::new(buf)),
 							);
 							Ok(stream::iter(
 								SerializedFileReader::new(buf)?.get_row_iter::<Row>(None)?,
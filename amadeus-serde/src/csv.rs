@@ -6,9 +6,10 @@ use serde_closure::FnMutNamed;
 use std::{
 	error, fmt::{self, Display}, io::Cursor, marker::PhantomData
 };
+use tracing::{debug_span, Instrument};
 
 use amadeus_core::{
-	file::{File, Page, Partition}, into_par_stream::IntoDistributedStream, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
+	file::{File, Page, Partition}, into_par_stream::IteratorExt, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
 };
 
 use super::{SerdeData, SerdeDeserializeGroup};
@@ -92,20 +93,24 @@ FnMutNamed! {
 	{
 		#[allow(clippy::let_and_return)]
 		let ret = async move {
+				let span = debug_span!("partition", ?partition);
 				Ok(stream::iter(
 					partition
 						.pages()
+						.instrument(span.clone())
 						.await
 						.map_err(CsvError::Partition)?
 						.into_iter(),
 				)
-				.flat_map(|page| {
+				.flat_map(move |page| {
+					let span = span.clone();
 					async move {
 						let mut buf = Vec::with_capacity(10 * 1024 * 1024);
 						let reader = Page::reader(page);
 						pin_mut!(reader);
 						let _ = reader
 							.read_to_end(&mut buf)
+							.instrument(span)
 							.await
 							.map_err(InternalCsvError::from)?;
 						Ok(stream::iter(
@@ -149,7 +154,7 @@ where
 	#[cfg(not(nightly))]
 	#[allow(clippy::type_complexity)]
 	type DistStream = amadeus_core::par_stream::FlatMap<
-		amadeus_core::into_par_stream::IterDistStream<std::vec::IntoIter<F::Partition>>,
+		amadeus_core::into_par_stream::PartitionsDistStream<std::vec::IntoIter<F::Partition>>,
 		Closure<F::Partition, Row, F::Error>,
 	>;
 	#[cfg(nightly)]
@@ -160,7 +165,10 @@ where
 	}
 	#[allow(clippy::let_and_return)]
 	fn dist_stream(self) -> Self::DistStream {
-		self.partitions.into_dist_stream().flat_map(Closure::new())
+		self.partitions
+			.into_iter()
+			.dist_partitions()
+			.flat_map(Closure::new())
 	}
 }
 
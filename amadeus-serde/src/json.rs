@@ -6,9 +6,10 @@ use serde_json::Error as InternalJsonError;
 use std::{
 	error, fmt::{self, Debug, Display}, io::{self, Cursor}, marker::PhantomData
 };
+use tracing::{debug_span, Instrument};
 
 use amadeus_core::{
-	file::{File, Page, Partition}, into_par_stream::IntoDistributedStream, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
+	file::{File, Page, Partition}, into_par_stream::IteratorExt, par_stream::DistributedStream, util::{DistParStream, ResultExpandIter}, Source
 };
 
 use super::{SerdeData, SerdeDeserialize};
@@ -51,20 +52,27 @@ FnMutNamed! {
 	{
 		#[allow(clippy::let_and_return)]
 		let ret = async move {
+				let span = debug_span!("partition", ?partition);
 				Ok(stream::iter(
 					partition
 						.pages()
+						.instrument(span.clone())
 						.await
 						.map_err(JsonError::Partition)?
 						.into_iter(),
 				)
-				.flat_map(|page| {
+				.flat_map(move |page| {
+					let span = span.clone();
 					async move {
 						let mut buf = Vec::with_capacity(10 * 1024 * 1024);
 						let reader = Page::reader(page);
 						pin_mut!(reader);
 						let buf = PassError::new(
-							reader.read_to_end(&mut buf).await.map(|_| Cursor::new(buf)),
+							reader
+								.read_to_end(&mut buf)
+								.instrument(span)
+								.await
+								.map(|_| Cursor::new(buf)),
 						);
 						Ok(stream::iter(
 							serde_json::Deserializer::from_reader(buf).into_iter().map(
@@ -103,7 +111,7 @@ where
 	#[cfg(not(nightly))]
 	#[allow(clippy::type_complexity)]
 	type DistStream = amadeus_core::par_stream::FlatMap<
-		amadeus_core::into_par_stream::IterDistStream<std::vec::IntoIter<F::Partition>>,
+		amadeus_core::into_par_stream::PartitionsDistStream<std::vec::IntoIter<F::Partition>>,
 		Closure<F::Partition, Row, F::Error>,
 	>;
 	#[cfg(nightly)]
@@ -114,7 +122,10 @@ where
 	}
 	#[allow(clippy::let_and_return)]
 	fn dist_stream(self) -> Self::DistStream {
-		self.partitions.into_dist_stream().flat_map(Closure::new())
+		self.partitions
+			.into_iter()
+			.dist_partitions()
+			.flat_map(Closure::new())
 	}
 }
 
@@ -0,0 +1,296 @@
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, f64::consts::PI, mem, ops};
+
+use super::{f64_to_usize, New, UnionAssign};
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+struct Centroid {
+	mean: f64,
+	weight: f64,
+}
+impl Centroid {
+	fn merge(&mut self, other: &Self) {
+		self.weight += other.weight;
+		self.mean += (other.mean - self.mean) * other.weight / self.weight;
+	}
+}
+
+/// An implementation of the merging [t-digest](https://github.com/tdunning/t-digest) data structure for estimating quantiles.
+///
+/// See [*Computing Extremely Accurate Quantiles Using t-Digests*](https://arxiv.org/abs/1902.04023) for background. Accuracy is highest toward the tails, which is what is wanted for e.g. p99 latencies. Digests over disjoint parts of a stream can be merged with [`UnionAssign`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TDigest {
+	compression: f64,
+	centroids: Vec<Centroid>,
+	unmerged: Vec<Centroid>,
+	count: f64,
+	min: f64,
+	max: f64,
+}
+
+impl TDigest {
+	/// Create an empty `TDigest` with the specified compression. Higher compression retains more centroids and gives more accurate estimates; `100.0` is a good default.
+	pub fn new(compression: f64) -> Self {
+		assert!(compression >= 1.0);
+		Self {
+			compression,
+			centroids: Vec::new(),
+			unmerged: Vec::new(),
+			count: 0.0,
+			min: f64::INFINITY,
+			max: f64::NEG_INFINITY,
+		}
+	}
+
+	/// "Visit" an element. NaNs are ignored.
+	#[inline]
+	pub fn push(&mut self, value: f64) {
+		if value.is_nan() {
+			return;
+		}
+		self.push_centroid(Centroid {
+			mean: value,
+			weight: 1.0,
+		});
+	}
+
+	fn push_centroid(&mut self, centroid: Centroid) {
+		self.count += centroid.weight;
+		self.min = self.min.min(centroid.mean);
+		self.max = self.max.max(centroid.mean);
+		self.unmerged.push(centroid);
+		if self.unmerged.len() >= f64_to_usize(self.compression.ceil()) * 5 {
+			self.compress();
+		}
+	}
+
+	/// Union another `TDigest` into this one.
+	pub fn union(&mut self, src: &Self) {
+		let (min, max) = (self.min.min(src.min), self.max.max(src.max));
+		for centroid in src.centroids.iter().chain(&src.unmerged) {
+			self.push_centroid(*centroid);
+		}
+		self.min = min;
+		self.max = max;
+	}
+
+	/// Retrieve the number of elements visited.
+	pub fn len(&self) -> f64 {
+		self.count
+	}
+
+	/// Returns whether no elements have been visited.
+	pub fn is_empty(&self) -> bool {
+		self.count == 0.0
+	}
+
+	/// The smallest element visited, or `None` if empty.
+	pub fn min(&self) -> Option<f64> {
+		if !self.is_empty() {
+			Some(self.min)
+		} else {
+			None
+		}
+	}
+
+	/// The largest element visited, or `None` if empty.
+	pub fn max(&self) -> Option<f64> {
+		if !self.is_empty() {
+			Some(self.max)
+		} else {
+			None
+		}
+	}
+
+	/// Estimate the value at quantile `q`, where `0.0 <= q <= 1.0`. Returns `None` if empty.
+	pub fn quantile(&self, q: f64) -> Option<f64> {
+		self.quantiles(&[q]).pop().unwrap()
+	}
+
+	/// Estimate the values at each of `qs`. See [`TDigest::quantile`].
+	pub fn quantiles(&self, qs: &[f64]) -> Vec<Option<f64>> {
+		let compressed;
+		let self_ = if !self.unmerged.is_empty() {
+			let mut self_ = self.clone();
+			self_.compress();
+			compressed = self_;
+			&compressed
+		} else {
+			self
+		};
+		qs.iter().map(|&q| self_.quantile_compressed(q)).collect()
+	}
+
+	fn quantile_compressed(&self, q: f64) -> Option<f64> {
+		assert!((0.0..=1.0).contains(&q), "quantile must be within 0.0..=1.0");
+		let first = self.centroids.first()?;
+		let last = self.centroids.last().unwrap();
+		let rank = q * self.count;
+		if rank <= first.weight / 2.0 {
+			return Some(interpolate(
+				self.min,
+				first.mean,
+				rank / (first.weight / 2.0),
+			));
+		}
+		if rank >= self.count - last.weight / 2.0 {
+			return Some(interpolate(
+				last.mean,
+				self.max,
+				(rank - (self.count - last.weight / 2.0)) / (last.weight / 2.0),
+			));
+		}
+		let mut cumulative = first.weight / 2.0;
+		for pair in self.centroids.windows(2) {
+			let (a, b) = (&pair[0], &pair[1]);
+			let step = (a.weight + b.weight) / 2.0;
+			if rank <= cumulative + step {
+				return Some(interpolate(a.mean, b.mean, (rank - cumulative) / step));
+			}
+			cumulative += step;
+		}
+		Some(last.mean)
+	}
+
+	/// Merge buffered elements into the centroids, such that no centroid exceeds the size permitted by the `k1` scale function.
+	fn compress(&mut self) {
+		if self.unmerged.is_empty() {
+			return;
+		}
+		let mut centroids = mem::take(&mut self.unmerged);
+		centroids.append(&mut self.centroids);
+		centroids.sort_by(|a, b| a.mean.partial_cmp(&b.mean).unwrap_or(Ordering::Equal));
+		let mut centroids = centroids.into_iter();
+		let mut current = centroids.next().unwrap();
+		let mut q0 = 0.0;
+		let mut q_limit = self.q_limit(q0);
+		for next in centroids {
+			if q0 + (current.weight + next.weight) / self.count <= q_limit {
+				current.merge(&next);
+			} else {
+				q0 += current.weight / self.count;
+				q_limit = self.q_limit(q0);
+				self.centroids.push(current);
+				current = next;
+			}
+		}
+		self.centroids.push(current);
+	}
+
+	fn q_limit(&self, q0: f64) -> f64 {
+		let k = self.compression / (2.0 * PI) * (2.0 * q0 - 1.0).asin() + 1.0;
+		if k >= self.compression / 4.0 {
+			1.0
+		} else {
+			((k * 2.0 * PI / self.compression).sin() + 1.0) / 2.0
+		}
+	}
+}
+
+fn interpolate(a: f64, b: f64, t: f64) -> f64 {
+	a + (b - a) * t.max(0.0).min(1.0)
+}
+
+impl New for TDigest {
+	type Config = f64;
+	fn new(config: &Self::Config) -> Self {
+		Self::new(*config)
+	}
+}
+impl<'a> UnionAssign<&'a TDigest> for TDigest {
+	fn union_assign(&mut self, rhs: &'a Self) {
+		self.union(rhs)
+	}
+}
+impl UnionAssign for TDigest {
+	fn union_assign(&mut self, rhs: Self) {
+		self.union(&rhs)
+	}
+}
+impl ops::AddAssign<f64> for TDigest {
+	fn add_assign(&mut self, rhs: f64) {
+		self.push(rhs)
+	}
+}
+impl<'a> ops::AddAssign<&'a Self> for TDigest {
+	fn add_assign(&mut self, rhs: &'a Self) {
+		self.union(rhs)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use rand::{seq::SliceRandom, SeedableRng};
+
+	#[test]
+	fn quantiles() {
+		let mut rng =
+			rand::rngs::SmallRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+		let n = 100_000;
+		let mut values = (0..n).map(f64::from).collect::<Vec<_>>();
+		values.shuffle(&mut rng);
+
+		let mut total = TDigest::new(100.0);
+		for chunk in values.chunks(7_919) {
+			let mut digest = TDigest::new(100.0);
+			for &value in chunk {
+				digest.push(value);
+			}
+			total.union_assign(&digest);
+		}
+		assert_eq!(total.len(), f64::from(n));
+		assert_eq!(total.min(), Some(0.0));
+		assert_eq!(total.max(), Some(f64::from(n - 1)));
+		for &q in &[0.0, 0.001, 0.01, 0.1, 0.5, 0.9, 0.99, 0.999, 1.0] {
+			let estimate = total.quantile(q).unwrap();
+			let actual = q * f64::from(n - 1);
+			assert!(
+				(estimate - actual).abs() <= f64::from(n) * 0.005,
+				"q: {} estimate: {} actual: {}",
+				q,
+				estimate,
+				actual
+			);
+		}
+		assert_eq!(TDigest::new(100.0).quantile(0.5), None);
+	}
+
+	#[test]
+	fn merge_order() {
+		// Digests of sorted, disjoint ranges merged out of order can leave a centroid straddling a
+		// gap that's filled later. The error is still bounded by half the largest centroid, which
+		// at the median holds up to π/compression of the elements.
+		let mut rng =
+			rand::rngs::SmallRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+		let n = 1_000;
+		let bound = f64::from(n) * PI / 100.0 / 2.0;
+		let values = (0..n).map(f64::from).collect::<Vec<_>>();
+		for &chunk in &[1, 7, 100, 250, 333, 500] {
+			let mut digests = values
+				.chunks(chunk)
+				.map(|chunk| {
+					let mut digest = TDigest::new(100.0);
+					for &value in chunk {
+						digest.push(value);
+					}
+					digest
+				})
+				.collect::<Vec<_>>();
+			for _ in 0..100 {
+				digests.shuffle(&mut rng);
+				let mut total = TDigest::new(100.0);
+				for digest in &digests {
+					total.union_assign(digest);
+				}
+				let estimate = total.quantile(0.5).unwrap();
+				assert!(
+					(estimate - 499.5).abs() <= bound,
+					"chunk: {} estimate: {}",
+					chunk,
+					estimate
+				);
+			}
+		}
+	}
+}
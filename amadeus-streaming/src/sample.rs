@@ -202,12 +202,16 @@ impl<T> ops::AddAssign for SampleUnstable<T> {
 				14,
 				15,
 			]); // TODO
-			for _ in 0..new.capacity() {
-				if rng.gen_range(0, m + n) < m {
-					new.push(self.reservoir.pop().unwrap());
+
+			// either reservoir may hold fewer than its capacity, if it's seen fewer elements
+			let len = self.reservoir.len() + other.reservoir.len();
+			for _ in 0..new.capacity().min(len) {
+				let t = if rng.gen_range(0, m + n) < m {
+					self.reservoir.pop().or_else(|| other.reservoir.pop())
 				} else {
-					new.push(other.reservoir.pop().unwrap());
-				}
+					other.reservoir.pop().or_else(|| self.reservoir.pop())
+				};
+				new.push(t.unwrap());
 			}
 			self.reservoir = new;
 			self.i += other.i;
@@ -258,4 +262,18 @@ mod test {
 		}
 		println!("{:#?}", hash);
 	}
+
+	#[test]
+	fn sample_unstable_merge_partial() {
+		let mut rng = rand::thread_rng();
+		let mut total = SampleUnstable::new(10);
+		for i in 0..5 {
+			let mut x = SampleUnstable::new(10);
+			x.push(i, &mut rng);
+			total += x;
+		}
+		let mut total = total.into_iter().collect::<Vec<_>>();
+		total.sort_unstable();
+		assert_eq!(total, (0..5).collect::<Vec<_>>());
+	}
 }
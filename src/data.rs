@@ -250,6 +250,13 @@ pub struct CloudfrontRow {
 	pub http_version: String,
 	pub fle_status: Option<String>,
 	pub fle_encrypted_fields: Option<String>,
+	pub remote_port: Option<u16>,
+	// pub time_to_first_byte: Option<Duration>,
+	pub detailed_result_type: Option<String>,
+	pub content_type: Option<String>,
+	pub content_length: Option<u64>,
+	pub range_start: Option<u64>,
+	pub range_end: Option<u64>,
 }
 #[cfg(feature = "aws")]
 impl From<amadeus_aws::CloudfrontRow> for CloudfrontRow {
@@ -276,6 +283,311 @@ impl From<amadeus_aws::CloudfrontRow> for CloudfrontRow {
 			http_version: from.http_version,
 			fle_status: from.fle_status,
 			fle_encrypted_fields: from.fle_encrypted_fields,
+			remote_port: from.remote_port,
+			// time_to_first_byte: from.time_to_first_byte,
+			detailed_result_type: from.detailed_result_type,
+			content_type: from.content_type,
+			content_length: from.content_length,
+			range_start: from.range_start,
+			range_end: from.range_end,
+		}
+	}
+}
+
+#[derive(
+	amadeus_derive::Data, Clone, Eq, PartialEq, PartialOrd, Hash, Serialize, Deserialize, Debug,
+)]
+#[amadeus(crate = "crate")]
+pub struct AlbRow {
+	pub kind: Option<String>,
+	pub time: DateTime,
+	pub load_balancer: String,
+	pub client_ip: IpAddr,
+	pub client_port: u16,
+	pub target_ip: Option<IpAddr>,
+	pub target_port: Option<u16>,
+	// pub request_processing_time: Option<Duration>,
+	// pub target_processing_time: Option<Duration>,
+	// pub response_processing_time: Option<Duration>,
+	pub elb_status: Option<u16>,
+	pub target_status: Option<u16>,
+	pub received_bytes: u64,
+	pub sent_bytes: u64,
+	pub method: Option<String>,
+	pub url: Option<Url>,
+	pub http_version: Option<String>,
+	pub user_agent: Option<String>,
+	pub ssl_protocol_cipher: Option<(String, String)>,
+	pub target_group_arn: Option<String>,
+	pub trace_id: Option<String>,
+	pub domain_name: Option<String>,
+	pub chosen_cert_arn: Option<String>,
+	pub matched_rule_priority: Option<u32>,
+	pub request_creation_time: Option<DateTime>,
+	pub actions_executed: List<String>,
+	pub redirect_url: Option<String>,
+	pub error_reason: Option<String>,
+}
+#[cfg(feature = "aws")]
+impl From<amadeus_aws::AlbRow> for AlbRow {
+	fn from(from: amadeus_aws::AlbRow) -> Self {
+		Self {
+			kind: from.kind,
+			time: from.time,
+			load_balancer: from.load_balancer,
+			client_ip: from.client_ip,
+			client_port: from.client_port,
+			target_ip: from.target_ip,
+			target_port: from.target_port,
+			// request_processing_time: from.request_processing_time,
+			// target_processing_time: from.target_processing_time,
+			// response_processing_time: from.response_processing_time,
+			elb_status: from.elb_status.map(|status| status.as_u16()),
+			target_status: from.target_status.map(|status| status.as_u16()),
+			received_bytes: from.received_bytes,
+			sent_bytes: from.sent_bytes,
+			method: from.method.map(|method| method.as_str().to_owned()),
+			url: from.url,
+			http_version: from.http_version,
+			user_agent: from.user_agent,
+			ssl_protocol_cipher: from.ssl_protocol_cipher,
+			target_group_arn: from.target_group_arn,
+			trace_id: from.trace_id,
+			domain_name: from.domain_name,
+			chosen_cert_arn: from.chosen_cert_arn,
+			matched_rule_priority: from.matched_rule_priority,
+			request_creation_time: from.request_creation_time,
+			actions_executed: from.actions_executed.into(),
+			redirect_url: from.redirect_url,
+			error_reason: from.error_reason,
+		}
+	}
+}
+
+#[derive(
+	amadeus_derive::Data, Clone, Eq, PartialEq, PartialOrd, Hash, Serialize, Deserialize, Debug,
+)]
+#[amadeus(crate = "crate")]
+pub struct CloudtrailRow {
+	pub event_time: DateTime,
+	pub event_version: String,
+	pub event_source: String,
+	pub event_name: String,
+	pub event_type: Option<String>,
+	pub event_category: Option<String>,
+	pub event_id: String,
+	pub aws_region: String,
+	pub source_ip_address: Option<String>,
+	pub user_agent: Option<String>,
+	pub user_type: Option<String>,
+	pub principal_id: Option<String>,
+	pub user_arn: Option<String>,
+	pub account_id: Option<String>,
+	pub access_key_id: Option<String>,
+	pub user_name: Option<String>,
+	pub invoked_by: Option<String>,
+	pub error_code: Option<String>,
+	pub error_message: Option<String>,
+	pub request_id: Option<String>,
+	pub request_parameters: Option<Json>,
+	pub response_elements: Option<Json>,
+	pub additional_event_data: Option<Json>,
+	pub resources: Option<Json>,
+	pub read_only: Option<bool>,
+	pub management_event: Option<bool>,
+	pub recipient_account_id: Option<String>,
+}
+#[cfg(feature = "aws")]
+impl From<amadeus_aws::CloudtrailRow> for CloudtrailRow {
+	fn from(from: amadeus_aws::CloudtrailRow) -> Self {
+		Self {
+			event_time: from.event_time,
+			event_version: from.event_version,
+			event_source: from.event_source,
+			event_name: from.event_name,
+			event_type: from.event_type,
+			event_category: from.event_category,
+			event_id: from.event_id,
+			aws_region: from.aws_region,
+			source_ip_address: from.source_ip_address,
+			user_agent: from.user_agent,
+			user_type: from.user_type,
+			principal_id: from.principal_id,
+			user_arn: from.user_arn,
+			account_id: from.account_id,
+			access_key_id: from.access_key_id,
+			user_name: from.user_name,
+			invoked_by: from.invoked_by,
+			error_code: from.error_code,
+			error_message: from.error_message,
+			request_id: from.request_id,
+			request_parameters: from.request_parameters,
+			response_elements: from.response_elements,
+			additional_event_data: from.additional_event_data,
+			resources: from.resources,
+			read_only: from.read_only,
+			management_event: from.management_event,
+			recipient_account_id: from.recipient_account_id,
+		}
+	}
+}
+
+#[derive(
+	amadeus_derive::Data, Clone, Eq, PartialEq, PartialOrd, Hash, Serialize, Deserialize, Debug,
+)]
+#[amadeus(crate = "crate")]
+pub struct VpcFlowRow {
+	pub version: Option<u8>,
+	pub account_id: Option<String>,
+	pub interface_id: Option<String>,
+	pub src_addr: Option<IpAddr>,
+	pub dst_addr: Option<IpAddr>,
+	pub src_port: Option<u16>,
+	pub dst_port: Option<u16>,
+	pub protocol: Option<u8>,
+	pub packets: Option<u64>,
+	pub bytes: Option<u64>,
+	pub start: Option<DateTime>,
+	pub end: Option<DateTime>,
+	pub action: Option<String>,
+	pub log_status: Option<String>,
+	pub vpc_id: Option<String>,
+	pub subnet_id: Option<String>,
+	pub instance_id: Option<String>,
+	pub tcp_flags: Option<u16>,
+	pub kind: Option<String>,
+	pub pkt_src_addr: Option<IpAddr>,
+	pub pkt_dst_addr: Option<IpAddr>,
+	pub region: Option<String>,
+	pub az_id: Option<String>,
+	pub sublocation_type: Option<String>,
+	pub sublocation_id: Option<String>,
+	pub pkt_src_aws_service: Option<String>,
+	pub pkt_dst_aws_service: Option<String>,
+	pub flow_direction: Option<String>,
+	pub traffic_path: Option<u8>,
+}
+#[cfg(feature = "aws")]
+impl From<amadeus_aws::VpcFlowRow> for VpcFlowRow {
+	fn from(from: amadeus_aws::VpcFlowRow) -> Self {
+		Self {
+			version: from.version,
+			account_id: from.account_id,
+			interface_id: from.interface_id,
+			src_addr: from.src_addr,
+			dst_addr: from.dst_addr,
+			src_port: from.src_port,
+			dst_port: from.dst_port,
+			protocol: from.protocol,
+			packets: from.packets,
+			bytes: from.bytes,
+			start: from.start,
+			end: from.end,
+			action: from.action,
+			log_status: from.log_status,
+			vpc_id: from.vpc_id,
+			subnet_id: from.subnet_id,
+			instance_id: from.instance_id,
+			tcp_flags: from.tcp_flags,
+			kind: from.kind,
+			pkt_src_addr: from.pkt_src_addr,
+			pkt_dst_addr: from.pkt_dst_addr,
+			region: from.region,
+			az_id: from.az_id,
+			sublocation_type: from.sublocation_type,
+			sublocation_id: from.sublocation_id,
+			pkt_src_aws_service: from.pkt_src_aws_service,
+			pkt_dst_aws_service: from.pkt_dst_aws_service,
+			flow_direction: from.flow_direction,
+			traffic_path: from.traffic_path,
+		}
+	}
+}
+
+#[derive(
+	amadeus_derive::Data, Clone, Eq, PartialEq, PartialOrd, Hash, Serialize, Deserialize, Debug,
+)]
+#[amadeus(crate = "crate")]
+pub struct WarcRecord {
+	pub record_type: String,
+	pub record_id: String,
+	pub date: DateTime,
+	pub target_uri: Option<String>,
+	pub ip_address: Option<IpAddr>,
+	pub content_type: Option<String>,
+	pub block_digest: Option<String>,
+	pub payload_digest: Option<String>,
+	pub concurrent_to: Option<String>,
+	pub headers: List<(String, String)>,
+	pub http_status: Option<u16>,
+	pub http_headers: Option<List<(String, String)>>,
+	pub payload: List<u8>,
+}
+#[cfg(feature = "commoncrawl")]
+impl From<amadeus_commoncrawl::WarcRecord> for WarcRecord {
+	fn from(from: amadeus_commoncrawl::WarcRecord) -> Self {
+		Self {
+			record_type: from.record_type.as_str().to_owned(),
+			record_id: from.record_id,
+			date: from.date,
+			target_uri: from.target_uri,
+			ip_address: from.ip_address,
+			content_type: from.content_type,
+			block_digest: from.block_digest,
+			payload_digest: from.payload_digest,
+			concurrent_to: from.concurrent_to,
+			headers: from.headers.into(),
+			http_status: from.http_status,
+			http_headers: from.http_headers.map(Into::into),
+			payload: from.payload.into(),
+		}
+	}
+}
+
+#[derive(
+	amadeus_derive::Data, Clone, Eq, PartialEq, PartialOrd, Hash, Serialize, Deserialize, Debug,
+)]
+#[amadeus(crate = "crate")]
+pub struct WetRecord {
+	pub url: String,
+	pub date: DateTime,
+	pub language: Option<String>,
+	pub text: String,
+}
+#[cfg(feature = "commoncrawl")]
+impl From<amadeus_commoncrawl::WetRecord> for WetRecord {
+	fn from(from: amadeus_commoncrawl::WetRecord) -> Self {
+		Self {
+			url: from.url,
+			date: from.date,
+			language: from.language,
+			text: from.text,
+		}
+	}
+}
+
+#[derive(
+	amadeus_derive::Data, Clone, Eq, PartialEq, PartialOrd, Hash, Serialize, Deserialize, Debug,
+)]
+#[amadeus(crate = "crate")]
+pub struct WatRecord {
+	pub url: String,
+	pub date: DateTime,
+	pub http_status: Option<u16>,
+	pub http_headers: List<(String, String)>,
+	pub outlinks: List<String>,
+	pub metadata: Json,
+}
+#[cfg(feature = "commoncrawl")]
+impl From<amadeus_commoncrawl::WatRecord> for WatRecord {
+	fn from(from: amadeus_commoncrawl::WatRecord) -> Self {
+		Self {
+			url: from.url,
+			date: from.date,
+			http_status: from.http_status,
+			http_headers: from.http_headers.into(),
+			outlinks: from.outlinks.into(),
+			metadata: from.metadata,
 		}
 	}
 }
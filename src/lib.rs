@@ -54,38 +54,44 @@ pub use crate::{
 
 pub mod dist {
 	pub mod prelude {
+		#[cfg(feature = "local-process")]
+		#[doc(no_inline)]
+		pub use crate::pool::LocalProcessPool;
 		#[cfg(feature = "constellation")]
 		#[doc(no_inline)]
 		pub use crate::pool::ProcessPool;
 		#[cfg(feature = "aws")]
 		#[doc(no_inline)]
 		pub use crate::source::aws::{
-			AwsCredentials, AwsError, AwsRegion, CloudfrontRow, S3Directory, S3File
+			AlbRow, AwsCredentials, AwsError, AwsRegion, AwsRetry, CloudfrontRow, CloudtrailRow, S3Directory, S3File, VpcFlowRow
 		};
 		#[doc(no_inline)]
 		pub use crate::{
 			data::{
 				Date, DateTime, DateTimeWithoutTimezone, DateWithoutTimezone, Decimal, Downcast, DowncastFrom, Enum, Group, Time, TimeWithoutTimezone, Timezone
-			}, par_pipe::DistributedPipe, par_stream::Identity, pool::ThreadPool, source::*, Data, DistributedStream, FromDistributedStream, IntoDistributedStream, IteratorExt, List, Value
+			}, par_pipe::DistributedPipe, par_stream::Identity, pool::{PoolConfig, ThreadPool}, source::*, Data, DistributedStream, FromDistributedStream, IntoDistributedStream, IteratorExt, List, Value
 		};
 		#[doc(no_inline)]
 		pub use serde_closure::{Fn, FnMut, FnOnce};
 	}
 }
 pub mod prelude {
+	#[cfg(feature = "local-process")]
+	#[doc(no_inline)]
+	pub use crate::pool::LocalProcessPool;
 	#[cfg(feature = "constellation")]
 	#[doc(no_inline)]
 	pub use crate::pool::ProcessPool;
 	#[cfg(feature = "aws")]
 	#[doc(no_inline)]
 	pub use crate::source::aws::{
-		AwsCredentials, AwsError, AwsRegion, CloudfrontRow, S3Directory, S3File
+		AlbRow, AwsCredentials, AwsError, AwsRegion, AwsRetry, CloudfrontRow, CloudtrailRow, S3Directory, S3File, VpcFlowRow
 	};
 	#[doc(no_inline)]
 	pub use crate::{
 		data::{
 			Date, DateTime, DateTimeWithoutTimezone, DateWithoutTimezone, Decimal, Downcast, DowncastFrom, Enum, Group, Time, TimeWithoutTimezone, Timezone
-		}, par_pipe::ParallelPipe, par_stream::Identity, pool::ThreadPool, source::*, Data, FromParallelStream, IntoParallelStream, IteratorExt, List, ParallelStream, Value
+		}, par_pipe::ParallelPipe, par_stream::Identity, pool::{PoolConfig, ThreadPool}, source::*, Data, FromParallelStream, IntoParallelStream, IteratorExt, List, ParallelStream, Value
 	};
 }
 
@@ -1,12 +1,24 @@
+mod config;
+#[cfg(not(target_arch = "wasm32"))]
+mod deterministic;
+#[cfg(feature = "local-process")]
+mod local_process;
 #[cfg(feature = "constellation")]
 mod process;
 mod thread;
+#[cfg(any(feature = "constellation", feature = "local-process"))]
+mod trace;
 pub(crate) mod util;
 
 use futures::future::{BoxFuture, TryFutureExt};
 use serde_closure::traits;
 use std::{error::Error, future::Future};
 
+pub use config::PoolConfig;
+#[cfg(not(target_arch = "wasm32"))]
+pub use deterministic::DeterministicPool;
+#[cfg(feature = "local-process")]
+pub use local_process::LocalProcessPool;
 #[cfg(feature = "constellation")]
 pub use process::ProcessPool;
 pub use thread::ThreadPool;
@@ -44,6 +56,33 @@ impl ProcessPoolTrait for ProcessPool {
 	}
 }
 
+#[cfg(feature = "local-process")]
+#[cfg_attr(not(nightly), serde_closure::desugar)]
+impl ProcessPoolTrait for LocalProcessPool {
+	type ThreadPool = ThreadPool;
+
+	fn processes(&self) -> usize {
+		LocalProcessPool::processes(self)
+	}
+	fn spawn<F, Fut, T>(&self, work: F) -> BoxFuture<'static, Result<T>>
+	where
+		F: traits::FnOnce(&Self::ThreadPool) -> Fut + ProcessSend + 'static,
+		Fut: Future<Output = T> + 'static,
+		T: ProcessSend + 'static,
+	{
+		Box::pin(LocalProcessPool::spawn(self, work).map_err(|e| Box::new(e) as _))
+	}
+	#[allow(unsafe_code)]
+	unsafe fn spawn_unchecked<'a, F, Fut, T>(&self, work: F) -> BoxFuture<'a, Result<T>>
+	where
+		F: traits::FnOnce(&Self::ThreadPool) -> Fut + ProcessSend + 'a,
+		Fut: Future<Output = T> + 'a,
+		T: ProcessSend + 'a,
+	{
+		Box::pin(LocalProcessPool::spawn_unchecked(self, work).map_err(|e| Box::new(e) as _))
+	}
+}
+
 #[cfg_attr(not(nightly), serde_closure::desugar)]
 impl ProcessPoolTrait for ThreadPool {
 	type ThreadPool = Self;
@@ -96,3 +135,56 @@ impl ThreadPoolTrait for ThreadPool {
 		Box::pin(ThreadPool::spawn_unchecked(self, work).map_err(|e| Box::new(e) as _))
 	}
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg_attr(not(nightly), serde_closure::desugar)]
+impl ProcessPoolTrait for DeterministicPool {
+	type ThreadPool = Self;
+
+	fn processes(&self) -> usize {
+		1
+	}
+	fn spawn<F, Fut, T>(&self, work: F) -> BoxFuture<'static, Result<T>>
+	where
+		F: traits::FnOnce(&Self::ThreadPool) -> Fut + ProcessSend + 'static,
+		Fut: Future<Output = T> + 'static,
+		T: ProcessSend + 'static,
+	{
+		Box::pin(DeterministicPool::spawn_process(self, work).map_err(|e| Box::new(e) as _))
+	}
+	#[allow(unsafe_code)]
+	unsafe fn spawn_unchecked<'a, F, Fut, T>(&self, work: F) -> BoxFuture<'a, Result<T>>
+	where
+		F: traits::FnOnce(&Self::ThreadPool) -> Fut + ProcessSend + 'a,
+		Fut: Future<Output = T> + 'a,
+		T: ProcessSend + 'a,
+	{
+		Box::pin(
+			DeterministicPool::spawn_process_unchecked(self, work).map_err(|e| Box::new(e) as _),
+		)
+	}
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ThreadPoolTrait for DeterministicPool {
+	fn threads(&self) -> usize {
+		1
+	}
+	fn spawn<F, Fut, T>(&self, work: F) -> BoxFuture<'static, Result<T>>
+	where
+		F: FnOnce() -> Fut + Send + 'static,
+		Fut: Future<Output = T> + 'static,
+		T: Send + 'static,
+	{
+		Box::pin(DeterministicPool::spawn(self, work).map_err(|e| Box::new(e) as _))
+	}
+	#[allow(unsafe_code)]
+	unsafe fn spawn_unchecked<'a, F, Fut, T>(&self, work: F) -> BoxFuture<'a, Result<T>>
+	where
+		F: FnOnce() -> Fut + Send + 'a,
+		Fut: Future<Output = T> + 'a,
+		T: Send + 'a,
+	{
+		Box::pin(DeterministicPool::spawn_unchecked(self, work).map_err(|e| Box::new(e) as _))
+	}
+}
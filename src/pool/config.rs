@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use std::{env, io, str::FromStr};
+
+pub(super) const DEFAULT_TASKS_PER_CORE: usize = 100;
+#[cfg(any(feature = "constellation", feature = "local-process"))]
+pub(super) const DEFAULT_RETRIES: usize = 3;
+
+/// Configuration shared by the pools, so the same binary can size itself sensibly wherever it
+/// runs.
+///
+/// Unset fields are resolved by each pool, with defaults based on the cores available to this
+/// process. Each pool's `config()` returns the configuration it resolved to. Pools only read the
+/// environment if asked to, with `with_config(&PoolConfig::from_env()?)`.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug, Serialize, Deserialize)]
+pub struct PoolConfig {
+	/// The number of worker processes.
+	pub processes: Option<usize>,
+	/// The number of threads each process runs tasks on. Defaults to the available cores, split
+	/// between the processes on this machine.
+	pub threads_per_process: Option<usize>,
+	/// The number of tasks each thread runs concurrently.
+	pub tasks_per_core: Option<usize>,
+	/// The memory in bytes to reserve for each process from the cluster. Only used by
+	/// `ProcessPool`.
+	pub memory_per_process: Option<u64>,
+	/// The number of times work is resent to another process if the process running it exits,
+	/// for example by crashing or being killed. Processes that exit are respawned. Defaults to 3.
+	/// Only used by the process pools; to re-run tasks that panic, see `retry`.
+	pub retries: Option<usize>,
+	/// Whether to pin each thread to its own core, to keep its caches warm. Defaults to false.
+	/// Only used by `ThreadPool` and `LocalProcessPool`, as `ProcessPool` can't tell which of its
+	/// processes share a machine.
+	pub pin_threads: Option<bool>,
+}
+
+impl PoolConfig {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Read the configuration from the environment variables `AMADEUS_PROCESSES`,
+	/// `AMADEUS_THREADS_PER_PROCESS`, `AMADEUS_TASKS_PER_CORE`, `AMADEUS_MEMORY_PER_PROCESS`,
+	/// `AMADEUS_RETRIES` and `AMADEUS_PIN_THREADS`. Unset variables leave their field unset, and
+	/// invalid ones are an error.
+	pub fn from_env() -> io::Result<Self> {
+		Self::from_lookup(|name| env::var(name))
+	}
+
+	/// Read the configuration from the variables that [`from_env()`](Self::from_env) reads, as
+	/// returned by `lookup` rather than from the environment.
+	pub fn from_lookup<F>(mut lookup: F) -> io::Result<Self>
+	where
+		F: FnMut(&str) -> Result<String, env::VarError>,
+	{
+		let config = Self {
+			processes: var(&mut lookup, "AMADEUS_PROCESSES")?,
+			threads_per_process: var(&mut lookup, "AMADEUS_THREADS_PER_PROCESS")?,
+			tasks_per_core: var(&mut lookup, "AMADEUS_TASKS_PER_CORE")?,
+			memory_per_process: var(&mut lookup, "AMADEUS_MEMORY_PER_PROCESS")?,
+			retries: var(&mut lookup, "AMADEUS_RETRIES")?,
+			pin_threads: var(&mut lookup, "AMADEUS_PIN_THREADS")?,
+		};
+		config.validate()?;
+		Ok(config)
+	}
+
+	/// Fill in the fields unset in `self` from `other`.
+	pub fn or(self, other: Self) -> Self {
+		Self {
+			processes: self.processes.or(other.processes),
+			threads_per_process: self.threads_per_process.or(other.threads_per_process),
+			tasks_per_core: self.tasks_per_core.or(other.tasks_per_core),
+			memory_per_process: self.memory_per_process.or(other.memory_per_process),
+			retries: self.retries.or(other.retries),
+			pin_threads: self.pin_threads.or(other.pin_threads),
+		}
+	}
+
+	/// The number of cores this process can use. This is the number of logical cores, limited
+	/// by any cgroup CPU quota, as set by container runtimes.
+	pub fn available_cores() -> usize {
+		let cores = num_cpus::get();
+		cgroup_quota().map_or(cores, |quota| cores.min(quota))
+	}
+
+	pub(super) fn validate(&self) -> io::Result<()> {
+		let fields = [
+			("processes", self.processes),
+			("threads_per_process", self.threads_per_process),
+			("tasks_per_core", self.tasks_per_core),
+		];
+		match fields.iter().find(|(_, value)| *value == Some(0)) {
+			Some((name, _)) => Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				format!("{} must be greater than zero", name),
+			)),
+			None => Ok(()),
+		}
+	}
+}
+
+fn var<T: FromStr>(
+	lookup: &mut impl FnMut(&str) -> Result<String, env::VarError>, name: &str,
+) -> io::Result<Option<T>> {
+	match lookup(name) {
+		Ok(value) => value.trim().parse().map(Some).map_err(|_| {
+			io::Error::new(
+				io::ErrorKind::InvalidInput,
+				format!("couldn't parse {}={:?}", name, value),
+			)
+		}),
+		Err(env::VarError::NotPresent) => Ok(None),
+		Err(err) => Err(io::Error::new(io::ErrorKind::InvalidInput, err)),
+	}
+}
+
+/// The CPU quota of this process's cgroup, rounded up to whole cores. Container runtimes mount
+/// the container's own cgroup at the root, so nested cgroups aren't looked for.
+#[cfg(target_os = "linux")]
+fn cgroup_quota() -> Option<usize> {
+	use std::{convert::TryFrom, fs};
+
+	let (quota, period) = if let Ok(max) = fs::read_to_string("/sys/fs/cgroup/cpu.max") {
+		// cgroup v2: "$MAX $PERIOD", where $MAX is "max" if unlimited
+		let mut max = max.split_whitespace();
+		(
+			max.next()?.parse::<u64>().ok()?,
+			max.next()?.parse::<u64>().ok()?,
+		)
+	} else {
+		// cgroup v1: the quota is -1 if unlimited
+		let read = |file| fs::read_to_string(format!("/sys/fs/cgroup/cpu/{}", file)).ok();
+		let quota = read("cpu.cfs_quota_us")?.trim().parse::<i64>().ok()?;
+		let period = read("cpu.cfs_period_us")?.trim().parse::<u64>().ok()?;
+		(
+			u64::try_from(quota).ok().filter(|&quota| quota > 0)?,
+			period,
+		)
+	};
+	if period == 0 {
+		return None;
+	}
+	usize::try_from(((quota + period - 1) / period).max(1)).ok()
+}
+#[cfg(not(target_os = "linux"))]
+fn cgroup_quota() -> Option<usize> {
+	None
+}
@@ -0,0 +1,143 @@
+//! A pool for reproducible tests.
+//!
+//! Tasks run one at a time on the current thread, each to completion when first polled, with
+//! randomized sinks seeded by the task's index, so a job gives the same output every run. Work and
+//! results sent to it as a process pool are serialized and deserialized, as they would be to send
+//! them to another process.
+
+use futures::{future, FutureExt};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_closure::traits;
+use std::{
+	collections::HashSet, future::Future, panic::{self, AssertUnwindSafe, RefUnwindSafe, UnwindSafe}, sync::{
+		atomic::{AtomicUsize, Ordering}, Arc
+	}
+};
+
+use amadeus_core::pool::{with_seeded_rng, ProcessSend};
+
+use super::util::{assert_sync_and_send, FutureExt1, Panicked};
+
+#[derive(Debug, Default)]
+struct DeterministicPoolInner {
+	spawned: AtomicUsize,
+	panics: HashSet<usize>,
+}
+
+/// A single-threaded pool that runs tasks in a fixed order, for reproducible tests.
+///
+/// It can be used as both a thread pool and a process pool. As a process pool, work and results
+/// are round-tripped through bincode, which catches types that misbehave when sent to another
+/// process without needing a cluster. Task failures can be tested by injecting panics
+/// with [`with_panics()`](Self::with_panics).
+#[derive(Clone, Debug, Default)]
+pub struct DeterministicPool(Arc<DeterministicPoolInner>);
+impl DeterministicPool {
+	pub fn new() -> Self {
+		Self::default()
+	}
+	/// A pool where the given tasks panic rather than running. Tasks are numbered from zero in
+	/// the order they're spawned, whether as process or thread tasks.
+	pub fn with_panics<I>(tasks: I) -> Self
+	where
+		I: IntoIterator<Item = usize>,
+	{
+		Self(Arc::new(DeterministicPoolInner {
+			spawned: AtomicUsize::new(0),
+			panics: tasks.into_iter().collect(),
+		}))
+	}
+	/// The number of tasks spawned so far.
+	pub fn spawned(&self) -> usize {
+		self.0.spawned.load(Ordering::SeqCst)
+	}
+	pub fn spawn<F, Fut, T>(&self, task: F) -> impl Future<Output = Result<T, Panicked>> + Send
+	where
+		F: FnOnce() -> Fut + Send + 'static,
+		Fut: Future<Output = T> + 'static,
+		T: Send + 'static,
+	{
+		#[allow(unsafe_code)]
+		unsafe {
+			self.spawn_unchecked(task)
+		}
+	}
+	#[allow(unsafe_code)]
+	pub unsafe fn spawn_unchecked<'a, F, Fut, T>(
+		&self, task: F,
+	) -> impl Future<Output = Result<T, Panicked>> + Send + 'a
+	where
+		F: FnOnce() -> Fut + Send + 'a,
+		Fut: Future<Output = T> + 'a,
+		T: Send + 'a,
+	{
+		let index = self.0.spawned.fetch_add(1, Ordering::SeqCst);
+		let inject = self.0.panics.contains(&index);
+		future::lazy(move |_| {
+			panic::catch_unwind(AssertUnwindSafe(|| {
+				assert!(!inject, "injected panic in task {}", index);
+				with_seeded_rng(index as u64, || task().block())
+			}))
+			.map_err(Panicked::from)
+		})
+	}
+}
+#[cfg_attr(not(nightly), serde_closure::desugar)]
+impl DeterministicPool {
+	/// Spawn `work` as if sending it to another process, round-tripping it and its result
+	/// through bincode.
+	pub fn spawn_process<F, Fut, T>(
+		&self, work: F,
+	) -> impl Future<Output = Result<T, Panicked>> + Send
+	where
+		F: traits::FnOnce(&Self) -> Fut + ProcessSend + 'static,
+		Fut: Future<Output = T> + 'static,
+		T: ProcessSend + 'static,
+	{
+		#[allow(unsafe_code)]
+		unsafe {
+			self.spawn_process_unchecked(work)
+		}
+	}
+	#[allow(unsafe_code)]
+	pub unsafe fn spawn_process_unchecked<'a, F, Fut, T>(
+		&self, work: F,
+	) -> impl Future<Output = Result<T, Panicked>> + Send + 'a
+	where
+		F: traits::FnOnce(&Self) -> Fut + ProcessSend + 'a,
+		Fut: Future<Output = T> + 'a,
+		T: ProcessSend + 'a,
+	{
+		let work = round_trip(work);
+		let self_ = self.clone();
+		self.spawn_unchecked(move || {
+			let work: F = work.unwrap_or_else(|err| panic!("{}", err));
+			work.call_once((&self_,))
+				.map(|ret| round_trip(ret).unwrap_or_else(|err| panic!("{}", err)))
+		})
+	}
+}
+
+/// Serialize and deserialize `t`, returning an error rather than panicking so that the panic
+/// happens in the task, where it's reported like any other.
+fn round_trip<T>(t: T) -> Result<T, String>
+where
+	T: Serialize + DeserializeOwned,
+{
+	bincode::serialize(&t)
+		.and_then(|bytes| bincode::deserialize(&bytes))
+		.map_err(|err| {
+			format!(
+				"couldn't round-trip {} through serde: {}",
+				std::any::type_name::<T>(),
+				err
+			)
+		})
+}
+
+impl UnwindSafe for DeterministicPool {}
+impl RefUnwindSafe for DeterministicPool {}
+
+fn _assert() {
+	let _ = assert_sync_and_send::<DeterministicPool>;
+}
@@ -0,0 +1,478 @@
+//! A process pool of workers on the local machine.
+//!
+//! Workers are spawned by re-running the current executable, which must call
+//! [`LocalProcessPool::init()`] at the start of `main`. They talk to the pool over a Unix socket,
+//! or a loopback TCP socket where those aren't available. As every worker runs the same binary, a closure is sent as its bincode-serialized
+//! captures alongside the offset of a function that knows how to deserialize and run it.
+
+use futures::{
+	channel::oneshot, future::{FutureExt, LocalBoxFuture}
+};
+use serde::{Deserialize, Serialize};
+use serde_closure::traits;
+use std::{
+	collections::{hash_map::RandomState, HashMap}, env, future::Future, hash::{BuildHasher, Hasher}, io::{self, BufReader, Write}, mem, panic::{self, AssertUnwindSafe, RefUnwindSafe, UnwindSafe}, process::{self, Child, Command, Stdio}, sync::{mpsc, Arc, Mutex}, thread, time::{Duration, Instant}
+};
+use tokio::task::{self, LocalSet};
+
+#[cfg(not(unix))]
+use std::net::{Ipv4Addr, TcpListener as Listener, TcpStream as Stream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener as Listener, UnixStream as Stream};
+
+use amadeus_core::pool::ProcessSend;
+
+use super::{
+	config::{DEFAULT_RETRIES, DEFAULT_TASKS_PER_CORE}, trace::{self, Records}, util::{assert_sync_and_send, LeastLoaded, Panicked}, PoolConfig, ThreadPool
+};
+
+const ADDR_VAR: &str = "AMADEUS_LOCAL_PROCESS_ADDR";
+const NONCE_VAR: &str = "AMADEUS_LOCAL_PROCESS_NONCE";
+/// How long a newly spawned worker has to connect before it's killed.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long workers have to exit once the pool is dropped before they're killed.
+const EXIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Runs the work in a worker, returning the serialized output.
+type Call = fn(&[u8], &ThreadPool) -> LocalBoxFuture<'static, Vec<u8>>;
+type Response = Result<Vec<u8>, Panicked>;
+
+#[derive(Serialize, Deserialize)]
+struct Request {
+	id: usize,
+	call: usize,
+	work: Vec<u8>,
+}
+
+/// Calls are sent as offsets from this function, as the binary may be loaded at a different
+/// address in each process.
+#[inline(never)]
+fn base() {}
+
+fn call<'a, F, Fut, T>(work: &[u8], thread_pool: &ThreadPool) -> LocalBoxFuture<'a, Vec<u8>>
+where
+	F: for<'b> traits::FnOnce<(&'b ThreadPool,), Output = Fut> + ProcessSend + 'a,
+	Fut: Future<Output = T> + 'a,
+	T: ProcessSend + 'a,
+{
+	let work: F = bincode::deserialize(work).unwrap();
+	trace::collect(|| work.call_once((thread_pool,)))
+		.map(|response| bincode::serialize(&response).unwrap())
+		.boxed_local()
+}
+
+/// Requests awaiting a response, which is `None` if the worker exited before sending it.
+#[derive(Debug, Default)]
+struct Pending {
+	requests: HashMap<usize, oneshot::Sender<Option<Response>>>,
+	next: usize,
+	exited: bool,
+}
+
+/// Listen on a new socket for a worker to connect to, returning it and its address.
+#[cfg(unix)]
+fn listen(nonce: u64) -> io::Result<(Listener, String)> {
+	let path = env::temp_dir().join(format!("amadeus-{}-{:x}.sock", process::id(), nonce));
+	let listener = Listener::bind(&path)?;
+	let addr = path
+		.into_os_string()
+		.into_string()
+		.map_err(|_| io::Error::new(io::ErrorKind::Other, "temp dir isn't valid UTF-8"))?;
+	Ok((listener, addr))
+}
+#[cfg(not(unix))]
+fn listen(_nonce: u64) -> io::Result<(Listener, String)> {
+	let listener = Listener::bind((Ipv4Addr::LOCALHOST, 0))?;
+	let addr = listener.local_addr()?.to_string();
+	Ok((listener, addr))
+}
+
+#[derive(Debug)]
+struct Process {
+	child: Mutex<Child>,
+	sender: Mutex<Stream>,
+	pending: Arc<Mutex<Pending>>,
+}
+/// Wait for the worker to connect, ignoring anything else that connects to the socket, and
+/// failing if it exits or doesn't connect within [`CONNECT_TIMEOUT`].
+fn accept(listener: &Listener, child: &mut Child, nonce: u64) -> io::Result<Stream> {
+	listener.set_nonblocking(true)?;
+	let deadline = Instant::now() + CONNECT_TIMEOUT;
+	loop {
+		match listener.accept() {
+			Ok((mut stream, _)) => {
+				stream.set_nonblocking(false)?;
+				stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+				if bincode::deserialize_from::<_, u64>(&mut stream).ok() == Some(nonce) {
+					stream.set_read_timeout(None)?;
+					break Ok(stream);
+				}
+			}
+			Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+				if let Some(status) = child.try_wait()? {
+					return Err(io::Error::new(
+						io::ErrorKind::Other,
+						format!(
+							"worker process exited before connecting ({}); LocalProcessPool::init() must be called at the start of main",
+							status
+						),
+					));
+				}
+				if Instant::now() >= deadline {
+					return Err(io::Error::new(
+						io::ErrorKind::TimedOut,
+						format!("worker process didn't connect within {:?}", CONNECT_TIMEOUT),
+					));
+				}
+				thread::sleep(Duration::from_millis(10));
+			}
+			Err(err) => return Err(err),
+		}
+	}
+}
+
+impl Process {
+	/// Spawn the `index`th process of a pool.
+	fn new(config: &PoolConfig, index: usize) -> io::Result<Self> {
+		let exe = env::current_exe()?;
+		let nonce = RandomState::new().build_hasher().finish();
+		let (listener, addr) = listen(nonce)?;
+		let child = Command::new(exe)
+			.env(ADDR_VAR, &addr)
+			.env(NONCE_VAR, nonce.to_string())
+			.stdin(Stdio::null())
+			.spawn();
+		let stream = child.and_then(|mut child| match accept(&listener, &mut child, nonce) {
+			Ok(stream) => Ok((stream, child)),
+			Err(err) => {
+				let _ = child.kill();
+				let _ = child.wait();
+				Err(err)
+			}
+		});
+		drop(listener);
+		#[cfg(unix)]
+		let _ = std::fs::remove_file(&addr);
+		let (mut stream, child) = stream?;
+		let first_core = index * config.threads_per_process.unwrap();
+		stream.write_all(&bincode::serialize(&(config, first_core)).unwrap())?;
+
+		let pending = Arc::new(Mutex::new(Pending::default()));
+		let reader = stream.try_clone()?;
+		let pid = child.id();
+		let pending_ = pending.clone();
+		let _ = thread::Builder::new()
+			.name(format!("amadeus-local-process-{}", pid))
+			.spawn(move || {
+				let mut reader = BufReader::new(reader);
+				while let Ok((id, response)) =
+					bincode::deserialize_from::<_, (usize, Response)>(&mut reader)
+				{
+					if let Some(sender) = pending_.lock().unwrap().requests.remove(&id) {
+						let _ = sender.send(Some(response));
+					}
+				}
+				let mut pending = pending_.lock().unwrap();
+				pending.exited = true;
+				for (_, sender) in pending.requests.drain() {
+					let _ = sender.send(None);
+				}
+			})?;
+
+		Ok(Self {
+			child: Mutex::new(child),
+			sender: Mutex::new(stream),
+			pending,
+		})
+	}
+	fn send(&self, call: usize, work: Vec<u8>) -> oneshot::Receiver<Option<Response>> {
+		let (sender, receiver) = oneshot::channel();
+		let mut pending = self.pending.lock().unwrap();
+		if pending.exited {
+			drop(pending);
+			let _ = sender.send(None);
+			return receiver;
+		}
+		let id = pending.next;
+		pending.next += 1;
+		let _ = pending.requests.insert(id, sender);
+		drop(pending);
+		let request = bincode::serialize(&Some(Request { id, call, work })).unwrap();
+		// If this fails the worker has exited, so fail the request rather than wait on the reader.
+		if self.sender.lock().unwrap().write_all(&request).is_err() {
+			if let Some(sender) = self.pending.lock().unwrap().requests.remove(&id) {
+				let _ = sender.send(None);
+			}
+		}
+		receiver
+	}
+	fn exited(&self) -> bool {
+		self.pending.lock().unwrap().exited
+	}
+}
+
+fn worker(addr: &str, nonce: u64) {
+	let mut stream = Stream::connect(addr).unwrap();
+	stream
+		.write_all(&bincode::serialize(&nonce).unwrap())
+		.unwrap();
+	let mut reader = BufReader::new(stream.try_clone().unwrap());
+	let (config, first_core): (PoolConfig, usize) = bincode::deserialize_from(&mut reader).unwrap();
+
+	// Requests are read eagerly so the pool is never blocked sending to a busy worker.
+	let (sender, receiver) = async_channel::unbounded();
+	let _ = thread::spawn(move || loop {
+		let request = bincode::deserialize_from::<_, Option<Request>>(&mut reader).unwrap_or(None);
+		let done = request.is_none();
+		if sender.try_send(request).is_err() || done {
+			break;
+		}
+	});
+	let (responses, responses_receiver) = mpsc::channel::<(usize, Response)>();
+	let _ = thread::spawn(move || {
+		for response in responses_receiver {
+			if stream
+				.write_all(&bincode::serialize(&response).unwrap())
+				.is_err()
+			{
+				break;
+			}
+		}
+	});
+
+	tokio::runtime::Builder::new()
+		.threaded_scheduler()
+		.enable_all()
+		.build()
+		.unwrap()
+		.block_on(LocalSet::new().run_until(async {
+			let thread_pool = ThreadPool::with_config_from_core(&config, first_core).unwrap();
+
+			while let Ok(Some(Request { id, call, work })) = receiver.recv().await {
+				#[allow(unsafe_code)]
+				let call = unsafe {
+					mem::transmute::<usize, Call>((base as *const () as usize).wrapping_add(call))
+				};
+				let ret = panic::catch_unwind(AssertUnwindSafe(|| call(&work, &thread_pool)));
+				let responses = responses.clone();
+				let _ = task::spawn_local(async move {
+					let ret: Response = match ret {
+						Ok(t) => AssertUnwindSafe(t).catch_unwind().await,
+						Err(e) => Err(e),
+					}
+					.map_err(Panicked::from);
+					let _ = responses.send((id, ret));
+				});
+			}
+		}));
+}
+
+#[derive(Debug)]
+struct LocalProcessPoolInner {
+	processes: Vec<Mutex<Arc<Process>>>,
+	config: PoolConfig,
+	load: LeastLoaded,
+}
+impl LocalProcessPoolInner {
+	/// The process at `index`, respawning it first if it has exited. If respawning fails the
+	/// exited process is returned, so that work sent to it fails.
+	///
+	/// Respawning blocks, so [`process_async`](Self::process_async) should be used from async code.
+	fn process(&self, index: usize) -> Arc<Process> {
+		let mut process = self.processes[index].lock().unwrap();
+		if process.exited() {
+			if let Ok(respawned) = Process::new(&self.config, index) {
+				let mut child = process.child.lock().unwrap();
+				let _ = child.kill();
+				let _ = child.wait();
+				drop(child);
+				*process = Arc::new(respawned);
+			}
+		}
+		process.clone()
+	}
+	/// As [`process`](Self::process), but respawning without blocking the executor.
+	async fn process_async(self: &Arc<Self>, index: usize) -> Arc<Process> {
+		let process = self.processes[index].lock().unwrap().clone();
+		if !process.exited() {
+			return process;
+		}
+		let self_ = self.clone();
+		task::spawn_blocking(move || self_.process(index))
+			.await
+			.unwrap()
+	}
+}
+impl Drop for LocalProcessPoolInner {
+	fn drop(&mut self) {
+		let shutdown = bincode::serialize(&None::<Request>).unwrap();
+		let processes = self
+			.processes
+			.iter()
+			.map(|process| process.lock().unwrap().clone())
+			.collect::<Vec<_>>();
+		for process in &processes {
+			let _ = process.sender.lock().unwrap().write_all(&shutdown);
+		}
+		// Give the workers a grace period to exit, then kill any that haven't.
+		let deadline = Instant::now() + EXIT_TIMEOUT;
+		for process in &processes {
+			let mut child = process.child.lock().unwrap();
+			while let Ok(None) = child.try_wait() {
+				if Instant::now() >= deadline {
+					let _ = child.kill();
+					let _ = child.wait();
+					break;
+				}
+				thread::sleep(Duration::from_millis(10));
+			}
+		}
+	}
+}
+
+/// A pool of worker processes on this machine.
+///
+/// This gives each worker its own heap and contains crashes: if a worker process dies it's
+/// respawned, and the work it was running is resent to another process up to
+/// [`PoolConfig::retries`] times before failing with a [`Panicked`] error, rather than taking
+/// down the whole program. It
+/// doesn't need a cluster, so it can also be used to run [`DistributedStream`](crate::dist::prelude::DistributedStream)s locally.
+///
+/// Workers are spawned by running the current executable again, so
+/// [`LocalProcessPool::init()`] must be called at the start of `main`.
+#[derive(Debug)]
+pub struct LocalProcessPool(Arc<LocalProcessPoolInner>);
+#[cfg_attr(not(nightly), serde_closure::desugar)]
+impl LocalProcessPool {
+	/// Spawn `processes` worker processes, by default one per available core, each running a
+	/// [`ThreadPool`] with `tasks_per_core`.
+	pub fn new(processes: Option<usize>, tasks_per_core: Option<usize>) -> io::Result<Self> {
+		Self::with_config(&PoolConfig {
+			processes,
+			tasks_per_core,
+			..PoolConfig::default()
+		})
+	}
+	/// Spawn `config.processes` worker processes, by default one per available core, with the
+	/// available cores split between them unless `config.threads_per_process` is set. If
+	/// `config.pin_threads` is set, each process's threads are pinned to cores of their own.
+	pub fn with_config(config: &PoolConfig) -> io::Result<Self> {
+		assert!(
+			env::var_os(ADDR_VAR).is_none(),
+			"LocalProcessPool::init() must be called at the start of main"
+		);
+		config.validate()?;
+		let cores = PoolConfig::available_cores();
+		let processes = config.processes.unwrap_or(cores);
+		let config = PoolConfig {
+			processes: Some(processes),
+			threads_per_process: Some(
+				config
+					.threads_per_process
+					.unwrap_or_else(|| (cores / processes).max(1)),
+			),
+			tasks_per_core: Some(config.tasks_per_core.unwrap_or(DEFAULT_TASKS_PER_CORE)),
+			memory_per_process: config.memory_per_process,
+			retries: Some(config.retries.unwrap_or(DEFAULT_RETRIES)),
+			pin_threads: Some(config.pin_threads.unwrap_or(false)),
+		};
+		let processes = (0..processes)
+			.map(|index| Process::new(&config, index).map(|process| Mutex::new(Arc::new(process))))
+			.collect::<io::Result<Vec<_>>>()?;
+		let load = LeastLoaded::new(processes.len());
+		Ok(Self(Arc::new(LocalProcessPoolInner {
+			processes,
+			config,
+			load,
+		})))
+	}
+	/// Run as a worker process if this process was spawned by a [`LocalProcessPool`], exiting
+	/// once the pool is dropped. Otherwise this returns immediately.
+	///
+	/// This must be called at the start of `main`.
+	pub fn init() {
+		let (addr, nonce) = match (env::var(ADDR_VAR), env::var(NONCE_VAR)) {
+			(Ok(addr), Ok(nonce)) => (addr, nonce),
+			_ => return,
+		};
+		worker(&addr, nonce.parse().unwrap());
+		process::exit(0);
+	}
+	/// The configuration this pool resolved to.
+	pub fn config(&self) -> PoolConfig {
+		self.0.config
+	}
+	pub fn processes(&self) -> usize {
+		self.0.processes.len()
+	}
+	pub fn spawn<F, Fut, T>(&self, work: F) -> impl Future<Output = Result<T, Panicked>> + Send
+	where
+		F: traits::FnOnce(&ThreadPool) -> Fut + ProcessSend + 'static,
+		Fut: Future<Output = T> + 'static,
+		T: ProcessSend + 'static,
+	{
+		#[allow(unsafe_code)]
+		unsafe {
+			self.spawn_unchecked(work)
+		}
+	}
+	#[allow(unsafe_code)]
+	pub unsafe fn spawn_unchecked<'a, F, Fut, T>(
+		&self, work: F,
+	) -> impl Future<Output = Result<T, Panicked>> + Send + 'a
+	where
+		F: traits::FnOnce(&ThreadPool) -> Fut + ProcessSend + 'a,
+		Fut: Future<Output = T> + 'a,
+		T: ProcessSend + 'a,
+	{
+		let call = call::<F, Fut, T> as fn(&[u8], &ThreadPool) -> LocalBoxFuture<'a, Vec<u8>>;
+		let call = (call as *const () as usize).wrapping_sub(base as *const () as usize);
+		// Kept serialized so it can be resent if the process running it exits.
+		let work = bincode::serialize(&work).unwrap();
+		let inner = self.0.clone();
+		async move {
+			let attempts = inner.config.retries.unwrap() + 1;
+			let (mut attempt, mut avoid) = (1, None);
+			loop {
+				let (index, in_flight) = inner.load.get(avoid);
+				let process = inner.process_async(index).await;
+				let pid = process.child.lock().unwrap().id();
+				// A dropped request means the worker is gone, as when it exits before responding.
+				let response = process.send(call, work.clone()).await.unwrap_or(None);
+				drop(in_flight);
+				match response {
+					Some(response) => {
+						break response.map(|response| {
+							let (response, records): (T, Records) =
+								bincode::deserialize(&response).unwrap();
+							trace::replay(&pid, records);
+							response
+						})
+					}
+					None if attempt < attempts => {
+						attempt += 1;
+						avoid = Some(index);
+					}
+					None => break Err(Panicked::exited(pid, attempt, attempts)),
+				}
+			}
+		}
+	}
+}
+
+impl Clone for LocalProcessPool {
+	/// Cloning a pool will create a new handle to the pool.
+	/// The behavior is similar to [Arc](https://doc.rust-lang.org/stable/std/sync/struct.Arc.html).
+	///
+	/// We could for example submit jobs from multiple threads concurrently.
+	fn clone(&self) -> Self {
+		Self(self.0.clone())
+	}
+}
+
+impl UnwindSafe for LocalProcessPool {}
+impl RefUnwindSafe for LocalProcessPool {}
+
+fn _assert() {
+	let _ = assert_sync_and_send::<LocalProcessPool>;
+}
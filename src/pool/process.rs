@@ -3,62 +3,19 @@ use futures::{future::LocalBoxFuture, FutureExt};
 use serde_closure::{traits, FnOnce};
 use serde_traitobject as st;
 use std::{
-	any, collections::VecDeque, fmt, future::Future, mem, panic::{self, RefUnwindSafe, UnwindSafe}, sync::{Arc, Mutex}
+	any, collections::VecDeque, fmt, future::Future, io, mem, panic::{self, RefUnwindSafe, UnwindSafe}, sync::{Arc, Mutex}
 };
 
 use amadeus_core::pool::ProcessSend;
 
 use super::{
-	util::{assert_sync_and_send, OnDrop, Panicked, RoundRobin, Synchronize}, ThreadPool
+	config::{DEFAULT_RETRIES, DEFAULT_TASKS_PER_CORE}, trace::{self, Records}, util::{assert_sync_and_send, FutureExt1, LeastLoaded, OnDrop, Panicked, Synchronize}, PoolConfig, ThreadPool
 };
 
 #[cfg_attr(not(nightly), serde_closure::desugar)]
 type Request = st::Box<dyn st::sc::FnOnce(&ThreadPool) -> LocalBoxFuture<'static, Response> + Send>;
 type Response = Box<dyn st::Any + Send>;
 
-mod future_ext {
-	use futures::{future::Future, pin_mut};
-	use std::{
-		sync::Arc, task::{Context, Poll}, thread::{self, Thread}
-	};
-
-	/// Extension trait to provide convenient [`block()`](FutureExt1::block) method on futures.
-	///
-	/// Named `FutureExt1` to avoid clashing with [`futures::future::FutureExt`].
-	pub(crate) trait FutureExt1: Future {
-		/// Convenience method over `futures::executor::block_on(future)`.
-		fn block(self) -> Self::Output
-		where
-			Self: Sized,
-		{
-			// futures::executor::block_on(self) // Not reentrant for some reason
-			struct ThreadNotify {
-				thread: Thread,
-			}
-			impl futures::task::ArcWake for ThreadNotify {
-				fn wake_by_ref(arc_self: &Arc<Self>) {
-					arc_self.thread.unpark();
-				}
-			}
-			let f = self;
-			pin_mut!(f);
-			let thread_notify = Arc::new(ThreadNotify {
-				thread: thread::current(),
-			});
-			let waker = futures::task::waker_ref(&thread_notify);
-			let mut cx = Context::from_waker(&waker);
-			loop {
-				if let Poll::Ready(t) = f.as_mut().poll(&mut cx) {
-					return t;
-				}
-				thread::park();
-			}
-		}
-	}
-	impl<T: ?Sized> FutureExt1 for T where T: Future {}
-}
-use future_ext::FutureExt1;
-
 #[derive(Debug)]
 struct Process {
 	sender: Sender<Option<Request>>,
@@ -66,10 +23,26 @@ struct Process {
 	inner: Mutex<ProcessInner>,
 	synchronize: Synchronize,
 }
+/// The responses awaited, each of which is `None` if the process exited before sending it.
 struct ProcessInner {
-	queue: VecDeque<Queued<Result<Response, Panicked>>>,
+	queue: VecDeque<Queued<Option<Result<Response, Panicked>>>>,
 	received: usize,
 	tail: usize,
+	exited: bool,
+}
+impl ProcessInner {
+	/// Pop the responses at the front of the queue that have been taken, or whose requests were
+	/// abandoned by dropping `send`, once they've been received.
+	fn pop_taken(&mut self) {
+		while self.tail < self.received {
+			if let Some(Queued::Taken) = self.queue.front() {
+				let _ = self.queue.pop_front().unwrap();
+				self.tail += 1;
+			} else {
+				break;
+			}
+		}
+	}
 }
 impl fmt::Debug for ProcessInner {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -77,6 +50,7 @@ impl fmt::Debug for ProcessInner {
 			.field("queue", &())
 			.field("received", &self.received)
 			.field("tail", &self.tail)
+			.field("exited", &self.exited)
 			.finish()
 	}
 }
@@ -105,144 +79,226 @@ impl<T> Queued<T> {
 	}
 }
 
-#[derive(Debug)]
-struct ProcessPoolInner {
-	processes: Vec<Process>,
-	i: RoundRobin,
-}
-impl ProcessPoolInner {
+impl Process {
 	#[allow(clippy::double_parens)] // TODO: work out what's triggering this
-	fn new(
-		processes: Option<usize>, tasks_per_core: Option<usize>, resources: Resources,
-	) -> Result<Self, SpawnError> {
-		let processes = processes.unwrap_or(3); // TODO!
-		let mut processes_vec = Vec::with_capacity(processes);
-		for _ in 0..processes {
-			let child = spawn(
-				resources,
-				FnOnce!(move |parent| {
-					tokio::runtime::Builder::new()
-						.threaded_scheduler()
-						.enable_all()
-						.build()
-						.unwrap()
-						.block_on(async {
-							let receiver = Receiver::<Option<Request>>::new(parent);
-							let sender = Sender::<Result<Response, Panicked>>::new(parent);
+	async fn spawn(config: &PoolConfig, resources: Resources) -> Result<Self, SpawnError> {
+		let config = *config;
+		let child = spawn(
+			resources,
+			FnOnce!(move |parent| {
+				tokio::runtime::Builder::new()
+					.threaded_scheduler()
+					.enable_all()
+					.build()
+					.unwrap()
+					.block_on(async {
+						let receiver = Receiver::<Option<Request>>::new(parent);
+						let sender = Sender::<Result<Response, Panicked>>::new(parent);
 
-							let thread_pool = ThreadPool::new(tasks_per_core).unwrap();
+						let thread_pool = ThreadPool::with_config(&config).unwrap();
 
-							while let Some(work) = receiver.recv().await.unwrap() {
-								let ret = panic::catch_unwind(panic::AssertUnwindSafe(|| {
-									work.into_box().call_once_box((&thread_pool,))
-								}));
-								let ret = match ret {
-									Ok(t) => panic::AssertUnwindSafe(t).catch_unwind().await,
-									Err(e) => Err(e),
-								}
-								.map_err(Panicked::from);
-								sender.send(ret).await;
+						while let Some(work) = receiver.recv().await.unwrap() {
+							let ret = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+								work.into_box().call_once_box((&thread_pool,))
+							}));
+							let ret = match ret {
+								Ok(t) => panic::AssertUnwindSafe(t).catch_unwind().await,
+								Err(e) => Err(e),
 							}
-						})
-				}),
-			)
-			.block();
-			if let Err(err) = child {
-				for Process { sender, .. } in processes_vec {
-					// TODO: select
-					sender.send(None).block();
-				}
-				return Err(err);
-			}
-			let child = child.unwrap();
+							.map_err(Panicked::from);
+							sender.send(ret).await;
+						}
+					})
+			}),
+		)
+		.await?;
 
-			let sender = Sender::new(child);
-			let receiver = Receiver::new(child);
+		let sender = Sender::new(child);
+		let receiver = Receiver::new(child);
 
-			let (queue, received, tail) = (VecDeque::new(), 0, 0);
+		let (queue, received, tail, exited) = (VecDeque::new(), 0, 0, false);
 
-			processes_vec.push(Process {
-				sender,
-				receiver,
-				inner: Mutex::new(ProcessInner {
-					queue,
-					received,
-					tail,
-				}),
-				synchronize: Synchronize::new(),
-			})
-		}
-		let i = RoundRobin::new(0, processes_vec.len());
 		Ok(Self {
-			processes: processes_vec,
-			i,
+			sender,
+			receiver,
+			inner: Mutex::new(ProcessInner {
+				queue,
+				received,
+				tail,
+				exited,
+			}),
+			synchronize: Synchronize::new(),
 		})
 	}
-	fn processes(&self) -> usize {
-		self.processes.len()
+	fn exited(&self) -> bool {
+		self.inner.lock().unwrap().exited
 	}
-	async fn spawn<F, Fut, T>(&self, work: F) -> Result<T, Panicked>
-	where
-		F: for<'a> traits::FnOnce<(&'a ThreadPool,), Output = Fut> + ProcessSend + 'static,
-		Fut: Future<Output = T> + 'static,
-		T: ProcessSend + 'static,
-	{
-		let process_index = self.i.get();
-		let process = &self.processes[process_index];
-		let x = process
-			.sender
-			.send(Some(st::Box::new(FnOnce!(move |thread_pool: &_| {
-				let work: F = work;
-				work.call_once((thread_pool,))
-					.map(|res| Box::new(res) as Response)
-					.boxed_local()
-			})) as Request));
-		x.await;
+	/// Send `request` and await its response, or `None` if the process exits first.
+	async fn send(&self, request: Request) -> Option<Result<Response, Panicked>> {
+		if self.exited() {
+			return None;
+		}
+		self.sender.send(Some(request)).await;
 		let index;
 		{
 			// https://github.com/rust-lang/rust/issues/57478
-			let mut process_inner_lock = process.inner.lock().unwrap();
+			let mut process_inner_lock = self.inner.lock().unwrap();
 			process_inner_lock.queue.push_back(Queued::Awaiting);
 			index = process_inner_lock.tail + process_inner_lock.queue.len() - 1;
 			drop(process_inner_lock);
 		}
 		let on_drop = OnDrop::new(|| {
-			let mut process_inner_lock = process.inner.lock().unwrap();
+			let mut process_inner_lock = self.inner.lock().unwrap();
 			let offset = index - process_inner_lock.tail;
 			process_inner_lock.queue[offset].drop_();
-			while let Some(Queued::Taken) = process_inner_lock.queue.front() {
-				let _ = process_inner_lock.queue.pop_front().unwrap();
-				process_inner_lock.tail += 1;
-			}
+			process_inner_lock.pop_taken();
 			drop(process_inner_lock);
 		});
-		while process.inner.lock().unwrap().received <= index {
-			process
-				.synchronize
+		while self.inner.lock().unwrap().received <= index {
+			self.synchronize
 				.synchronize(async {
-					if process.inner.lock().unwrap().received > index {
+					if self.inner.lock().unwrap().received > index {
 						return;
 					}
-					let z = process.receiver.recv().await;
-					let t = z.unwrap();
-					let mut process_inner_lock = process.inner.lock().unwrap();
-					let offset = process_inner_lock.received - process_inner_lock.tail;
-					process_inner_lock.queue[offset].received(t);
-					process_inner_lock.received += 1;
+					let z = self.receiver.recv().await;
+					let mut process_inner_lock = self.inner.lock().unwrap();
+					if let Ok(t) = z {
+						let offset = process_inner_lock.received - process_inner_lock.tail;
+						process_inner_lock.queue[offset].received(Some(t));
+						process_inner_lock.received += 1;
+					} else {
+						// The process exited, so nothing more will be received.
+						process_inner_lock.exited = true;
+						let end = process_inner_lock.tail + process_inner_lock.queue.len();
+						while process_inner_lock.received < end {
+							let offset = process_inner_lock.received - process_inner_lock.tail;
+							process_inner_lock.queue[offset].received(None);
+							process_inner_lock.received += 1;
+						}
+					}
+					// Responses to requests whose senders have gone are discarded.
+					process_inner_lock.pop_taken();
 					drop(process_inner_lock);
 				})
 				.await;
 		}
 		on_drop.cancel();
-		let mut process_inner_lock = process.inner.lock().unwrap();
+		let mut process_inner_lock = self.inner.lock().unwrap();
 		let offset = index - process_inner_lock.tail;
 		let boxed = process_inner_lock.queue[offset].take();
-		while let Some(Queued::Taken) = process_inner_lock.queue.front() {
-			let _ = process_inner_lock.queue.pop_front().unwrap();
-			process_inner_lock.tail += 1;
-		}
+		process_inner_lock.pop_taken();
 		drop(process_inner_lock);
-		boxed.map(|boxed| *Box::<dyn any::Any>::downcast::<T>(boxed.into_any_send()).unwrap())
+		boxed
+	}
+}
+impl Drop for Process {
+	fn drop(&mut self) {
+		if !self.exited() {
+			// TODO: select, incl recv
+			self.sender.send(None).block();
+		}
+	}
+}
+
+#[derive(Debug)]
+struct ProcessPoolInner {
+	processes: Vec<Mutex<Arc<Process>>>,
+	config: PoolConfig,
+	resources: Resources,
+	load: LeastLoaded,
+}
+impl ProcessPoolInner {
+	fn new(config: &PoolConfig, mut resources: Resources) -> Result<Self, SpawnError> {
+		// The size of the cluster isn't known, so default to a small number of processes.
+		let processes = config.processes.unwrap_or(3);
+		resources.mem = config.memory_per_process.unwrap_or(resources.mem);
+		// Threads per process are left to be resolved by each worker from its own cores.
+		let config = PoolConfig {
+			processes: Some(processes),
+			threads_per_process: config.threads_per_process,
+			tasks_per_core: Some(config.tasks_per_core.unwrap_or(DEFAULT_TASKS_PER_CORE)),
+			memory_per_process: Some(resources.mem),
+			retries: Some(config.retries.unwrap_or(DEFAULT_RETRIES)),
+			pin_threads: None,
+		};
+		let processes = (0..processes)
+			.map(|_| {
+				Process::spawn(&config, resources)
+					.block()
+					.map(|process| Mutex::new(Arc::new(process)))
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+		let load = LeastLoaded::new(processes.len());
+		Ok(Self {
+			processes,
+			config,
+			resources,
+			load,
+		})
+	}
+	fn processes(&self) -> usize {
+		self.processes.len()
+	}
+	/// The process at `index`, respawning it first if it has exited. If respawning fails the
+	/// exited process is returned, so that work sent to it fails.
+	async fn process(&self, index: usize) -> Arc<Process> {
+		let process = self.processes[index].lock().unwrap().clone();
+		if !process.exited() {
+			return process;
+		}
+		if let Ok(respawned) = Process::spawn(&self.config, self.resources).await {
+			let mut slot = self.processes[index].lock().unwrap();
+			// Another caller may have respawned it meanwhile, in which case ours is shut down.
+			if Arc::ptr_eq(&*slot, &process) {
+				*slot = Arc::new(respawned);
+			}
+			return slot.clone();
+		}
+		process
+	}
+	/// Send the request made by `request` to the least loaded process, resending it to another
+	/// if the process exits, up to `config.retries` times.
+	async fn send(&self, request: impl Fn() -> Request) -> Result<(usize, Response), Panicked> {
+		let attempts = self.config.retries.unwrap() + 1;
+		let (mut attempt, mut avoid) = (1, None);
+		loop {
+			let (process_index, in_flight) = self.load.get(avoid);
+			let process = self.process(process_index).await;
+			let response = process.send(request()).await;
+			drop(in_flight);
+			match response {
+				Some(response) => break response.map(|response| (process_index, response)),
+				None if attempt < attempts => {
+					attempt += 1;
+					avoid = Some(process_index);
+				}
+				None => break Err(Panicked::exited(process_index, attempt, attempts)),
+			}
+		}
+	}
+	async fn spawn<F, Fut, T>(&self, work: F) -> Result<T, Panicked>
+	where
+		F: for<'a> traits::FnOnce<(&'a ThreadPool,), Output = Fut> + ProcessSend + 'static,
+		Fut: Future<Output = T> + 'static,
+		T: ProcessSend + 'static,
+	{
+		// Kept serialized so it can be resent if the process running it exits.
+		let work = bincode::serialize(&work).unwrap();
+		let request = || {
+			let work: F = bincode::deserialize(&work).unwrap();
+			st::Box::new(FnOnce!(move |thread_pool: &_| {
+				let work: F = work;
+				trace::collect(|| work.call_once((thread_pool,)))
+					.map(|res| Box::new(res) as Response)
+					.boxed_local()
+			})) as Request
+		};
+		self.send(request).await.map(|(process_index, boxed)| {
+			let (response, records) =
+				*Box::<dyn any::Any>::downcast::<(T, Records)>(boxed.into_any_send()).unwrap();
+			trace::replay(&process_index, records);
+			response
+		})
 	}
 	#[allow(unsafe_code)]
 	async unsafe fn spawn_unchecked<'a, F, Fut, T>(&self, work: F) -> Result<T, Panicked>
@@ -251,96 +307,77 @@ impl ProcessPoolInner {
 		Fut: Future<Output = T> + 'a,
 		T: ProcessSend + 'a,
 	{
-		let process_index = self.i.get();
-		let process = &self.processes[process_index];
-		let request = st::Box::new(FnOnce!(move |thread_pool: &_| {
-			let work: F = work;
-			work.call_once((thread_pool,))
-				.map(|response| {
-					let response = bincode::serialize(&response).unwrap();
-					Box::new(response) as Response
-				})
-				.boxed_local()
-		}));
-		let request = mem::transmute::<
-			st::Box<dyn st::sc::FnOnce(&ThreadPool) -> LocalBoxFuture<'a, Response> + Send>,
-			st::Box<dyn st::sc::FnOnce(&ThreadPool) -> LocalBoxFuture<'static, Response> + Send>,
-		>(request);
-		let x = process.sender.send(Some(request));
-		x.await;
-		let index;
-		{
-			// https://github.com/rust-lang/rust/issues/57478
-			let mut process_inner_lock = process.inner.lock().unwrap();
-			process_inner_lock.queue.push_back(Queued::Awaiting);
-			index = process_inner_lock.tail + process_inner_lock.queue.len() - 1;
-			drop(process_inner_lock);
-		}
-		let on_drop = OnDrop::new(|| {
-			let mut process_inner_lock = process.inner.lock().unwrap();
-			let offset = index - process_inner_lock.tail;
-			process_inner_lock.queue[offset].drop_();
-			while let Some(Queued::Taken) = process_inner_lock.queue.front() {
-				let _ = process_inner_lock.queue.pop_front().unwrap();
-				process_inner_lock.tail += 1;
-			}
-			drop(process_inner_lock);
-		});
-		while process.inner.lock().unwrap().received <= index {
-			process
-				.synchronize
-				.synchronize(async {
-					if process.inner.lock().unwrap().received > index {
-						return;
-					}
-					let z = process.receiver.recv().await;
-					let t = z.unwrap();
-					let mut process_inner_lock = process.inner.lock().unwrap();
-					let offset = process_inner_lock.received - process_inner_lock.tail;
-					process_inner_lock.queue[offset].received(t);
-					process_inner_lock.received += 1;
-					drop(process_inner_lock);
-				})
-				.await;
-		}
-		on_drop.cancel();
-		let mut process_inner_lock = process.inner.lock().unwrap();
-		let offset = index - process_inner_lock.tail;
-		let boxed = process_inner_lock.queue[offset].take();
-		while let Some(Queued::Taken) = process_inner_lock.queue.front() {
-			let _ = process_inner_lock.queue.pop_front().unwrap();
-			process_inner_lock.tail += 1;
-		}
-		drop(process_inner_lock);
-		boxed.map(|boxed| {
-			bincode::deserialize(
+		// Kept serialized so it can be resent if the process running it exits.
+		let work = bincode::serialize(&work).unwrap();
+		let request = || {
+			let work: F = bincode::deserialize(&work).unwrap();
+			let request = st::Box::new(FnOnce!(move |thread_pool: &_| {
+				let work: F = work;
+				trace::collect(|| work.call_once((thread_pool,)))
+					.map(|response| {
+						let response = bincode::serialize(&response).unwrap();
+						Box::new(response) as Response
+					})
+					.boxed_local()
+			}));
+			mem::transmute::<
+				st::Box<dyn st::sc::FnOnce(&ThreadPool) -> LocalBoxFuture<'a, Response> + Send>,
+				st::Box<
+					dyn st::sc::FnOnce(&ThreadPool) -> LocalBoxFuture<'static, Response> + Send,
+				>,
+			>(request)
+		};
+		self.send(request).await.map(|(process_index, boxed)| {
+			let (response, records): (T, Records) = bincode::deserialize(
 				&Box::<dyn any::Any>::downcast::<Vec<u8>>(boxed.into_any_send()).unwrap(),
 			)
-			.unwrap()
+			.unwrap();
+			trace::replay(&process_index, records);
+			response
 		})
 	}
 }
-impl Drop for ProcessPoolInner {
-	fn drop(&mut self) {
-		for Process { sender, .. } in &self.processes {
-			// TODO: select, incl recv
-			sender.send(None).block();
-		}
-	}
-}
 
 #[derive(Debug)]
 pub struct ProcessPool(Arc<ProcessPoolInner>);
 #[cfg_attr(not(nightly), serde_closure::desugar)]
 impl ProcessPool {
+	/// Spawn `processes` worker processes, by default 3, each running a [`ThreadPool`] with
+	/// `tasks_per_core`.
+	///
+	/// # Panics
+	///
+	/// If `processes` or `tasks_per_core` is zero.
 	pub fn new(
 		processes: Option<usize>, tasks_per_core: Option<usize>, resources: Resources,
 	) -> Result<Self, SpawnError> {
-		Ok(Self(Arc::new(ProcessPoolInner::new(
+		let config = PoolConfig {
 			processes,
 			tasks_per_core,
-			resources,
-		)?)))
+			..PoolConfig::default()
+		};
+		config
+			.validate()
+			.unwrap_or_else(|err| panic!("Amadeus: {}", err));
+		Ok(Self(Arc::new(ProcessPoolInner::new(&config, resources)?)))
+	}
+	/// Spawn `config.processes` worker processes, by default 3, each reserving
+	/// `config.memory_per_process` if set and `resources` otherwise. Processes that exit are
+	/// respawned, and the work they were running resent to another up to `config.retries` times.
+	pub fn with_config(config: &PoolConfig, resources: Resources) -> io::Result<Self> {
+		config.validate()?;
+		let inner = ProcessPoolInner::new(config, resources).map_err(|err| {
+			io::Error::new(
+				io::ErrorKind::Other,
+				format!("couldn't spawn worker process: {:?}", err),
+			)
+		})?;
+		Ok(Self(Arc::new(inner)))
+	}
+	/// The configuration this pool resolved to. `threads_per_process` is only set if it was
+	/// configured, as otherwise each worker resolves it from its own cores.
+	pub fn config(&self) -> PoolConfig {
+		self.0.config
 	}
 	pub fn processes(&self) -> usize {
 		self.0.processes()
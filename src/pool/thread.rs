@@ -11,14 +11,15 @@ use {
 	futures::{future, FutureExt}, std::panic::AssertUnwindSafe
 };
 
-use super::util::{assert_sync_and_send, Panicked};
-
-const DEFAULT_TASKS_PER_CORE: usize = 100;
+use super::{
+	config::DEFAULT_TASKS_PER_CORE, util::{assert_sync_and_send, Panicked}, PoolConfig
+};
 
 #[derive(Debug)]
 struct ThreadPoolInner {
 	logical_cores: usize,
 	tasks_per_core: usize,
+	pin_threads: bool,
 	#[cfg(not(target_arch = "wasm32"))]
 	pool: Pool,
 }
@@ -26,22 +27,67 @@ struct ThreadPoolInner {
 #[derive(Debug)]
 pub struct ThreadPool(Arc<ThreadPoolInner>);
 impl ThreadPool {
+	/// Create a pool with a thread per available core, each running `tasks_per_core` tasks.
 	pub fn new(tasks_per_core: Option<usize>) -> io::Result<Self> {
+		Self::with_config(&PoolConfig {
+			tasks_per_core,
+			..PoolConfig::default()
+		})
+	}
+	/// Create a pool with `config.threads_per_process` threads, by default one per available
+	/// core, each running `config.tasks_per_core` tasks.
+	pub fn with_config(config: &PoolConfig) -> io::Result<Self> {
+		Self::with_config_from_core(config, 0)
+	}
+	/// As `with_config`, but pinning the threads, if `config.pin_threads` is set, to the
+	/// available cores from the `first_core`th on, so that the processes on a machine can be
+	/// given cores of their own.
+	#[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+	pub(crate) fn with_config_from_core(
+		config: &PoolConfig, first_core: usize,
+	) -> io::Result<Self> {
+		config.validate()?;
 		let logical_cores = if !cfg!(target_arch = "wasm32") {
-			num_cpus::get()
+			config
+				.threads_per_process
+				.unwrap_or_else(PoolConfig::available_cores)
 		} else {
 			1
 		};
-		let tasks_per_core = tasks_per_core.unwrap_or(DEFAULT_TASKS_PER_CORE);
+		let tasks_per_core = config.tasks_per_core.unwrap_or(DEFAULT_TASKS_PER_CORE);
+		let pin_threads = config.pin_threads.unwrap_or(false);
 		#[cfg(not(target_arch = "wasm32"))]
-		let pool = Pool::new(logical_cores);
+		let pool = {
+			let cores = if pin_threads {
+				core_affinity::get_core_ids()
+			} else {
+				None
+			};
+			Pool::new(logical_cores, |i| {
+				cores
+					.as_ref()
+					.map(|cores| cores[(first_core + i) % cores.len()])
+			})
+		};
 		Ok(ThreadPool(Arc::new(ThreadPoolInner {
 			logical_cores,
 			tasks_per_core,
+			pin_threads,
 			#[cfg(not(target_arch = "wasm32"))]
 			pool,
 		})))
 	}
+	/// The configuration this pool resolved to.
+	pub fn config(&self) -> PoolConfig {
+		PoolConfig {
+			processes: Some(1),
+			threads_per_process: Some(self.0.logical_cores),
+			tasks_per_core: Some(self.0.tasks_per_core),
+			memory_per_process: None,
+			retries: None,
+			pin_threads: Some(self.0.pin_threads),
+		}
+	}
 	pub fn threads(&self) -> usize {
 		self.0.logical_cores * self.0.tasks_per_core
 	}
@@ -51,6 +97,8 @@ impl ThreadPool {
 		Fut: Future<Output = T> + 'static,
 		T: Send + 'static,
 	{
+		#[cfg(feature = "trace")]
+		let task = with_current_subscriber(task);
 		#[cfg(not(target_arch = "wasm32"))]
 		return self
 			.0
@@ -78,6 +126,8 @@ impl ThreadPool {
 		Fut: Future<Output = T> + 'a,
 		T: Send + 'a,
 	{
+		#[cfg(feature = "trace")]
+		let task = with_current_subscriber(task);
 		#[cfg(not(target_arch = "wasm32"))]
 		return Guard::new(
 			self.0
@@ -122,6 +172,20 @@ impl Clone for ThreadPool {
 	}
 }
 
+/// Have `task` record to the subscriber that's current here, rather than that of whichever thread
+/// runs it. This is how the tasks of work run in a worker process are collected.
+#[cfg(feature = "trace")]
+fn with_current_subscriber<F, Fut>(
+	task: F,
+) -> impl FnOnce() -> tracing::instrument::WithDispatch<Fut> + Send
+where
+	F: FnOnce() -> Fut + Send,
+{
+	use tracing::{dispatcher, instrument::WithSubscriber};
+	let dispatch = dispatcher::get_default(Clone::clone);
+	move || dispatcher::with_default(&dispatch, task).with_subscriber(dispatch.clone())
+}
+
 impl UnwindSafe for ThreadPool {}
 impl RefUnwindSafe for ThreadPool {}
 
@@ -166,8 +230,9 @@ fn _assert() {
 #[cfg(not(target_arch = "wasm32"))]
 mod pool {
 	use async_channel::{bounded, Sender};
+	use core_affinity::CoreId;
 	use futures::{future::RemoteHandle, FutureExt};
-	use std::{any::Any, future::Future, mem, panic::AssertUnwindSafe, pin::Pin};
+	use std::{any::Any, future::Future, mem, panic::AssertUnwindSafe, pin::Pin, thread};
 	use tokio::{
 		runtime::Handle, task::{JoinError, LocalSet}
 	};
@@ -180,14 +245,19 @@ mod pool {
 		sender: Sender<(Request, Sender<RemoteHandle<Response>>)>,
 	}
 	impl Pool {
-		pub(super) fn new(threads: usize) -> Self {
+		/// Spawn `threads` threads, pinning the `i`th to `core(i)` if it's given.
+		pub(super) fn new(threads: usize, core: impl Fn(usize) -> Option<CoreId>) -> Self {
 			let handle = Handle::current();
 			let handle1 = handle.clone();
 			let (sender, receiver) = bounded::<(Request, Sender<RemoteHandle<Response>>)>(1);
-			for _ in 0..threads {
+			for i in 0..threads {
 				let receiver = receiver.clone();
 				let handle = handle.clone();
-				let _ = handle1.spawn_blocking(move || {
+				let core = core(i);
+				let run = move || {
+					if let Some(core) = core {
+						let _ = core_affinity::set_for_current(core);
+					}
 					let local = LocalSet::new();
 					handle.block_on(local.run_until(async {
 						while let Ok((task, sender)) = receiver.recv().await {
@@ -198,7 +268,13 @@ mod pool {
 							});
 						}
 					}))
-				});
+				};
+				if core.is_some() {
+					// A thread of its own, so the pinning doesn't outlive the pool.
+					let _ = thread::spawn(run);
+				} else {
+					let _ = handle1.spawn_blocking(run);
+				}
 			}
 			Self { sender }
 		}
@@ -280,7 +356,7 @@ mod pool {
 			const TASKS: usize = 1000;
 			const ITERS: usize = 200;
 			const THREADS: usize = 4;
-			let pool = Pool::new(THREADS);
+			let pool = Pool::new(THREADS, |_| None);
 			let count = Arc::new(AtomicUsize::new((1..TASKS).sum()));
 			for _ in 0..ITERS {
 				join_all((0..TASKS).map(|i| {
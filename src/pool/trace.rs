@@ -0,0 +1,269 @@
+//! Carries the spans and events of work run in a worker process back to the driver, with the
+//! `trace` feature.
+//!
+//! Work is run with [`collect()`], under a subscriber of its own that gathers everything recorded
+//! while it runs, including in the tasks it spawns, as thread pools run tasks under the subscriber
+//! of whatever spawned them. Anything else in the worker goes to whichever subscriber was
+//! installed there. What was gathered is sent back alongside the work's result, and the driver
+//! [`replay()`]s it into its own subscriber as spans and events with their original names,
+//! targets, levels and fields, plus the `process` they ran in, within whichever span is waiting on
+//! the work. Spans are only replayed once the work is done, so they carry their `duration`.
+
+#[cfg(feature = "trace")]
+mod collector;
+
+#[cfg(feature = "trace")]
+pub(crate) use self::forward::{collect, replay, Records};
+
+#[cfg(not(feature = "trace"))]
+pub(crate) use self::disabled::{collect, replay, Records};
+
+#[cfg(feature = "trace")]
+mod forward {
+	use futures::FutureExt;
+	use once_cell::sync::{Lazy, OnceCell};
+	use serde::{Deserialize, Serialize};
+	use std::{collections::HashMap, fmt, future::Future, sync::Mutex, time::Duration};
+	use tracing::{
+		dispatcher, field::{self, Field, Value}, instrument::WithSubscriber, warn, Dispatch, Event, Level, Metadata, Span
+	};
+	use tracing_core::{callsite::Callsite, identify_callsite, subscriber::Interest, Kind};
+
+	use super::collector::Collector;
+
+	/// The most records kept per piece of work, so that a chatty task can't blow up its response.
+	pub(super) const MAX_RECORDS: usize = 10_000;
+	/// The most fields replayed per record, including `process` and `duration`.
+	const MAX_FIELDS: usize = 32;
+
+	/// What a piece of work recorded in a worker process, in the order spans opened and events
+	/// happened.
+	#[derive(Default, Debug, Serialize, Deserialize)]
+	pub(crate) struct Records {
+		pub(super) records: Vec<Record>,
+		/// How many were dropped for exceeding [`MAX_RECORDS`].
+		pub(super) dropped: usize,
+	}
+
+	/// A span that closed or an event that happened in a worker process.
+	#[derive(Debug, Serialize, Deserialize)]
+	pub(super) struct Record {
+		/// Increasing in the order spans opened and events happened. A span's id.
+		pub(super) id: u64,
+		/// The span it was in, if that was recorded.
+		pub(super) parent: Option<u64>,
+		pub(super) metadata: RecordMetadata,
+		pub(super) fields: Vec<(String, FieldValue)>,
+		/// How long a span was open for, or `None` for an event.
+		pub(super) duration: Option<Duration>,
+	}
+
+	#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+	pub(super) struct RecordMetadata {
+		name: String,
+		target: String,
+		level: u8,
+		module_path: Option<String>,
+		file: Option<String>,
+		line: Option<u32>,
+	}
+	impl RecordMetadata {
+		pub(super) fn new(metadata: &Metadata<'_>) -> Self {
+			Self {
+				name: metadata.name().to_owned(),
+				target: metadata.target().to_owned(),
+				level: level_to_u8(*metadata.level()),
+				module_path: metadata.module_path().map(ToOwned::to_owned),
+				file: metadata.file().map(ToOwned::to_owned),
+				line: metadata.line(),
+			}
+		}
+	}
+
+	#[derive(Clone, Debug, Serialize, Deserialize)]
+	pub(super) enum FieldValue {
+		I64(i64),
+		U64(u64),
+		Bool(bool),
+		Str(String),
+		/// Anything else, as formatted by `Debug`.
+		Debug(String),
+	}
+	impl FieldValue {
+		fn value(&self) -> Box<dyn Value + '_> {
+			match self {
+				Self::I64(value) => Box::new(*value),
+				Self::U64(value) => Box::new(*value),
+				Self::Bool(value) => Box::new(*value),
+				Self::Str(value) => Box::new(&**value),
+				Self::Debug(value) => Box::new(field::display(value)),
+			}
+		}
+	}
+
+	/// Run `work` under a [`Collector`], returning its output along with what it recorded.
+	pub(crate) fn collect<'a, F, Fut>(work: F) -> impl Future<Output = (Fut::Output, Records)> + 'a
+	where
+		F: FnOnce() -> Fut,
+		Fut: Future + 'a,
+	{
+		let dispatch = Dispatch::new(Collector::new());
+		let work = dispatcher::with_default(&dispatch, work);
+		work.with_subscriber(dispatch.clone()).map(move |output| {
+			let records = dispatch.downcast_ref::<Collector>().unwrap().take();
+			(output, records)
+		})
+	}
+
+	/// Replay `records` received from worker process `process` into the current subscriber,
+	/// within the current span.
+	pub(crate) fn replay(process: &dyn fmt::Display, records: Records) {
+		let Records { records, dropped } = records;
+		let process = process.to_string();
+		let current = Span::current();
+		// The replayed spans by their id in the worker, kept open until all are replayed.
+		let mut spans = HashMap::<u64, Span>::new();
+		for Record {
+			id,
+			parent,
+			metadata,
+			fields,
+			duration,
+		} in records
+		{
+			let parent = parent
+				.and_then(|parent| spans.get(&parent))
+				.unwrap_or(&current)
+				.clone();
+			let metadata = callsite(&metadata, &fields, duration.is_some());
+			if !dispatcher::get_default(|dispatch| dispatch.enabled(metadata)) {
+				// Anything within a disabled span goes in its parent instead.
+				if duration.is_some() {
+					let _ = spans.insert(id, parent);
+				}
+				continue;
+			}
+			let process_ = field::display(&process);
+			let duration_ = duration.map(field::debug);
+			let values = fields
+				.iter()
+				.map(|(_, value)| value.value())
+				.collect::<Vec<_>>();
+			let values = Some(&process_ as &dyn Value)
+				.into_iter()
+				.chain(duration_.as_ref().map(|duration| duration as &dyn Value))
+				.chain(values.iter().map(|value| value as &dyn Value));
+			let fieldset = metadata.fields();
+			let names = fieldset.iter().collect::<Vec<Field>>();
+			let mut values_ = [(&names[0], None::<&dyn Value>); MAX_FIELDS];
+			for ((value_, name), value) in values_.iter_mut().zip(&names).zip(values) {
+				*value_ = (name, Some(value));
+			}
+			let values = fieldset.value_set(&values_);
+			if duration.is_some() {
+				let _ = spans.insert(id, Span::child_of(parent.id(), metadata, &values));
+			} else {
+				Event::child_of(parent.id(), metadata, &values);
+			}
+		}
+		if dropped > 0 {
+			warn!(process = %process, "dropped {} records", dropped);
+		}
+	}
+
+	/// The callsites of replayed records, by their metadata and field names. These are leaked as
+	/// tracing needs them to be `'static`, but there are only as many as there are distinct
+	/// callsites in the workers.
+	static CALLSITES: Lazy<Mutex<HashMap<CallsiteKey, &'static ReplayCallsite>>> =
+		Lazy::new(Default::default);
+
+	type CallsiteKey = (RecordMetadata, Vec<String>);
+
+	struct ReplayCallsite {
+		metadata: OnceCell<Metadata<'static>>,
+	}
+	impl Callsite for ReplayCallsite {
+		fn set_interest(&self, _interest: Interest) {}
+		fn metadata(&self) -> &Metadata<'_> {
+			self.metadata.get().unwrap()
+		}
+	}
+
+	/// The metadata to replay a record with, whose fields are `process`, `duration` if it's a span,
+	/// and then its own.
+	fn callsite(
+		metadata: &RecordMetadata, fields: &[(String, FieldValue)], span: bool,
+	) -> &'static Metadata<'static> {
+		let names = Some("process")
+			.into_iter()
+			.chain(if span { Some("duration") } else { None })
+			.chain(fields.iter().map(|(name, _)| &**name))
+			.take(MAX_FIELDS)
+			.map(ToOwned::to_owned)
+			.collect::<Vec<_>>();
+		let key = (metadata.clone(), names);
+		let mut callsites = CALLSITES.lock().unwrap();
+		if let Some(callsite) = callsites.get(&key) {
+			return callsite.metadata.get().unwrap();
+		}
+		let leak = |s: &str| -> &'static str { Box::leak(s.to_owned().into_boxed_str()) };
+		let names = key.1.iter().map(|name| leak(name)).collect::<Vec<_>>();
+		let callsite: &'static ReplayCallsite = Box::leak(Box::new(ReplayCallsite {
+			metadata: OnceCell::new(),
+		}));
+		let _ = callsite.metadata.set(Metadata::new(
+			leak(&metadata.name),
+			leak(&metadata.target),
+			level_from_u8(metadata.level),
+			metadata.file.as_deref().map(leak),
+			metadata.line,
+			metadata.module_path.as_deref().map(leak),
+			field::FieldSet::new(
+				Box::leak(names.into_boxed_slice()),
+				identify_callsite!(callsite),
+			),
+			if span { Kind::SPAN } else { Kind::EVENT },
+		));
+		tracing_core::callsite::register(callsite);
+		let _ = callsites.insert(key, callsite);
+		callsite.metadata.get().unwrap()
+	}
+
+	fn level_to_u8(level: Level) -> u8 {
+		match level {
+			Level::ERROR => 0,
+			Level::WARN => 1,
+			Level::INFO => 2,
+			Level::DEBUG => 3,
+			Level::TRACE => 4,
+		}
+	}
+	fn level_from_u8(level: u8) -> Level {
+		match level {
+			0 => Level::ERROR,
+			1 => Level::WARN,
+			2 => Level::INFO,
+			3 => Level::DEBUG,
+			_ => Level::TRACE,
+		}
+	}
+}
+
+#[cfg(not(feature = "trace"))]
+mod disabled {
+	use futures::FutureExt;
+	use std::{fmt, future::Future};
+
+	/// Nothing is recorded without the `trace` feature.
+	pub(crate) type Records = ();
+
+	pub(crate) fn collect<'a, F, Fut>(work: F) -> impl Future<Output = (Fut::Output, Records)> + 'a
+	where
+		F: FnOnce() -> Fut,
+		Fut: Future + 'a,
+	{
+		work().map(|output| (output, ()))
+	}
+
+	pub(crate) fn replay(_process: &dyn fmt::Display, _records: Records) {}
+}
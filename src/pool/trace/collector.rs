@@ -0,0 +1,207 @@
+use std::{
+	cell::RefCell, collections::HashMap, convert::TryFrom, fmt, mem, sync::{
+		atomic::{AtomicU64, AtomicUsize, Ordering}, Mutex
+	}, time::Instant
+};
+use tracing::{
+	field::{Field, Visit}, span, subscriber::Subscriber, Event, Level, Metadata
+};
+use tracing_core::span::Current;
+
+use super::forward::{FieldValue, Record, RecordMetadata, Records, MAX_RECORDS};
+
+/// Spans and records are split into this many shards, so that threads rarely contend.
+const SHARDS: u64 = 16;
+
+/// Span ids, which also order spans and events. Shared by all collectors, so the spans entered on
+/// a thread are never confused between them.
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static NEXT_THREAD: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+	/// The spans entered on this thread, innermost last.
+	static STACK: RefCell<Vec<u64>> = RefCell::new(Vec::new());
+	/// The shard of records this thread pushes to.
+	static SHARD: usize =
+		usize::try_from(NEXT_THREAD.fetch_add(1, Ordering::Relaxed) % SHARDS).unwrap();
+}
+
+struct SpanData {
+	metadata: &'static Metadata<'static>,
+	parent: Option<u64>,
+	fields: Vec<(String, FieldValue)>,
+	start: Instant,
+	refs: usize,
+}
+
+/// A subscriber that records the spans and events of a single piece of work.
+pub(super) struct Collector {
+	spans: Vec<Mutex<HashMap<u64, SpanData>>>,
+	records: Vec<Mutex<Vec<Record>>>,
+	/// How many records have been pushed, including those dropped.
+	pushed: AtomicUsize,
+}
+impl Collector {
+	pub(super) fn new() -> Self {
+		Self {
+			spans: (0..SHARDS).map(|_| Mutex::default()).collect(),
+			records: (0..SHARDS).map(|_| Mutex::default()).collect(),
+			pushed: AtomicUsize::new(0),
+		}
+	}
+	/// Take what's been recorded so far. Spans still open aren't included.
+	pub(super) fn take(&self) -> Records {
+		let mut records = self
+			.records
+			.iter()
+			.flat_map(|records| mem::take(&mut *records.lock().unwrap()))
+			.collect::<Vec<_>>();
+		records.sort_by_key(|record| record.id);
+		let dropped = self
+			.pushed
+			.swap(0, Ordering::Relaxed)
+			.saturating_sub(MAX_RECORDS);
+		Records { records, dropped }
+	}
+	fn spans(&self, id: u64) -> &Mutex<HashMap<u64, SpanData>> {
+		&self.spans[usize::try_from(id % SHARDS).unwrap()]
+	}
+	fn push(&self, record: Record) {
+		if self.pushed.fetch_add(1, Ordering::Relaxed) < MAX_RECORDS {
+			SHARD.with(|&shard| self.records[shard].lock().unwrap().push(record));
+		}
+	}
+	/// The explicit parent if given, otherwise the current span if contextual, if it's one of ours.
+	fn parent(&self, parent: Option<&span::Id>, is_contextual: bool) -> Option<u64> {
+		let parent = parent.map(span::Id::into_u64).or_else(|| {
+			if is_contextual {
+				STACK.with(|stack| stack.borrow().last().copied())
+			} else {
+				None
+			}
+		})?;
+		if self.spans(parent).lock().unwrap().contains_key(&parent) {
+			Some(parent)
+		} else {
+			None
+		}
+	}
+}
+impl Subscriber for Collector {
+	fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+		*metadata.level() <= Level::DEBUG
+	}
+	fn new_span(&self, attrs: &span::Attributes<'_>) -> span::Id {
+		let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+		let parent = self.parent(attrs.parent(), attrs.is_contextual());
+		let mut fields = Vec::new();
+		attrs.record(&mut Fields(&mut fields));
+		let _ = self.spans(id).lock().unwrap().insert(
+			id,
+			SpanData {
+				metadata: attrs.metadata(),
+				parent,
+				fields,
+				start: Instant::now(),
+				refs: 1,
+			},
+		);
+		span::Id::from_u64(id)
+	}
+	fn record(&self, span: &span::Id, values: &span::Record<'_>) {
+		let id = span.into_u64();
+		if let Some(span) = self.spans(id).lock().unwrap().get_mut(&id) {
+			values.record(&mut Fields(&mut span.fields));
+		}
+	}
+	fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+	fn event(&self, event: &Event<'_>) {
+		let parent = self.parent(event.parent(), event.is_contextual());
+		let mut fields = Vec::new();
+		event.record(&mut Fields(&mut fields));
+		self.push(Record {
+			id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+			parent,
+			metadata: RecordMetadata::new(event.metadata()),
+			fields,
+			duration: None,
+		});
+	}
+	fn enter(&self, span: &span::Id) {
+		STACK.with(|stack| stack.borrow_mut().push(span.into_u64()));
+	}
+	fn exit(&self, span: &span::Id) {
+		STACK.with(|stack| {
+			let mut stack = stack.borrow_mut();
+			if let Some(i) = stack.iter().rposition(|&id| id == span.into_u64()) {
+				let _ = stack.remove(i);
+			}
+		});
+	}
+	fn current_span(&self) -> Current {
+		let current = STACK.with(|stack| stack.borrow().last().copied());
+		current
+			.and_then(|id| {
+				let metadata = self.spans(id).lock().unwrap().get(&id)?.metadata;
+				Some(Current::new(span::Id::from_u64(id), metadata))
+			})
+			.unwrap_or_else(Current::none)
+	}
+	fn clone_span(&self, span: &span::Id) -> span::Id {
+		let id = span.into_u64();
+		if let Some(span) = self.spans(id).lock().unwrap().get_mut(&id) {
+			span.refs += 1;
+		}
+		span.clone()
+	}
+	fn try_close(&self, span: span::Id) -> bool {
+		let id = span.into_u64();
+		let span = {
+			let mut spans = self.spans(id).lock().unwrap();
+			match spans.get_mut(&id) {
+				Some(span) if span.refs > 1 => {
+					span.refs -= 1;
+					return false;
+				}
+				Some(_) => spans.remove(&id).unwrap(),
+				None => return false,
+			}
+		};
+		self.push(Record {
+			id,
+			parent: span.parent,
+			metadata: RecordMetadata::new(span.metadata),
+			fields: span.fields,
+			duration: Some(span.start.elapsed()),
+		});
+		true
+	}
+}
+
+/// Records fields, replacing any earlier value of the same name.
+struct Fields<'a>(&'a mut Vec<(String, FieldValue)>);
+impl Fields<'_> {
+	fn set(&mut self, field: &Field, value: FieldValue) {
+		match self.0.iter_mut().find(|(name, _)| name == field.name()) {
+			Some((_, value_)) => *value_ = value,
+			None => self.0.push((field.name().to_owned(), value)),
+		}
+	}
+}
+impl Visit for Fields<'_> {
+	fn record_i64(&mut self, field: &Field, value: i64) {
+		self.set(field, FieldValue::I64(value));
+	}
+	fn record_u64(&mut self, field: &Field, value: u64) {
+		self.set(field, FieldValue::U64(value));
+	}
+	fn record_bool(&mut self, field: &Field, value: bool) {
+		self.set(field, FieldValue::Bool(value));
+	}
+	fn record_str(&mut self, field: &Field, value: &str) {
+		self.set(field, FieldValue::Str(value.to_owned()));
+	}
+	fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+		self.set(field, FieldValue::Debug(format!("{:?}", value)));
+	}
+}
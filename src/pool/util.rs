@@ -1,17 +1,17 @@
 #![allow(dead_code)]
 
-use futures::future;
+use futures::{future, pin_mut};
 use serde::{Deserialize, Serialize};
 use std::{
 	any::Any, error::Error, fmt::{self, Debug, Display}, future::Future, mem, sync::{
-		atomic::{AtomicBool, AtomicUsize, Ordering}, Mutex
-	}, task::{Poll, Waker}
+		atomic::{AtomicBool, AtomicUsize, Ordering}, Arc, Mutex
+	}, task::{Context, Poll, Waker}, thread::{self, Thread}
 };
 
-#[cfg(feature = "constellation")]
+#[cfg(any(feature = "constellation", feature = "local-process"))]
 #[derive(Debug)]
 pub(crate) struct RoundRobin(AtomicUsize, usize);
-#[cfg(feature = "constellation")]
+#[cfg(any(feature = "constellation", feature = "local-process"))]
 impl RoundRobin {
 	pub(crate) fn new(start: usize, limit: usize) -> Self {
 		Self(AtomicUsize::new(start), limit)
@@ -25,8 +25,56 @@ impl RoundRobin {
 	}
 }
 
+/// Tracks the tasks in flight on each process, so that work can go to whichever is least busy
+/// rather than queueing behind a slow task.
+#[cfg(any(feature = "constellation", feature = "local-process"))]
+#[derive(Debug)]
+pub(crate) struct LeastLoaded(Arc<Vec<AtomicUsize>>, RoundRobin);
+#[cfg(any(feature = "constellation", feature = "local-process"))]
+impl LeastLoaded {
+	pub(crate) fn new(processes: usize) -> Self {
+		Self(
+			Arc::new((0..processes).map(|_| AtomicUsize::new(0)).collect()),
+			RoundRobin::new(0, processes),
+		)
+	}
+	/// The index of the process with the fewest tasks in flight, ties broken round-robin, and
+	/// other than `avoid` if there's another. It's counted as running another task until the
+	/// returned guard is dropped.
+	pub(crate) fn get(&self, avoid: Option<usize>) -> (usize, InFlight) {
+		let start = self.1.get();
+		let n = self.0.len();
+		let i = (start..start + n)
+			.map(|i| i % n)
+			.min_by_key(|&i| (n > 1 && Some(i) == avoid, self.0[i].load(Ordering::SeqCst)))
+			.unwrap();
+		let _ = self.0[i].fetch_add(1, Ordering::SeqCst);
+		(i, InFlight(self.0.clone(), i))
+	}
+}
+
+#[cfg(any(feature = "constellation", feature = "local-process"))]
+#[derive(Debug)]
+pub(crate) struct InFlight(Arc<Vec<AtomicUsize>>, usize);
+#[cfg(any(feature = "constellation", feature = "local-process"))]
+impl Drop for InFlight {
+	fn drop(&mut self) {
+		let _ = self.0[self.1].fetch_sub(1, Ordering::SeqCst);
+	}
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Panicked(String);
+impl Panicked {
+	/// The error for work whose process exited on its last attempt.
+	#[cfg(any(feature = "constellation", feature = "local-process"))]
+	pub(crate) fn exited(process: impl Display, attempt: usize, attempts: usize) -> Self {
+		Self(format!(
+			"worker process {} exited, on attempt {} of {}",
+			process, attempt, attempts
+		))
+	}
+}
 impl From<Box<dyn Any + Send>> for Panicked {
 	fn from(e: Box<dyn Any + Send>) -> Self {
 		// https://github.com/rust-lang/rust/blob/b43eb4235ac43c822d903ad26ed806f34cc1a14a/src/libstd/panicking.rs#L179-L185
@@ -112,6 +160,41 @@ impl Synchronize {
 	}
 }
 
+/// Extension trait to provide convenient [`block()`](FutureExt1::block) method on futures.
+///
+/// Named `FutureExt1` to avoid clashing with [`futures::future::FutureExt`].
+pub(crate) trait FutureExt1: Future {
+	/// Convenience method over `futures::executor::block_on(future)`.
+	fn block(self) -> Self::Output
+	where
+		Self: Sized,
+	{
+		// futures::executor::block_on(self) // Not reentrant for some reason
+		struct ThreadNotify {
+			thread: Thread,
+		}
+		impl futures::task::ArcWake for ThreadNotify {
+			fn wake_by_ref(arc_self: &Arc<Self>) {
+				arc_self.thread.unpark();
+			}
+		}
+		let f = self;
+		pin_mut!(f);
+		let thread_notify = Arc::new(ThreadNotify {
+			thread: thread::current(),
+		});
+		let waker = futures::task::waker_ref(&thread_notify);
+		let mut cx = Context::from_waker(&waker);
+		loop {
+			if let Poll::Ready(t) = f.as_mut().poll(&mut cx) {
+				return t;
+			}
+			thread::park();
+		}
+	}
+}
+impl<T: ?Sized> FutureExt1 for T where T: Future {}
+
 pub(crate) fn assert_sync_and_send<T: Send + Sync>(t: T) -> T {
 	t
 }
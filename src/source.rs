@@ -12,16 +12,20 @@ use crate::{
 
 #[cfg(feature = "aws")]
 #[doc(inline)]
-pub use amadeus_aws::Cloudfront;
+pub use amadeus_aws::{Alb, Cloudfront, Cloudtrail, VpcFlow};
 #[cfg(feature = "aws")]
 pub mod aws {
-	pub use crate::data::CloudfrontRow;
+	pub use crate::data::{AlbRow, CloudfrontRow, CloudtrailRow, VpcFlowRow};
 	#[doc(inline)]
-	pub use amadeus_aws::{AwsCredentials, AwsError, AwsRegion, S3Directory, S3File};
+	pub use amadeus_aws::{
+		custom_endpoint, endpoint_from_env, AwsCredentials, AwsError, AwsRegion, AwsRetry, MalformedLine, S3Directory, S3File
+	};
 }
 #[cfg(feature = "commoncrawl")]
 #[doc(inline)]
-pub use amadeus_commoncrawl::CommonCrawl;
+pub use amadeus_commoncrawl::{
+	CommonCrawl, CommonCrawlWat, CommonCrawlWet, Warc, WarcDirectory, WarcRecordType
+};
 #[cfg(feature = "parquet")]
 #[doc(inline)]
 pub use amadeus_parquet::{Parquet, ParquetDirectory};
@@ -130,6 +134,21 @@ where
 	}
 }
 #[cfg(feature = "aws")]
+impl Source for Alb {
+	type Item = crate::data::AlbRow;
+	type Error = <Self as amadeus_core::Source>::Error;
+
+	type ParStream = IntoStream<<Self as amadeus_core::Source>::ParStream, Self::Item>;
+	type DistStream = IntoStream<<Self as amadeus_core::Source>::DistStream, Self::Item>;
+
+	fn par_stream(self) -> Self::ParStream {
+		IntoStream::new(<Self as amadeus_core::Source>::par_stream(self))
+	}
+	fn dist_stream(self) -> Self::DistStream {
+		IntoStream::new(<Self as amadeus_core::Source>::dist_stream(self))
+	}
+}
+#[cfg(feature = "aws")]
 impl Source for Cloudfront {
 	type Item = crate::data::CloudfrontRow;
 	type Error = <Self as amadeus_core::Source>::Error;
@@ -144,6 +163,36 @@ impl Source for Cloudfront {
 		IntoStream::new(<Self as amadeus_core::Source>::dist_stream(self))
 	}
 }
+#[cfg(feature = "aws")]
+impl Source for Cloudtrail {
+	type Item = crate::data::CloudtrailRow;
+	type Error = <Self as amadeus_core::Source>::Error;
+
+	type ParStream = IntoStream<<Self as amadeus_core::Source>::ParStream, Self::Item>;
+	type DistStream = IntoStream<<Self as amadeus_core::Source>::DistStream, Self::Item>;
+
+	fn par_stream(self) -> Self::ParStream {
+		IntoStream::new(<Self as amadeus_core::Source>::par_stream(self))
+	}
+	fn dist_stream(self) -> Self::DistStream {
+		IntoStream::new(<Self as amadeus_core::Source>::dist_stream(self))
+	}
+}
+#[cfg(feature = "aws")]
+impl Source for VpcFlow {
+	type Item = crate::data::VpcFlowRow;
+	type Error = <Self as amadeus_core::Source>::Error;
+
+	type ParStream = IntoStream<<Self as amadeus_core::Source>::ParStream, Self::Item>;
+	type DistStream = IntoStream<<Self as amadeus_core::Source>::DistStream, Self::Item>;
+
+	fn par_stream(self) -> Self::ParStream {
+		IntoStream::new(<Self as amadeus_core::Source>::par_stream(self))
+	}
+	fn dist_stream(self) -> Self::DistStream {
+		IntoStream::new(<Self as amadeus_core::Source>::dist_stream(self))
+	}
+}
 #[cfg(feature = "commoncrawl")]
 impl Source for CommonCrawl {
 	type Item = amadeus_types::Webpage<'static>;
@@ -159,6 +208,54 @@ impl Source for CommonCrawl {
 		IntoStream::new(<Self as amadeus_core::Source>::dist_stream(self))
 	}
 }
+#[cfg(feature = "commoncrawl")]
+impl Source for CommonCrawlWet {
+	type Item = crate::data::WetRecord;
+	type Error = <Self as amadeus_core::Source>::Error;
+
+	type ParStream = IntoStream<<Self as amadeus_core::Source>::ParStream, Self::Item>;
+	type DistStream = IntoStream<<Self as amadeus_core::Source>::DistStream, Self::Item>;
+
+	fn par_stream(self) -> Self::ParStream {
+		IntoStream::new(<Self as amadeus_core::Source>::par_stream(self))
+	}
+	fn dist_stream(self) -> Self::DistStream {
+		IntoStream::new(<Self as amadeus_core::Source>::dist_stream(self))
+	}
+}
+#[cfg(feature = "commoncrawl")]
+impl Source for CommonCrawlWat {
+	type Item = crate::data::WatRecord;
+	type Error = <Self as amadeus_core::Source>::Error;
+
+	type ParStream = IntoStream<<Self as amadeus_core::Source>::ParStream, Self::Item>;
+	type DistStream = IntoStream<<Self as amadeus_core::Source>::DistStream, Self::Item>;
+
+	fn par_stream(self) -> Self::ParStream {
+		IntoStream::new(<Self as amadeus_core::Source>::par_stream(self))
+	}
+	fn dist_stream(self) -> Self::DistStream {
+		IntoStream::new(<Self as amadeus_core::Source>::dist_stream(self))
+	}
+}
+#[cfg(feature = "commoncrawl")]
+impl<File> Source for Warc<File>
+where
+	File: amadeus_core::file::File,
+{
+	type Item = crate::data::WarcRecord;
+	type Error = <Self as amadeus_core::Source>::Error;
+
+	type ParStream = IntoStream<<Self as amadeus_core::Source>::ParStream, Self::Item>;
+	type DistStream = IntoStream<<Self as amadeus_core::Source>::DistStream, Self::Item>;
+
+	fn par_stream(self) -> Self::ParStream {
+		IntoStream::new(<Self as amadeus_core::Source>::par_stream(self))
+	}
+	fn dist_stream(self) -> Self::DistStream {
+		IntoStream::new(<Self as amadeus_core::Source>::dist_stream(self))
+	}
+}
 
 #[pin_project]
 #[derive(new)]
@@ -223,6 +320,12 @@ where
 {
 	type Item = Result<U, E>;
 	type Async = IntoTask<I::Async, U>;
+	fn partition(&self) -> Option<String> {
+		self.task.partition()
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.task.size_hint()
+	}
 	fn into_async(self) -> Self::Async {
 		IntoTask {
 			task: self.task.into_async(),
@@ -1,6 +1,6 @@
 #![allow(clippy::suspicious_map)]
 
-use amadeus::prelude::*;
+use amadeus::{prelude::*, source::aws::endpoint_from_env};
 use std::time::SystemTime;
 
 #[tokio::test(threaded_scheduler)]
@@ -11,13 +11,17 @@ async fn cloudfront() {
 	let start = SystemTime::now();
 
 	let rows = Cloudfront::new_with(
-		AwsRegion::UsEast1,
+		endpoint_from_env(AwsRegion::UsEast1),
 		"us-east-1.data-analytics",
 		"cflogworkshop/raw/cf-accesslogs/",
 		AwsCredentials::Anonymous,
 	)
 	.await
-	.unwrap();
+	.unwrap()
+	.retry(AwsRetry {
+		max_attempts: 5,
+		..AwsRetry::default()
+	});
 
 	let ((), (count, count2, (), list)): ((), (usize, usize, (), List<CloudfrontRow>)) = rows
 		.clone()
@@ -1,4 +1,4 @@
-use amadeus::dist::prelude::*;
+use amadeus::{dist::prelude::*, source::aws::endpoint_from_env};
 #[cfg(feature = "constellation")]
 use constellation::*;
 use std::time::{Duration, SystemTime};
@@ -36,13 +36,17 @@ async fn run<P: amadeus_core::pool::ProcessPool>(pool: &P) -> Duration {
 	let start = SystemTime::now();
 
 	let rows = Cloudfront::new_with(
-		AwsRegion::UsEast1,
+		endpoint_from_env(AwsRegion::UsEast1),
 		"us-east-1.data-analytics",
 		"cflogworkshop/raw/cf-accesslogs/",
 		AwsCredentials::Anonymous,
 	)
 	.await
-	.unwrap();
+	.unwrap()
+	.retry(AwsRetry {
+		max_attempts: 5,
+		..AwsRetry::default()
+	});
 
 	let ((), (count, count2, (), list)): ((), (usize, usize, (), List<CloudfrontRow>)) = rows
 		.clone()
@@ -13,7 +13,9 @@
 
 use std::time::{Duration, SystemTime};
 
-use amadeus::{data::Webpage, prelude::*};
+use amadeus::{
+	data::{WatRecord, Webpage, WetRecord}, prelude::*
+};
 
 #[tokio::test(threaded_scheduler)]
 #[cfg_attr(miri, ignore)]
@@ -98,3 +100,39 @@ async fn commoncrawl() {
 
 	println!("in {:?}", start.elapsed().unwrap());
 }
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn commoncrawl_wet() {
+	let start = SystemTime::now();
+
+	let pool = &ThreadPool::new(None).unwrap();
+
+	let records = CommonCrawlWet::new("CC-MAIN-2020-24").await.unwrap();
+	let _ = records
+		.par_stream()
+		.all(pool, move |x: Result<WetRecord, _>| -> bool {
+			let x = x.unwrap();
+			assert!(x.url.starts_with("http"));
+			start.elapsed().unwrap() < Duration::new(10, 0)
+		})
+		.await;
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn commoncrawl_wat() {
+	let start = SystemTime::now();
+
+	let pool = &ThreadPool::new(None).unwrap();
+
+	let records = CommonCrawlWat::new("CC-MAIN-2020-24").await.unwrap();
+	let _ = records
+		.par_stream()
+		.all(pool, move |x: Result<WatRecord, _>| -> bool {
+			let x = x.unwrap();
+			assert!(x.url.starts_with("http"));
+			start.elapsed().unwrap() < Duration::new(10, 0)
+		})
+		.await;
+}
@@ -1,6 +1,14 @@
 use either::Either;
+use futures::StreamExt;
+use std::{
+	sync::{
+		atomic::{AtomicUsize, Ordering}, Arc
+	}, time::Duration
+};
 
-use amadeus::prelude::*;
+use amadeus::{
+	par_sink::SinkMap, par_stream::{CancellationToken, Cancelled, Metrics}, prelude::*
+};
 
 #[tokio::test(threaded_scheduler)]
 #[cfg_attr(miri, ignore)]
@@ -33,4 +41,219 @@ async fn into_par_stream() {
 	}
 	let sum: usize = slice.iter().cloned().par().sum(&pool).await;
 	assert_eq!(sum, slice.iter().sum::<usize>());
+
+	for i in 0..slice.len() + 2 {
+		let res = slice.iter().cloned().par().take(&pool, i).await;
+		assert_eq!(res, slice.iter().cloned().take(i).collect::<Vec<_>>());
+		let res = slice
+			.iter()
+			.cloned()
+			.par()
+			.skip(i)
+			.take(&pool, slice.len())
+			.await;
+		assert_eq!(res, slice.iter().cloned().skip(i).collect::<Vec<_>>());
+		let mut res = slice
+			.iter()
+			.cloned()
+			.par()
+			.skip(i)
+			.collect::<_, Vec<_>>(&pool)
+			.await;
+		res.sort_unstable();
+		assert_eq!(res, slice.iter().cloned().skip(i).collect::<Vec<_>>());
+		// the number of items each task yields isn't known, so the tasks to skip run as one
+		let res = slice
+			.iter()
+			.cloned()
+			.par()
+			.filter(|x: &usize| x % 3 == 1)
+			.skip(i)
+			.take(&pool, slice.len())
+			.await;
+		assert_eq!(
+			res,
+			slice
+				.iter()
+				.cloned()
+				.filter(|x| x % 3 == 1)
+				.skip(i)
+				.collect::<Vec<_>>()
+		);
+	}
+	let first = slice.iter().cloned().par().take(&pool, 1).await;
+	assert_eq!(first, [0]);
+
+	// tasks stop being scheduled once the first 10 items are in hand
+	let seen = Arc::new(AtomicUsize::new(0));
+	let seen_ = seen.clone();
+	let res = (0..100_000_usize)
+		.par()
+		.inspect(move |_: &usize| {
+			let _ = seen_.fetch_add(1, Ordering::Relaxed);
+		})
+		.take(&pool, 10)
+		.await;
+	assert_eq!(res, (0..10).collect::<Vec<_>>());
+	assert!(seen.load(Ordering::Relaxed) < 100_000);
+
+	// depending on the order the tasks' t-digests are merged in, the median can be off by up to
+	// half a centroid, which holds at most π/100 of the values
+	let (values, bound) = (
+		(0..1000_u32).map(f64::from).collect::<Vec<_>>(),
+		1000.0 * std::f64::consts::PI / 100.0 / 2.0,
+	);
+	let res = values
+		.iter()
+		.cloned()
+		.par()
+		.quantiles(&pool, &[0.0, 0.5, 1.0])
+		.await;
+	assert_eq!((res[0], res[2]), (Some(0.0), Some(999.0)));
+	assert!((res[1].unwrap() - 499.5).abs() <= bound);
+	let res = values.iter().cloned().par().percentile(&pool, 50.0).await;
+	assert!((res.unwrap() - 499.5).abs() <= bound);
+	let res = values
+		.iter()
+		.cloned()
+		.par()
+		.map(|x: f64| (x < 500.0, x))
+		.group_by(&pool, Identity.percentile(100.0))
+		.await;
+	assert_eq!(res[&true], Some(499.0));
+	assert_eq!(res[&false], Some(999.0));
+	let res = values
+		.iter()
+		.cloned()
+		.par()
+		.pipe(&pool, Identity.percentile(50.0).with_compression(20.0))
+		.await;
+	assert!((res.unwrap() - 499.5).abs() <= 1000.0 * std::f64::consts::PI / 20.0 / 2.0);
+
+	// one partition yields far more items than the others, but after repartitioning no task
+	// holds more than the requested number of them
+	let total = (0..10_usize).map(|i| (0..i * 10).sum::<usize>()).sum::<usize>();
+	let sizes = (0..10_usize)
+		.par()
+		.flat_map(|i: usize| futures::stream::iter(0..i * 10))
+		.repartition(&pool, 7)
+		.chunks(100)
+		.map(|chunk: Vec<usize>| (chunk.len(), chunk.iter().sum::<usize>()))
+		.collect::<_, Vec<_>>(&pool)
+		.await;
+	assert_eq!(sizes.iter().map(|&(len, _)| len).max(), Some(7));
+	assert_eq!(sizes.iter().map(|&(len, _)| len).sum::<usize>(), 450);
+	assert_eq!(sizes.iter().map(|&(_, sum)| sum).sum::<usize>(), total);
+	let sizes = [1000_usize, 1, 1, 1]
+		.iter()
+		.cloned()
+		.par()
+		.flat_map(|i: usize| futures::stream::iter(0..i))
+		.rebalance(&pool)
+		.chunks(1000)
+		.map(|chunk: Vec<usize>| chunk.len())
+		.collect::<_, Vec<_>>(&pool)
+		.await;
+	let threads = pool.threads();
+	assert_eq!(sizes.iter().sum::<usize>(), 1003);
+	assert_eq!(sizes.iter().max(), Some(&(999 / threads + 1)));
+
+	let total = (0..100_u64).map(|i| (0..i).sum::<u64>()).sum::<u64>();
+	let max = (0..100_u64)
+		.par()
+		.flat_map(|i: u64| futures::stream::iter(0..i))
+		.chunks(7)
+		.map(|chunk: Vec<u64>| chunk.len())
+		.max(&pool)
+		.await;
+	assert_eq!(max, Some(7));
+	let len = (0..100_u64)
+		.par()
+		.pipe(
+			&pool,
+			Identity
+				.map(|x: u64| x * 2)
+				.chunks(7)
+				.map(|chunk: Vec<u64>| chunk.len())
+				.sum::<usize>(),
+		)
+		.await;
+	assert_eq!(len, 100);
+	let sum: u64 = (0..100_u64)
+		.par()
+		.flat_map(|i: u64| futures::stream::iter(0..i))
+		.batch::<List<u64>>(7)
+		.unbatch()
+		.sum(&pool)
+		.await;
+	assert_eq!(sum, total);
+
+	let mut metrics = SinkMap::<_, _, Value>::new()
+		.add("sum", Identity.sum::<u64>())
+		.add("max", Identity.max())
+		.add("mean", Identity.map(|x: u64| x as f64).mean());
+	metrics.insert("any", Identity.any(|x: u64| x > 90));
+	let res = (0..100_u64).par().pipe(&pool, metrics).await;
+	assert_eq!(
+		res.keys().cloned().collect::<Vec<_>>(),
+		["sum", "max", "mean", "any"]
+	);
+	assert_eq!(res["sum"], Value::from(4950_u64));
+	assert_eq!(res["max"], Value::from(Some(99_u64)));
+	assert_eq!(res["mean"], Value::from(49.5));
+	assert_eq!(res["any"], Value::from(true));
+
+	let metrics = Metrics::new();
+	let updates = metrics.subscribe();
+	let sum: u64 = (0..10_000_u64).par().monitor(&metrics).sum(&pool).await;
+	assert_eq!(sum, 49_995_000);
+	let snapshot = metrics.snapshot();
+	assert_eq!(snapshot.items, 10_000);
+	assert_eq!(snapshot.failed, 0);
+	assert_eq!(snapshot.completed, snapshot.scheduled);
+	assert!(snapshot.finished);
+	drop(metrics);
+	let updates = updates.collect::<Vec<_>>().await;
+	assert_eq!(updates.last(), Some(&snapshot));
+
+	// tasks `take` has no need of are counted as dropped, so the job still finishes
+	let metrics = Metrics::new();
+	let mut updates = metrics.subscribe();
+	let res = (0..10_000_u64).par().monitor(&metrics).take(&pool, 5).await;
+	assert_eq!(res, [0, 1, 2, 3, 4]);
+	tokio::time::timeout(Duration::from_secs(10), async {
+		while !metrics.snapshot().finished {
+			let _ = updates.next().await;
+		}
+	})
+	.await
+	.unwrap();
+	let snapshot = metrics.snapshot();
+	assert!(snapshot.dropped > 0);
+	assert!(snapshot.scheduled < 10_000);
+
+	let token = CancellationToken::new();
+	let res = (0..100_u64)
+		.par()
+		.cancellable(&pool, &token, Identity.sum::<u64>())
+		.await;
+	assert_eq!(res, Ok(4950));
+	let token_ = token.clone();
+	let res = (0..1_000_000_u64)
+		.par()
+		.inspect(move |&i| {
+			if i == 1_000 {
+				token_.cancel()
+			}
+		})
+		.cancellable(&pool, &token, Identity.count());
+	assert_eq!(res.await, Err(Cancelled));
+	// the terminal returns promptly even while tasks are blocked on an item
+	let token = CancellationToken::with_timeout(Duration::from_millis(10));
+	let res = (0..10_u64)
+		.par()
+		.flat_map(|_| futures::stream::pending::<u64>())
+		.cancellable(&pool, &token, Identity.count())
+		.await;
+	assert_eq!(res, Err(Cancelled));
 }
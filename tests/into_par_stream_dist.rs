@@ -1,9 +1,13 @@
 #[cfg(feature = "constellation")]
 use constellation::*;
 use either::Either;
-use std::time::{Duration, SystemTime};
+use std::{
+	env, fs, process, time::{Duration, SystemTime}
+};
 
-use amadeus::dist::prelude::*;
+use amadeus::{
+	dist::prelude::*, par_sink::DistSinkMap, par_stream::{CancellationToken, Cancelled, Checkpoint, MemoryBudget, MemoryExceeded, Metrics}
+};
 
 fn main() {
 	if cfg!(miri) {
@@ -11,6 +15,8 @@ fn main() {
 	}
 	#[cfg(feature = "constellation")]
 	init(Resources::default());
+	#[cfg(feature = "local-process")]
+	LocalProcessPool::init();
 
 	tokio::runtime::Builder::new()
 		.threaded_scheduler()
@@ -29,8 +35,18 @@ fn main() {
 			};
 			#[cfg(not(feature = "constellation"))]
 			let process_pool_time = "-";
+			#[cfg(feature = "local-process")]
+			let local_process_pool_time = {
+				let local_process_pool = LocalProcessPool::new(None, None).unwrap();
+				run(&local_process_pool).await
+			};
+			#[cfg(not(feature = "local-process"))]
+			let local_process_pool_time = "-";
 
-			println!("in {:?} {:?}", thread_pool_time, process_pool_time);
+			println!(
+				"in {:?} {:?} {:?}",
+				thread_pool_time, process_pool_time, local_process_pool_time
+			);
 		})
 }
 
@@ -45,6 +61,20 @@ async fn run<P: amadeus_core::pool::ProcessPool>(pool: &P) -> Duration {
 	let res: usize = [1, 2, 3].into_dist_stream().sum(&pool).await;
 	assert_eq!(res, 6);
 
+	// tasks run in other processes report back
+	let metrics = Metrics::new();
+	let sum: u64 = (0..1_000_u64)
+		.dist()
+		.monitor(&metrics)
+		.map(FnMut!(|i: u64| i))
+		.sum(&pool)
+		.await;
+	assert_eq!(sum, 499_500);
+	let snapshot = metrics.snapshot();
+	assert_eq!(snapshot.items, 1_000);
+	assert_eq!(snapshot.completed, 1_000);
+	assert!(snapshot.finished);
+
 	let slice = [
 		0_usize, 1, 2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73,
 		79, 83, 89, 97,
@@ -64,5 +94,252 @@ async fn run<P: amadeus_core::pool::ProcessPool>(pool: &P) -> Duration {
 	let sum: usize = slice.iter().cloned().dist().sum(&pool).await;
 	assert_eq!(sum, slice.iter().sum::<usize>());
 
+	for i in 0..slice.len() + 2 {
+		let res = slice.iter().cloned().dist().take(&pool, i).await;
+		assert_eq!(res, slice.iter().cloned().take(i).collect::<Vec<_>>());
+		let res = slice
+			.iter()
+			.cloned()
+			.dist()
+			.skip(i)
+			.take(&pool, slice.len())
+			.await;
+		assert_eq!(res, slice.iter().cloned().skip(i).collect::<Vec<_>>());
+		let mut res = slice
+			.iter()
+			.cloned()
+			.dist()
+			.skip(i)
+			.collect::<_, Vec<_>>(&pool)
+			.await;
+		res.sort_unstable();
+		assert_eq!(res, slice.iter().cloned().skip(i).collect::<Vec<_>>());
+		// the number of items each task yields isn't known, so the tasks to skip run as one
+		let res = slice
+			.iter()
+			.cloned()
+			.dist()
+			.filter(FnMut!(|x: &usize| x % 3 == 1))
+			.skip(i)
+			.take(&pool, slice.len())
+			.await;
+		assert_eq!(
+			res,
+			slice
+				.iter()
+				.cloned()
+				.filter(|x| x % 3 == 1)
+				.skip(i)
+				.collect::<Vec<_>>()
+		);
+	}
+	let first = slice.iter().cloned().dist().take(&pool, 1).await;
+	assert_eq!(first, [0]);
+
+	// depending on the order the tasks' t-digests are merged in, the median can be off by up to
+	// half a centroid, which holds at most π/100 of the values
+	let (values, bound) = (
+		(0..1000_u32).map(f64::from).collect::<Vec<_>>(),
+		1000.0 * std::f64::consts::PI / 100.0 / 2.0,
+	);
+	let res = values
+		.iter()
+		.cloned()
+		.dist()
+		.quantiles(&pool, &[0.0, 0.5, 1.0])
+		.await;
+	assert_eq!((res[0], res[2]), (Some(0.0), Some(999.0)));
+	assert!((res[1].unwrap() - 499.5).abs() <= bound);
+	let res = values.iter().cloned().dist().percentile(&pool, 50.0).await;
+	assert!((res.unwrap() - 499.5).abs() <= bound);
+	let res = values
+		.iter()
+		.cloned()
+		.dist()
+		.map(FnMut!(|x: f64| (x < 500.0, x)))
+		.group_by(&pool, Identity.percentile(100.0))
+		.await;
+	assert_eq!(res[&true], Some(499.0));
+	assert_eq!(res[&false], Some(999.0));
+
+	// one partition yields far more items than the others, but after repartitioning no task
+	// holds more than the requested number of them
+	let total = (0..10_usize).map(|i| (0..i * 10).sum::<usize>()).sum::<usize>();
+	let sizes = (0..10_usize)
+		.dist()
+		.flat_map(FnMut!(|i: usize| futures::stream::iter(0..i * 10)))
+		.repartition(pool, 7)
+		.chunks(100)
+		.map(FnMut!(|chunk: Vec<usize>| -> (usize, usize) {
+			(chunk.len(), chunk.iter().sum::<usize>())
+		}))
+		.collect::<_, Vec<_>>(pool)
+		.await;
+	assert_eq!(sizes.iter().map(|&(len, _)| len).max(), Some(7));
+	assert_eq!(sizes.iter().map(|&(len, _)| len).sum::<usize>(), 450);
+	assert_eq!(sizes.iter().map(|&(_, sum)| sum).sum::<usize>(), total);
+	let sizes = [1000_usize, 1, 1, 1]
+		.iter()
+		.cloned()
+		.dist()
+		.flat_map(FnMut!(|i: usize| futures::stream::iter(0..i)))
+		.rebalance(pool)
+		.chunks(1000)
+		.map(FnMut!(|chunk: Vec<usize>| chunk.len()))
+		.collect::<_, Vec<_>>(pool)
+		.await;
+	let processes = pool.processes();
+	assert_eq!(sizes.iter().sum::<usize>(), 1003);
+	assert_eq!(sizes.iter().max(), Some(&(999 / processes + 1)));
+
+	let total = (0..100_u64).map(|i| (0..i).sum::<u64>()).sum::<u64>();
+	let max = (0..100_u64)
+		.dist()
+		.flat_map(FnMut!(|i: u64| futures::stream::iter(0..i)))
+		.chunks(7)
+		.map(FnMut!(|chunk: Vec<u64>| chunk.len()))
+		.max(&pool)
+		.await;
+	assert_eq!(max, Some(7));
+	let sum: u64 = (0..100_u64)
+		.dist()
+		.flat_map(FnMut!(|i: u64| futures::stream::iter(0..i)))
+		.batch::<List<u64>>(7)
+		.unbatch()
+		.sum(&pool)
+		.await;
+	assert_eq!(sum, total);
+
+	let mut metrics = DistSinkMap::<_, _, Value>::new()
+		.add("sum", Identity.sum::<u64>())
+		.add("max", Identity.max())
+		.add("mean", Identity.map(FnMut!(|x: u64| x as f64)).mean());
+	metrics.insert("any", Identity.any(FnMut!(|x: u64| x > 90)));
+	let res = (0..100_u64).dist().pipe(pool, metrics).await;
+	assert_eq!(
+		res.keys().cloned().collect::<Vec<_>>(),
+		["sum", "max", "mean", "any"]
+	);
+	assert_eq!(res["sum"], Value::from(4950_u64));
+	assert_eq!(res["max"], Value::from(Some(99_u64)));
+	assert_eq!(res["mean"], Value::from(49.5));
+	assert_eq!(res["any"], Value::from(true));
+
+	let token = CancellationToken::new();
+	let sum = (0..1000_u64)
+		.dist()
+		.cancellable(pool, &token, DistributedPipe::<u64>::sum(Identity))
+		.await;
+	assert_eq!(sum, Ok(499_500));
+	token.cancel();
+	let res = (0..1000_u64)
+		.dist()
+		.cancellable(pool, &token, DistributedPipe::<u64>::count(Identity))
+		.await;
+	assert_eq!(res, Err(Cancelled));
+	// work already sent to the processes is abandoned rather than waited for
+	let token = CancellationToken::with_timeout(Duration::from_millis(100));
+	let res = (0..10_u64)
+		.dist()
+		.map(FnMut!(|i: u64| -> u64 {
+			std::thread::sleep(Duration::from_secs(1));
+			i
+		}))
+		.cancellable(pool, &token, DistributedPipe::<u64>::count(Identity))
+		.await;
+	assert_eq!(res, Err(Cancelled));
+	// tasks in the processes share a copy of the token, so stop at its deadline
+	let token = CancellationToken::with_timeout(Duration::from_millis(100));
+	let res = (0..10_u64)
+		.dist()
+		.flat_map(FnMut!(|_| futures::stream::pending::<u64>()))
+		.cancellable(pool, &token, DistributedPipe::<u64>::count(Identity))
+		.await;
+	assert_eq!(res, Err(Cancelled));
+
+	let budget = MemoryBudget::new(1 << 30);
+	let sum = (0..1000_u64)
+		.dist()
+		.memory_bounded(pool, &budget, 1024, DistributedPipe::<u64>::sum(Identity))
+		.await;
+	assert_eq!(sum, Ok(499_500));
+	// exceeding the budget in any process fails the job
+	let budget = MemoryBudget::new(32 << 20);
+	let res = (0..1024_u64)
+		.dist()
+		.map(FnMut!(|_: u64| -> Vec<u8> {
+			let mut item = Vec::new();
+			item.resize(1 << 20, 1);
+			item
+		}))
+		.memory_bounded(
+			pool,
+			&budget,
+			0,
+			DistributedPipe::<Vec<u8>>::collect::<Vec<_>>(Identity),
+		)
+		.await;
+	assert_eq!(
+		res.map(|items| items.len()),
+		Err(MemoryExceeded { limit: 32 << 20 })
+	);
+
+	let dir = env::temp_dir().join(format!("amadeus-checkpoint-{}", process::id()));
+	let job = format!("sum-{}", pool.processes());
+	let checkpoint = Checkpoint::new(&dir, &job).unwrap();
+	checkpoint.clear().unwrap();
+	let sum = |may_run: bool, factor: u64| {
+		(0..1000_u64)
+			.dist()
+			.map(FnMut!(move |i: u64| -> u64 {
+				assert!(may_run, "this shouldn't run");
+				i * factor
+			}))
+			.checkpointed(
+				pool,
+				&checkpoint,
+				DistributedPipe::<u64>::sum::<u64>(Identity),
+			)
+	};
+	assert_eq!(sum(true, 1).await, 499_500);
+	// everything is already done, so nothing should be rerun
+	assert_eq!(sum(false, 1).await, 499_500);
+	// rerun just the batches whose checkpoints are lost, the first and the last. Doubling each item
+	// on the rerun shows which tasks ran, as only theirs are counted twice
+	let mut batches = fs::read_dir(dir.join(&job))
+		.unwrap()
+		.map(|entry| entry.unwrap().path())
+		.filter(|path| path.extension() == Some("bin".as_ref()))
+		.map(|path| {
+			path.file_stem()
+				.unwrap()
+				.to_str()
+				.unwrap()
+				.parse::<usize>()
+				.unwrap()
+		})
+		.collect::<Vec<_>>();
+	batches.sort_unstable();
+	let mut lost = vec![batches[0], *batches.last().unwrap()];
+	lost.dedup();
+	let mut lost_sum = 0;
+	for batch in lost {
+		let path = dir.join(&job).join(format!("{}.bin", batch));
+		let (_, state): (Vec<usize>, u64) =
+			bincode::deserialize(&fs::read(&path).unwrap()).unwrap();
+		lost_sum += state;
+		fs::remove_file(path).unwrap();
+	}
+	assert_eq!(sum(true, 2).await, 499_500 + lost_sum);
+	// a different job id doesn't use the saved results
+	let other = Checkpoint::new(&dir, &format!("{}-other", job)).unwrap();
+	other.clear().unwrap();
+	let res = (0..1000_u64)
+		.dist()
+		.checkpointed(pool, &other, DistributedPipe::<u64>::sum::<u64>(Identity))
+		.await;
+	assert_eq!(res, 499_500);
+	fs::remove_dir_all(dir).unwrap();
+
 	start.elapsed().unwrap()
 }
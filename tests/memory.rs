@@ -0,0 +1,58 @@
+use std::{
+	sync::{
+		atomic::{AtomicUsize, Ordering}, Arc
+	}, thread, time::Duration
+};
+
+use amadeus::{
+	par_stream::{MemoryBudget, MemoryExceeded}, prelude::*
+};
+
+// Budgets are of the growth in the whole process's resident memory, so these run in a test binary
+// of their own, one after another, with nothing else allocating alongside them.
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn memory_bounded() {
+	let pool = &ThreadPool::new(None).unwrap();
+
+	let budget = MemoryBudget::new(1 << 30);
+	let res = (0..1_000_u64)
+		.par()
+		.memory_bounded(pool, &budget, 1024, Identity.collect::<Vec<_>>())
+		.await;
+	assert_eq!(res.map(|items| items.len()), Ok(1_000));
+	// only memory that's actually held counts, not every item that passes through
+	let budget = MemoryBudget::new(32 << 20);
+	let res = (0..1_000_000_u64)
+		.par()
+		.memory_bounded(pool, &budget, 0, Identity.count())
+		.await;
+	assert_eq!(res, Ok(1_000_000));
+	// reducer state counts, so collecting more than the budget fails rather than growing on
+	let budget = MemoryBudget::new(32 << 20);
+	let res = (0..1_024_u64)
+		.par()
+		.map(|_| vec![1_u8; 1 << 20])
+		.memory_bounded(pool, &budget, 0, Identity.collect::<Vec<_>>())
+		.await;
+	assert_eq!(
+		res.map(|items| items.len()),
+		Err(MemoryExceeded { limit: 32 << 20 })
+	);
+	let (running, max_running) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+	let max_running_ = max_running.clone();
+	let budget = MemoryBudget::new(1 << 30);
+	let res = (0..100_u64)
+		.par()
+		.map(move |i: u64| {
+			let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+			let _ = max_running_.fetch_max(now, Ordering::SeqCst);
+			thread::sleep(Duration::from_millis(1));
+			let _ = running.fetch_sub(1, Ordering::SeqCst);
+			i
+		})
+		.memory_bounded(pool, &budget, 1 << 29, Identity.sum::<u64>())
+		.await;
+	assert_eq!(res, Ok(4950));
+	assert!(max_running.load(Ordering::SeqCst) <= 2);
+}
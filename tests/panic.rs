@@ -1,7 +1,11 @@
 use futures::FutureExt;
-use std::{panic, panic::AssertUnwindSafe, time::SystemTime};
+use std::{
+	panic, panic::AssertUnwindSafe, sync::atomic::{AtomicUsize, Ordering}, thread, time::{Duration, SystemTime}
+};
 
-use amadeus::prelude::*;
+use amadeus::{
+	par_stream::{Metrics, TimedOut}, prelude::*
+};
 
 #[tokio::test(threaded_scheduler)]
 #[cfg_attr(miri, ignore)]
@@ -10,15 +14,112 @@ async fn panic() {
 
 	let pool = &ThreadPool::new(None).unwrap();
 
-	let res = AssertUnwindSafe((0i32..1_000).into_par_stream().for_each(pool, |i| {
-		if i == 500 {
-			panic!("this is intended to panic")
-		}
-	}))
+	let metrics = Metrics::new();
+	let res = AssertUnwindSafe((0i32..1_000).into_par_stream().monitor(&metrics).for_each(
+		pool,
+		|i| {
+			if i == 500 {
+				panic!("this is intended to panic")
+			}
+		},
+	))
 	.catch_unwind()
 	.await;
 
 	assert!(res.is_err());
+	assert_eq!(metrics.snapshot().failed, 1);
 
 	println!("in {:?}", start.elapsed().unwrap());
 }
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn retry() {
+	static FAILURES: AtomicUsize = AtomicUsize::new(0);
+
+	let pool = &ThreadPool::new(None).unwrap();
+
+	let flaky = |fails| {
+		move |i: u64| {
+			if i == 500 && FAILURES.fetch_add(1, Ordering::SeqCst) < fails {
+				panic!("this is intended to panic")
+			}
+			i
+		}
+	};
+
+	let sum: u64 = (0u64..1_000)
+		.into_par_stream()
+		.map(flaky(2))
+		.retry(2)
+		.sum(pool)
+		.await;
+	assert_eq!(sum, (0..1_000_u64).sum::<u64>());
+
+	FAILURES.store(0, Ordering::SeqCst);
+	let res = AssertUnwindSafe(
+		(0u64..1_000)
+			.into_par_stream()
+			.map(flaky(3))
+			.retry(2)
+			.sum::<_, u64>(pool),
+	)
+	.catch_unwind()
+	.await;
+	assert!(res.is_err());
+
+	// Tasks reading a source partition are named by it.
+	let res = AssertUnwindSafe(
+		vec!["a", "b", "c"]
+			.into_iter()
+			.par_partitions()
+			.map(|partition| {
+				if partition == "b" {
+					panic!("this is intended to panic")
+				}
+				partition.len()
+			})
+			.retry(0)
+			.sum::<_, usize>(pool),
+	)
+	.catch_unwind()
+	.await;
+	let err = res.unwrap_err();
+	let message = err.downcast_ref::<String>().unwrap();
+	assert!(message.contains(r#"partition "b" failed"#), "{}", message);
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn task_timeout() {
+	let pool = &ThreadPool::new(None).unwrap();
+
+	let timed_out = Err(TimedOut {
+		timeout: Duration::from_millis(5),
+	});
+	let res = (0u64..10)
+		.into_par_stream()
+		.map(|i| {
+			thread::sleep(Duration::from_millis(20));
+			i
+		})
+		.task_timeout(Duration::from_millis(5))
+		.collect::<_, Vec<_>>(pool)
+		.await;
+	assert!(res.contains(&timed_out));
+	// a task waiting on an item that never comes is woken to time out
+	let res = (0u64..10)
+		.into_par_stream()
+		.flat_map(|_| futures::stream::pending::<u64>())
+		.task_timeout(Duration::from_millis(5))
+		.collect::<_, Vec<_>>(pool)
+		.await;
+	assert!(!res.is_empty() && res.iter().all(|item| *item == timed_out));
+	let res = (0u64..10)
+		.into_par_stream()
+		.task_timeout(Duration::from_secs(10))
+		.map(Result::unwrap)
+		.sum::<_, u64>(pool)
+		.await;
+	assert_eq!(res, 45);
+}
@@ -13,6 +13,8 @@ fn main() {
 	}
 	#[cfg(feature = "constellation")]
 	init(Resources::default());
+	#[cfg(feature = "local-process")]
+	LocalProcessPool::init();
 
 	tokio::runtime::Builder::new()
 		.threaded_scheduler()
@@ -31,8 +33,56 @@ fn main() {
 			};
 			#[cfg(not(feature = "constellation"))]
 			let process_pool_time = "-";
+			#[cfg(feature = "local-process")]
+			let local_process_pool_time = {
+				let local_process_pool = LocalProcessPool::new(None, None).unwrap();
+				run(&local_process_pool).await
+			};
+			#[cfg(not(feature = "local-process"))]
+			let local_process_pool_time = "-";
+
+			#[cfg(feature = "local-process")]
+			{
+				// A worker process dying is respawned, and its work resent.
+				let local_process_pool = LocalProcessPool::new(Some(2), None).unwrap();
+				let marker = std::env::temp_dir()
+					.join(format!("amadeus-panic-dist-{}", std::process::id()))
+					.into_os_string()
+					.into_string()
+					.unwrap();
+				let res = local_process_pool
+					.spawn(FnOnce!(move |_: &_| async move {
+						if std::fs::OpenOptions::new()
+							.write(true)
+							.create_new(true)
+							.open(&marker)
+							.is_ok()
+						{
+							std::process::abort()
+						}
+						std::fs::remove_file(&marker).unwrap();
+						0_usize
+					}))
+					.await;
+				assert_eq!(res.unwrap(), 0);
+
+				// Work that kills every process it's sent to fails rather than the whole program.
+				let local_process_pool = LocalProcessPool::new(Some(1), None).unwrap();
+				let res = local_process_pool
+					.spawn(FnOnce!(|_: &_| async {
+						if true {
+							std::process::abort()
+						}
+						0_usize
+					}))
+					.await;
+				assert!(res.is_err());
+			}
 
-			println!("in {:?} {:?}", thread_pool_time, process_pool_time);
+			println!(
+				"in {:?} {:?} {:?}",
+				thread_pool_time, process_pool_time, local_process_pool_time
+			);
 		})
 }
 
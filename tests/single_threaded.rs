@@ -1,9 +1,11 @@
 #![allow(clippy::suspicious_map)]
 
 use futures::{future, future::BoxFuture, FutureExt};
-use std::{error::Error, future::Future, time::SystemTime};
+use std::{
+	error::Error, future::Future, panic::{self, AssertUnwindSafe}, time::SystemTime
+};
 
-use amadeus::prelude::*;
+use amadeus::{pool::DeterministicPool, prelude::*};
 use amadeus_core::pool::ThreadPool;
 
 #[test]
@@ -60,3 +62,63 @@ impl ThreadPool for LocalPool {
 		Box::pin(future::lazy(|_| work().now_or_never().unwrap()).map(Ok))
 	}
 }
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn deterministic() {
+	let start = SystemTime::now();
+
+	let run = || {
+		let pool = &DeterministicPool::new();
+
+		let (sample, _) = (0..100)
+			.map(|i| format!("string {}", i))
+			.par()
+			.fork(
+				pool,
+				Identity.sample_unstable(10),
+				(
+					Identity
+						.map(|row: &String| (row[..8].to_owned(), ()))
+						.group_by(Identity.count()),
+					Identity.count(),
+					Identity.for_each(|_: &_| ()),
+					Identity.map(|_: &_| ()).count(),
+				),
+			)
+			.now_or_never()
+			.unwrap();
+		sample.into_iter().collect::<Vec<_>>()
+	};
+	let sample = run();
+	assert_eq!(sample.len(), 10);
+	assert_eq!(sample, run());
+
+	println!("in {:?}", start.elapsed().unwrap());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn deterministic_dist() {
+	use amadeus::dist::prelude::{DistributedStream, FnMut};
+
+	let pool = &DeterministicPool::new();
+	let sum: u64 = (0..1000_u64)
+		.dist()
+		.map(FnMut!(|i: u64| i * 2))
+		.sum(pool)
+		.now_or_never()
+		.unwrap();
+	assert_eq!(sum, 999_000);
+	assert!(pool.spawned() > 0);
+
+	let pool = &DeterministicPool::with_panics(vec![0]);
+	let res = panic::catch_unwind(AssertUnwindSafe(|| {
+		(0..1000_u64)
+			.dist()
+			.sum::<_, u64>(pool)
+			.now_or_never()
+			.unwrap()
+	}));
+	assert!(res.is_err());
+}
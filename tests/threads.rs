@@ -1,7 +1,7 @@
 use futures::future::join_all;
 use rand::{rngs::SmallRng, Rng, SeedableRng};
 use std::{
-	convert::TryInto, time::{Duration, SystemTime}
+	convert::TryInto, env, time::{Duration, SystemTime}
 };
 use tokio::time::delay_for as sleep;
 
@@ -29,3 +29,55 @@ async fn threads() {
 
 	println!("in {:?}", start.elapsed().unwrap());
 }
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn config() {
+	let config = PoolConfig {
+		threads_per_process: Some(2),
+		tasks_per_core: Some(3),
+		..PoolConfig::default()
+	};
+	let pool = ThreadPool::with_config(&config).unwrap();
+	assert_eq!(pool.threads(), 6);
+	assert_eq!(
+		pool.config(),
+		PoolConfig {
+			processes: Some(1),
+			memory_per_process: None,
+			retries: None,
+			pin_threads: Some(false),
+			..config
+		}
+	);
+	assert_eq!(pool.spawn(|| async { 1 + 1 }).await.unwrap(), 2);
+
+	let pool = ThreadPool::new(None).unwrap();
+	assert!(pool.config().threads_per_process.unwrap() <= PoolConfig::available_cores());
+
+	let config = PoolConfig {
+		tasks_per_core: Some(0),
+		..PoolConfig::default()
+	};
+	assert!(ThreadPool::with_config(&config).is_err());
+
+	let config = PoolConfig {
+		threads_per_process: Some(2),
+		pin_threads: Some(true),
+		..PoolConfig::default()
+	};
+	let pool = ThreadPool::with_config(&config).unwrap();
+	assert_eq!(pool.config().pin_threads, Some(true));
+	assert_eq!(pool.spawn(|| async { 1 + 1 }).await.unwrap(), 2);
+
+	let lookup = |value: &'static str| {
+		move |name: &str| match name {
+			"AMADEUS_TASKS_PER_CORE" => Ok(value.to_owned()),
+			_ => Err(env::VarError::NotPresent),
+		}
+	};
+	assert!(PoolConfig::from_lookup(lookup("many")).is_err());
+	assert!(PoolConfig::from_lookup(lookup("0")).is_err());
+	let pool = ThreadPool::with_config(&PoolConfig::from_lookup(lookup("3")).unwrap()).unwrap();
+	assert_eq!(pool.config().tasks_per_core, Some(3));
+}
@@ -15,6 +15,8 @@ fn main() {
 	}
 	#[cfg(feature = "constellation")]
 	init(Resources::default());
+	#[cfg(feature = "local-process")]
+	LocalProcessPool::init();
 
 	tokio::runtime::Builder::new()
 		.threaded_scheduler()
@@ -33,8 +35,18 @@ fn main() {
 			};
 			#[cfg(not(feature = "constellation"))]
 			let process_pool_time = "-";
+			#[cfg(feature = "local-process")]
+			let local_process_pool_time = {
+				let local_process_pool = LocalProcessPool::new(None, None).unwrap();
+				run(&local_process_pool, 1000).await
+			};
+			#[cfg(not(feature = "local-process"))]
+			let local_process_pool_time = "-";
 
-			println!("in {:?} {:?}", thread_pool_time, process_pool_time);
+			println!(
+				"in {:?} {:?} {:?}",
+				thread_pool_time, process_pool_time, local_process_pool_time
+			);
 		})
 }
 
@@ -0,0 +1,27 @@
+//! Kept in its own binary, as setting environment variables isn't safe while other threads may be
+//! reading them.
+
+use std::env;
+
+use amadeus::dist::prelude::*;
+
+fn main() {
+	if cfg!(miri) {
+		return;
+	}
+
+	env::set_var("AMADEUS_TASKS_PER_CORE", "3");
+	assert_eq!(PoolConfig::from_env().unwrap().tasks_per_core, Some(3));
+	env::set_var("AMADEUS_TASKS_PER_CORE", "many");
+	assert!(PoolConfig::from_env().is_err());
+
+	// the environment is only read when asked for
+	tokio::runtime::Builder::new()
+		.threaded_scheduler()
+		.enable_all()
+		.build()
+		.unwrap()
+		.block_on(async {
+			assert!(ThreadPool::new(None).is_ok());
+		})
+}
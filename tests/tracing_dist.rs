@@ -0,0 +1,107 @@
+#[cfg(feature = "constellation")]
+use constellation::*;
+use std::{
+	collections::HashMap, sync::{
+		atomic::{AtomicU64, Ordering}, Arc, Mutex
+	}
+};
+use tracing::{
+	span, subscriber::{self, Subscriber}, Event, Metadata
+};
+
+use amadeus::dist::prelude::*;
+
+fn main() {
+	if cfg!(miri) {
+		return;
+	}
+	#[cfg(feature = "constellation")]
+	init(Resources::default());
+	#[cfg(feature = "local-process")]
+	LocalProcessPool::init();
+
+	let counter = Counter::default();
+	let counts = counter.counts.clone();
+	subscriber::set_global_default(counter).unwrap();
+
+	tokio::runtime::Builder::new()
+		.threaded_scheduler()
+		.enable_all()
+		.build()
+		.unwrap()
+		.block_on(async {
+			let thread_pool = ThreadPool::new(None).unwrap();
+			run(&thread_pool).await;
+			let count = |name: &str| counts.lock().unwrap().get(name).copied().unwrap_or(0);
+			assert!(count("job") > 0);
+			assert!(count("task") > 0);
+			assert_eq!(count("remote"), 0);
+
+			#[cfg(feature = "constellation")]
+			{
+				let process_pool = ProcessPool::new(None, None, Resources::default()).unwrap();
+				run(&process_pool).await;
+				assert!(count("remote") > 0);
+			}
+			#[cfg(feature = "local-process")]
+			{
+				counts.lock().unwrap().clear();
+				let local_process_pool = LocalProcessPool::new(None, None).unwrap();
+				run(&local_process_pool).await;
+				// each item logs an event in a worker, which is replayed here as it was logged
+				assert!(count("tracing_dist") >= 1000);
+				assert!(count("remote") >= 1000);
+				// as are the spans of the tasks, which only run in the workers
+				assert!(count("task") > 0);
+				assert!(count("process_task") > 0);
+			}
+		})
+}
+
+async fn run<P: amadeus_core::pool::ProcessPool>(pool: &P) {
+	let sum: u64 = (0..1000_u64)
+		.dist()
+		.map(FnMut!(|i: u64| {
+			tracing::info!(i, "mapped");
+			i
+		}))
+		.sum(pool)
+		.await;
+	assert_eq!(sum, 499_500);
+}
+
+/// Counts spans by name and events by target, and both as `remote` if they were replayed from a
+/// worker process.
+#[derive(Default)]
+struct Counter {
+	next_id: AtomicU64,
+	counts: Arc<Mutex<HashMap<&'static str, usize>>>,
+}
+impl Counter {
+	fn count(&self, key: &'static str) {
+		*self.counts.lock().unwrap().entry(key).or_default() += 1;
+	}
+	fn count_remote(&self, metadata: &Metadata<'_>) {
+		if metadata.fields().field("process").is_some() {
+			self.count("remote");
+		}
+	}
+}
+impl Subscriber for Counter {
+	fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+		true
+	}
+	fn new_span(&self, attrs: &span::Attributes<'_>) -> span::Id {
+		self.count(attrs.metadata().name());
+		self.count_remote(attrs.metadata());
+		span::Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+	}
+	fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+	fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+	fn event(&self, event: &Event<'_>) {
+		self.count(event.metadata().target());
+		self.count_remote(event.metadata());
+	}
+	fn enter(&self, _span: &span::Id) {}
+	fn exit(&self, _span: &span::Id) {}
+}
@@ -0,0 +1,117 @@
+#![warn(
+	missing_copy_implementations,
+	missing_debug_implementations,
+	// missing_docs,
+	trivial_numeric_casts,
+	unused_extern_crates,
+	unused_import_braces,
+	unused_qualifications,
+	unused_results,
+	// clippy::pedantic
+)] // from https://github.com/rust-unofficial/patterns/blob/master/anti_patterns/deny-warnings.md
+
+use std::{env, fs, process};
+
+use amadeus::{data::WarcRecord, par_stream::Metrics, prelude::*};
+
+fn record(type_: &str, id: usize, headers: &str, content: &str) -> String {
+	format!(
+		"WARC/1.0\r\nWARC-Type: {}\r\nWARC-Record-ID: <urn:uuid:{}>\r\nWARC-Date: 2020-05-25T03:15:21Z\r\n{}Content-Length: {}\r\n\r\n{}\r\n\r\n",
+		type_,
+		id,
+		headers,
+		content.len(),
+		content
+	)
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(miri, ignore)]
+async fn warc() {
+	let pool = &ThreadPool::new(None).unwrap();
+
+	let mut warc = record("warcinfo", 0, "", "software: amadeus\r\n");
+	for i in 0..10 {
+		let uri = format!("WARC-Target-URI: http://example.com/{}\r\n", i);
+		warc += &record(
+			"request",
+			3 * i + 1,
+			&format!("{}Content-Type: application/http; msgtype=request\r\n", uri),
+			"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n",
+		);
+		warc += &record(
+			"response",
+			3 * i + 2,
+			&format!(
+				"{}WARC-IP-Address: 127.0.0.1\r\nContent-Type: application/http; msgtype=response\r\n",
+				uri
+			),
+			"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\nhello",
+		);
+		warc += &record("metadata", 3 * i + 3, &uri, "fetchTimeMs: 1\r\n");
+	}
+	let dir = env::temp_dir().join(format!("amadeus-warc-{}", process::id()));
+	fs::create_dir_all(dir.join("crawl")).unwrap();
+	fs::write(dir.join("a.warc"), &warc).unwrap();
+	fs::write(dir.join("crawl/b.warc"), &warc).unwrap();
+	fs::write(dir.join("crawl/.c.warc"), &warc).unwrap();
+	fs::write(dir.join("README"), "").unwrap();
+
+	let records = Warc::new(dir.join("a.warc")).await.unwrap();
+	let metrics = Metrics::new();
+	let count = records
+		.par_stream()
+		.monitor(&metrics)
+		.map(|record: Result<WarcRecord, _>| record.unwrap())
+		.count(pool)
+		.await;
+	assert_eq!(count, 31);
+	assert_eq!(metrics.snapshot().bytes, warc.len() as u64);
+
+	let responses = Warc::new(dir.join("a.warc"))
+		.await
+		.unwrap()
+		.record_types(&[WarcRecordType::Response]);
+	let count = responses
+		.par_stream()
+		.map(|record: Result<WarcRecord, _>| record.unwrap())
+		.filter(|record: &WarcRecord| {
+			record.http_status == Some(200) && record.payload == List::<u8>::from(b"hello".to_vec())
+		})
+		.count(pool)
+		.await;
+	assert_eq!(count, 10);
+
+	let records = Warc::new(WarcDirectory::new(dir.clone())).await.unwrap();
+	let count = records
+		.par_stream()
+		.map(|record: Result<WarcRecord, _>| record.unwrap())
+		.count(pool)
+		.await;
+	assert_eq!(count, 62);
+
+	// records of unknown types or too big to buffer are errors rather than panics
+	let unknown = record("warcinfo", 0, "", "") + &record("future", 1, "", "");
+	let big = record("warcinfo", 0, "", "") + &record("resource", 1, "", &"a".repeat(5 << 20));
+	for (name, warc, error) in &[
+		("unknown.warc", unknown, "unknown WARC-Type \"future\""),
+		("big.warc", big, "larger than the 4194304 byte buffer"),
+	] {
+		fs::write(dir.join(name), warc).unwrap();
+		let records = Warc::new(dir.join(name)).await.unwrap();
+		let records = records
+			.par_stream()
+			.map(|record: Result<WarcRecord, _>| {
+				record
+					.map(|record| record.record_type)
+					.map_err(|err| err.to_string())
+			})
+			.collect::<_, Vec<_>>(pool)
+			.await;
+		assert_eq!(records.len(), 2);
+		assert_eq!(records[0], Ok(String::from("warcinfo")));
+		assert!(records[1].as_ref().unwrap_err().contains(error));
+	}
+
+	fs::remove_dir_all(dir).unwrap();
+}